@@ -352,7 +352,9 @@ where
 
     /// Fetch the metadata for this extrinsic.
     pub fn extrinsic_metadata(&self) -> Result<ExtrinsicMetadataDetails, Error> {
-        let pallet = self.metadata.pallet_by_index_err(self.pallet_index())?;
+        let pallet = self
+            .metadata
+            .pallet_by_call_index_err(self.pallet_index())?;
         let variant = pallet
             .call_variant_by_index(self.variant_index())
             .ok_or_else(|| MetadataError::VariantIndexNotFound(self.variant_index()))?;
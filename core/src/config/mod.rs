@@ -126,6 +126,18 @@ pub trait Header: Sized + Encode + Decode {
     fn hash(&self) -> <Self::Hasher as Hasher>::Output {
         Self::Hasher::hash_of(self)
     }
+
+    /// Return the root of the state trie that this header commits to, if known. This
+    /// is used to verify storage proofs, and returns `None` unless implemented.
+    fn state_root(&self) -> Option<<Self::Hasher as Hasher>::Output> {
+        None
+    }
+
+    /// Return the hash of the parent block, if known. This is used to find the runtime
+    /// that a block was produced with, and returns `None` unless implemented.
+    fn parent_hash(&self) -> Option<<Self::Hasher as Hasher>::Output> {
+        None
+    }
 }
 
 cfg_substrate_compat! {
@@ -143,6 +155,14 @@ cfg_substrate_compat! {
             fn number(&self) -> Self::Number {
                 *self.number()
             }
+
+            fn state_root(&self) -> Option<<Self::Hasher as Hasher>::Output> {
+                Some(*<T as sp_runtime::traits::Header>::state_root(self))
+            }
+
+            fn parent_hash(&self) -> Option<<Self::Hasher as Hasher>::Output> {
+                Some(*<T as sp_runtime::traits::Header>::parent_hash(self))
+            }
         }
 
         impl<T: sp_runtime::traits::Hash> Hasher for T {
@@ -45,6 +45,12 @@ pub trait RefineParams<T: Config> {
     /// Refine params to an extrinsic. There is usually some notion of 'the param is already set/unset' in types implementing this trait.
     /// The refinement should most likely not affect cases where a param is in a 'is already set by the user' state.
     fn refine(&mut self, _data: &RefineParamsData<T>) {}
+
+    /// The account nonce which has already been set in these params, if any. Refining the
+    /// params is not expected to change the account nonce when one is returned here.
+    fn account_nonce(&self) -> Option<u64> {
+        None
+    }
 }
 
 impl<T: Config> RefineParams<T> for () {}
@@ -57,6 +63,10 @@ macro_rules! impl_tuples {
                 $(self.$index.refine(data);)+
             }
 
+            fn account_nonce(&self) -> Option<u64> {
+                None$(.or_else(|| self.$index.account_nonce()))+
+            }
+
         }
     }
 }
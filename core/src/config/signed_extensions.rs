@@ -100,6 +100,10 @@ impl<T: Config> RefineParams<T> for CheckNonceParams {
             self.0 = Some(data.account_nonce());
         }
     }
+
+    fn account_nonce(&self) -> Option<u64> {
+        self.0
+    }
 }
 
 /// The [`CheckTxVersion`] signed extension.
@@ -77,6 +77,7 @@ impl<N, H> Header for SubstrateHeader<N, H>
 where
     N: Copy + Into<u64> + Into<U256> + TryFrom<U256> + Encode,
     H: Hasher + Encode,
+    H::Output: Clone,
     SubstrateHeader<N, H>: Encode + Decode,
 {
     type Number = N;
@@ -84,6 +85,12 @@ where
     fn number(&self) -> Self::Number {
         self.number
     }
+    fn state_root(&self) -> Option<H::Output> {
+        Some(self.state_root.clone())
+    }
+    fn parent_hash(&self) -> Option<H::Output> {
+        Some(self.parent_hash.clone())
+    }
 }
 
 /// Generic header digest. From `sp_runtime::generic::digest`.
@@ -33,6 +33,9 @@ pub enum Error {
     /// Block body error.
     #[display(fmt = "Error working with block body: {_0}")]
     Block(BlockError),
+    /// Storage proof error.
+    #[display(fmt = "Storage proof error: {_0}")]
+    StorageProof(StorageProofError),
 }
 
 #[cfg(feature = "std")]
@@ -89,6 +92,14 @@ pub enum MetadataError {
     /// Call not found.
     #[display(fmt = "Call with name {_0} not found")]
     CallNameNotFound(String),
+    /// Call argument not found.
+    #[display(fmt = "Call {call} has no argument named {argument}")]
+    CallArgumentNotFound {
+        /// The name of the call.
+        call: String,
+        /// The name of the argument.
+        argument: String,
+    },
     /// Runtime trait not found.
     #[display(fmt = "Runtime trait with name {_0} not found")]
     RuntimeTraitNotFound(String),
@@ -164,6 +175,31 @@ pub enum StorageAddressError {
 #[cfg(feature = "std")]
 impl std::error::Error for StorageAddressError {}
 
+/// Something went wrong trying to verify a storage value against a storage proof.
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StorageProofError {
+    /// A trie node needed to look up the key was not included in the proof.
+    #[display(
+        fmt = "The storage proof is incomplete; a trie node needed to verify the value is missing"
+    )]
+    IncompleteProof,
+    /// A trie node in the proof could not be decoded.
+    #[display(fmt = "Cannot decode trie node in storage proof: {_0}")]
+    InvalidNode(codec::Error),
+    /// The header does not expose a state root to verify the proof against.
+    #[display(
+        fmt = "The block header does not expose a state root to verify storage proofs against"
+    )]
+    StateRootNotAvailable,
+    /// The header we were given does not hash to the block hash we expected.
+    #[display(fmt = "The block header does not match the expected block hash")]
+    HeaderHashMismatch,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StorageProofError {}
+
 /// An error that can be emitted when trying to construct an instance of [`crate::config::ExtrinsicParams`],
 /// encode data from the instance, or match on signed extensions.
 #[derive(Display, Debug)]
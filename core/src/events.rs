@@ -257,7 +257,7 @@ impl<T: Config> EventDetails<T> {
         let event_fields_start_idx = all_bytes.len() - input.len();
 
         // Get metadata for the event:
-        let event_pallet = metadata.pallet_by_index_err(pallet_index)?;
+        let event_pallet = metadata.pallet_by_event_index_err(pallet_index)?;
         let event_variant = event_pallet
             .event_variant_by_index(variant_index)
             .ok_or(MetadataError::VariantIndexNotFound(variant_index))?;
@@ -340,7 +340,7 @@ impl<T: Config> EventDetails<T> {
     pub fn event_metadata(&self) -> EventMetadataDetails {
         let pallet = self
             .metadata
-            .pallet_by_index(self.pallet_index())
+            .pallet_by_event_index(self.pallet_index())
             .expect("event pallet to be found; we did this already during decoding");
         let variant = pallet
             .event_variant_by_index(self.variant_index())
@@ -41,6 +41,7 @@
 //! println!("Alice's account info: {value:?}");
 //! ```
 
+mod proof;
 mod storage_key;
 mod utils;
 
@@ -54,6 +55,8 @@ use alloc::vec::Vec;
 #[doc(hidden)]
 pub use utils::lookup_storage_entry_details;
 
+pub use proof::StorageProof;
+
 /// When the provided `address` is statically generated via the `#[subxt]` macro, this validates
 /// that the shape of the storage value is the same as the shape expected by the static address.
 ///
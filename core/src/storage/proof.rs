@@ -0,0 +1,391 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Verify storage values against a state root using the trie nodes returned from
+//! calls like `state_getReadProof`. This understands the trie node encoding used by
+//! Substrate (both state versions 0 and 1).
+
+use crate::config::Hasher;
+use crate::error::StorageProofError;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use codec::{Compact, Decode};
+
+// Node header prefixes, as used by the Substrate trie node codec.
+const EMPTY_TRIE: u8 = 0;
+const LEAF_PREFIX_MASK: u8 = 0b_01 << 6;
+const BRANCH_WITHOUT_MASK: u8 = 0b_10 << 6;
+const BRANCH_WITH_MASK: u8 = 0b_11 << 6;
+const ALT_HASHING_LEAF_PREFIX_MASK: u8 = 0b_001 << 5;
+const ALT_HASHING_BRANCH_WITH_MASK: u8 = 0b_0001 << 4;
+
+/// A set of trie nodes which prove the existence (or absence) of some storage values
+/// under a given state root. Any value obtained via [`StorageProof::verify_value()`] is
+/// guaranteed to be the value stored under the state root that the proof was created with.
+#[derive(Clone, Debug)]
+pub struct StorageProof {
+    state_root: Vec<u8>,
+    nodes: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl StorageProof {
+    /// Construct a [`StorageProof`] from a state root and the encoded trie nodes making up
+    /// the proof. `H` is the hasher used to build the trie (ie the hasher used in the block header).
+    pub fn new<H>(state_root: H::Output, proof: impl IntoIterator<Item = Vec<u8>>) -> Self
+    where
+        H: Hasher,
+        H::Output: AsRef<[u8]>,
+    {
+        let nodes = proof
+            .into_iter()
+            .map(|node| (H::hash(&node).as_ref().to_vec(), node))
+            .collect();
+
+        StorageProof {
+            state_root: state_root.as_ref().to_vec(),
+            nodes,
+        }
+    }
+
+    /// Look up the value stored at the given key. This returns `Ok(None)` if the proof shows that
+    /// no value exists at the key, and an error if the proof does not contain enough information
+    /// to say either way.
+    pub fn verify_value(&self, key: &[u8]) -> Result<Option<&[u8]>, StorageProofError> {
+        let hash_len = self.state_root.len();
+        let key = NibbleSlice::new(key);
+        let mut key_pos = 0;
+        let mut node_data = self.node(&self.state_root)?;
+
+        loop {
+            let node = Node::decode(node_data, hash_len).map_err(StorageProofError::InvalidNode)?;
+
+            match node {
+                Node::Empty => return Ok(None),
+                Node::Leaf { partial, value } => {
+                    if key.len() - key_pos != partial.len()
+                        || !key.starts_with_at(key_pos, &partial)
+                    {
+                        return Ok(None);
+                    }
+                    return self.value(value).map(Some);
+                }
+                Node::Branch {
+                    partial,
+                    value,
+                    children,
+                } => {
+                    if key.len() - key_pos < partial.len() || !key.starts_with_at(key_pos, &partial)
+                    {
+                        return Ok(None);
+                    }
+                    key_pos += partial.len();
+
+                    // We've consumed the whole key, so the value (if any) lives in this branch.
+                    if key_pos == key.len() {
+                        return value.map(|v| self.value(v)).transpose();
+                    }
+
+                    let child = children[key.at(key_pos) as usize];
+                    key_pos += 1;
+
+                    node_data = match child {
+                        None => return Ok(None),
+                        Some(NodeRef::Inline(data)) => data,
+                        Some(NodeRef::Hash(hash)) => self.node(hash)?,
+                    };
+                }
+            }
+        }
+    }
+
+    fn node(&self, hash: &[u8]) -> Result<&[u8], StorageProofError> {
+        self.nodes
+            .get(hash)
+            .map(|n| &**n)
+            .ok_or(StorageProofError::IncompleteProof)
+    }
+
+    fn value<'a>(&'a self, value: Value<'a>) -> Result<&'a [u8], StorageProofError> {
+        match value {
+            Value::Inline(data) => Ok(data),
+            // In state version 1, larger values are stored outside of the trie node
+            // and are provided in the proof alongside the nodes:
+            Value::Hashed(hash) => self.node(hash),
+        }
+    }
+}
+
+/// A decoded trie node, borrowing from the encoded bytes.
+// Nodes are only ever decoded one at a time on the stack, so the size difference is fine.
+#[allow(clippy::large_enum_variant)]
+enum Node<'a> {
+    Empty,
+    Leaf {
+        partial: NibbleSlice<'a>,
+        value: Value<'a>,
+    },
+    Branch {
+        partial: NibbleSlice<'a>,
+        value: Option<Value<'a>>,
+        children: [Option<NodeRef<'a>>; 16],
+    },
+}
+
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Inline(&'a [u8]),
+    Hashed(&'a [u8]),
+}
+
+#[derive(Clone, Copy)]
+enum NodeRef<'a> {
+    Inline(&'a [u8]),
+    Hash(&'a [u8]),
+}
+
+impl<'a> Node<'a> {
+    fn decode(mut data: &'a [u8], hash_len: usize) -> Result<Self, codec::Error> {
+        let input = &mut data;
+        let first = u8::decode(input)?;
+
+        if first == EMPTY_TRIE {
+            return Ok(Node::Empty);
+        }
+
+        // Work out what sort of node we have, and how many nibbles long its partial key is.
+        let (kind, nibble_count) = match first & (0b11 << 6) {
+            LEAF_PREFIX_MASK => (NodeKind::Leaf, decode_size(first, input, 2)?),
+            BRANCH_WITH_MASK => (NodeKind::BranchWithValue, decode_size(first, input, 2)?),
+            BRANCH_WITHOUT_MASK => (NodeKind::Branch, decode_size(first, input, 2)?),
+            _ if first & (0b111 << 5) == ALT_HASHING_LEAF_PREFIX_MASK => {
+                (NodeKind::HashedValueLeaf, decode_size(first, input, 3)?)
+            }
+            _ if first & (0b1111 << 4) == ALT_HASHING_BRANCH_WITH_MASK => {
+                (NodeKind::HashedValueBranch, decode_size(first, input, 4)?)
+            }
+            _ => return Err("Unexpected trie node header".into()),
+        };
+
+        // An odd number of nibbles is padded at the front with a 0 nibble.
+        let padding = nibble_count % 2 != 0;
+        let partial_bytes = take(input, nibble_count.div_ceil(2))?;
+        if padding && partial_bytes[0] & 0xf0 != 0 {
+            return Err("Bad trie node partial key padding".into());
+        }
+        let partial = NibbleSlice {
+            data: partial_bytes,
+            offset: padding as usize,
+            len: nibble_count,
+        };
+
+        match kind {
+            NodeKind::Leaf => {
+                let value = Value::Inline(decode_inline(input)?);
+                Ok(Node::Leaf { partial, value })
+            }
+            NodeKind::HashedValueLeaf => {
+                let value = Value::Hashed(take(input, hash_len)?);
+                Ok(Node::Leaf { partial, value })
+            }
+            NodeKind::Branch | NodeKind::BranchWithValue | NodeKind::HashedValueBranch => {
+                let bitmap = u16::decode(input)?;
+                let value = match kind {
+                    NodeKind::BranchWithValue => Some(Value::Inline(decode_inline(input)?)),
+                    NodeKind::HashedValueBranch => Some(Value::Hashed(take(input, hash_len)?)),
+                    _ => None,
+                };
+
+                let mut children = [None; 16];
+                for (i, child) in children.iter_mut().enumerate() {
+                    if bitmap & (1 << i) == 0 {
+                        continue;
+                    }
+                    let data = decode_inline(input)?;
+                    *child = if data.len() == hash_len {
+                        Some(NodeRef::Hash(data))
+                    } else if data.len() < hash_len {
+                        Some(NodeRef::Inline(data))
+                    } else {
+                        return Err("Inline trie node is too large".into());
+                    };
+                }
+
+                Ok(Node::Branch {
+                    partial,
+                    value,
+                    children,
+                })
+            }
+        }
+    }
+}
+
+enum NodeKind {
+    Leaf,
+    HashedValueLeaf,
+    Branch,
+    BranchWithValue,
+    HashedValueBranch,
+}
+
+/// Decode the nibble count from a node header. Sizes which don't fit into the
+/// remaining bits of the first byte spill over into subsequent bytes.
+fn decode_size(first: u8, input: &mut &[u8], prefix_bits: usize) -> Result<usize, codec::Error> {
+    let max_value = 255u8 >> prefix_bits;
+    let mut result = (first & max_value) as usize;
+    if result < max_value as usize {
+        return Ok(result);
+    }
+    result -= 1;
+    loop {
+        let n = u8::decode(input)? as usize;
+        if n < 255 {
+            return Ok(result + n + 1);
+        }
+        result += 255;
+    }
+}
+
+/// Decode some compact-length-prefixed bytes.
+fn decode_inline<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], codec::Error> {
+    let len = Compact::<u32>::decode(input)?.0 as usize;
+    take(input, len)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], codec::Error> {
+    if input.len() < len {
+        return Err("Not enough bytes to decode trie node".into());
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Ok(taken)
+}
+
+/// A view over some bytes as a sequence of nibbles.
+struct NibbleSlice<'a> {
+    data: &'a [u8],
+    offset: usize,
+    len: usize,
+}
+
+impl<'a> NibbleSlice<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        NibbleSlice {
+            data,
+            offset: 0,
+            len: data.len() * 2,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn at(&self, idx: usize) -> u8 {
+        let idx = self.offset + idx;
+        let byte = self.data[idx / 2];
+        if idx % 2 == 0 {
+            byte >> 4
+        } else {
+            byte & 0x0f
+        }
+    }
+
+    /// Do the nibbles starting at `pos` begin with all of the nibbles in `other`?
+    fn starts_with_at(&self, pos: usize, other: &NibbleSlice<'_>) -> bool {
+        self.len - pos >= other.len && (0..other.len).all(|i| self.at(pos + i) == other.at(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::substrate::BlakeTwo256;
+    use alloc::vec;
+    use sp_trie::{LayoutV0, LayoutV1, MemoryDB, TrieDBMutBuilder, TrieLayout, TrieMut};
+
+    type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn entries() -> Entries {
+        vec![
+            (b"alpha".to_vec(), b"one".to_vec()),
+            (b"alphabet".to_vec(), b"two".to_vec()),
+            (b"alpine".to_vec(), vec![3; 64]),
+            (b"beta".to_vec(), vec![4; 100]),
+            (vec![0; 32], b"zero".to_vec()),
+            (vec![0xff; 48], vec![]),
+        ]
+    }
+
+    // Build a trie from the given entries and return the root plus every node in the
+    // trie; a superset of the nodes is a valid proof for any key.
+    fn build_proof<L: TrieLayout<Hash = sp_core::Blake2Hasher>>(entries: &Entries) -> StorageProof {
+        let mut db = MemoryDB::<sp_core::Blake2Hasher>::default();
+        let mut root = Default::default();
+        {
+            let mut trie = TrieDBMutBuilder::<L>::new(&mut db, &mut root).build();
+            for (k, v) in entries {
+                trie.insert(k, v).unwrap();
+            }
+        }
+        let nodes = db.drain().into_values().map(|(node, _rc)| node);
+        StorageProof::new::<BlakeTwo256>(root, nodes)
+    }
+
+    fn check_entries(proof: &StorageProof, entries: &Entries) {
+        for (k, v) in entries {
+            assert_eq!(proof.verify_value(k).unwrap(), Some(&**v));
+        }
+        for missing in [&b"alp"[..], b"alphabets", b"gamma", &[0; 31], &[]] {
+            assert_eq!(proof.verify_value(missing).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn verifies_values_state_version_0() {
+        let entries = entries();
+        let proof = build_proof::<LayoutV0<sp_core::Blake2Hasher>>(&entries);
+        check_entries(&proof, &entries);
+    }
+
+    #[test]
+    fn verifies_values_state_version_1() {
+        let entries = entries();
+        let proof = build_proof::<LayoutV1<sp_core::Blake2Hasher>>(&entries);
+        check_entries(&proof, &entries);
+    }
+
+    #[test]
+    fn missing_nodes_are_an_error() {
+        let entries = entries();
+        let full = build_proof::<LayoutV1<sp_core::Blake2Hasher>>(&entries);
+
+        // Keep only the root node; anything deeper can no longer be verified.
+        let root_node = full.nodes.get(&full.state_root).unwrap().clone();
+        let proof = StorageProof {
+            state_root: full.state_root.clone(),
+            nodes: BTreeMap::from([(full.state_root.clone(), root_node)]),
+        };
+
+        assert_eq!(
+            proof.verify_value(b"alpha"),
+            Err(StorageProofError::IncompleteProof)
+        );
+    }
+
+    #[test]
+    fn wrong_root_is_an_error() {
+        let entries = entries();
+        let full = build_proof::<LayoutV1<sp_core::Blake2Hasher>>(&entries);
+        let proof = StorageProof {
+            state_root: vec![1; 32],
+            nodes: full.nodes,
+        };
+
+        assert_eq!(
+            proof.verify_value(b"alpha"),
+            Err(StorageProofError::IncompleteProof)
+        );
+    }
+}
@@ -153,6 +153,30 @@ pub fn create_partial_signed<T: Config, Call: Payload>(
     })
 }
 
+/// Create a partial extrinsic to be signed by the given signer, which is given the chance to
+/// wrap the call data first (see [`SignerT::wrap_call_data()`]). Partial extrinsics which will be
+/// signed by a signer that wraps call data, such as a [`signer::ProxySigner`], must be created
+/// this way rather than via [`create_partial_signed()`].
+///
+/// Note: if not provided, the default account nonce will be set to 0 and the default mortality will be _immortal_.
+/// This is because this method runs offline, and so is unable to fetch the data needed for more appropriate values.
+pub fn create_partial_signed_for<T, Call, Signer>(
+    call: &Call,
+    client_state: &ClientState<T>,
+    signer: &Signer,
+    params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+) -> Result<PartialTransaction<T>, Error>
+where
+    T: Config,
+    Call: Payload,
+    Signer: SignerT<T>,
+{
+    let mut partial_signed = create_partial_signed(call, client_state, params)?;
+    partial_signed.call_data =
+        signer.wrap_call_data(partial_signed.call_data, &client_state.metadata)?;
+    Ok(partial_signed)
+}
+
 /// Creates a signed extrinsic without submitting it.
 ///
 /// Note: if not provided, the default account nonce will be set to 0 and the default mortality will be _immortal_.
@@ -173,8 +197,8 @@ where
     validate(call, &client_state.metadata)?;
 
     // 2. Gather the "additional" and "extra" params along with the encoded call data,
-    //    ready to be signed.
-    let partial_signed = create_partial_signed(call, client_state, params)?;
+    //    ready to be signed. The signer may wrap the call data, for instance to proxy it.
+    let partial_signed = create_partial_signed_for(call, client_state, signer, params)?;
 
     // 3. Sign and construct an extrinsic from these details.
     Ok(partial_signed.sign(signer))
@@ -224,6 +248,10 @@ impl<T: Config> PartialTransaction<T> {
     /// Convert this [`PartialTransaction`] into a [`Transaction`], ready to submit.
     /// The provided `signer` is responsible for providing the "from" address for the transaction,
     /// as well as providing a signature to attach to it.
+    ///
+    /// The call data is not handed to the signer to wrap here, so if the signer wraps call data
+    /// (for instance a [`signer::ProxySigner`]), this [`PartialTransaction`] should have been
+    /// created via [`create_partial_signed_for()`].
     pub fn sign<Signer>(&self, signer: &Signer) -> Transaction<T>
     where
         Signer: SignerT<T>,
@@ -6,7 +6,7 @@
 //! transactions that can be submitted.
 
 use crate::error::MetadataError;
-use crate::metadata::Metadata;
+use crate::metadata::{DecodeWithMetadata, Metadata};
 use crate::Error;
 use alloc::borrow::{Cow, ToOwned};
 use alloc::string::String;
@@ -143,7 +143,7 @@ impl<CallData: EncodeAsFields> Payload for DefaultPayload<CallData> {
             .call_variant_by_name(&self.call_name)
             .ok_or_else(|| MetadataError::CallNameNotFound((*self.call_name).to_owned()))?;
 
-        let pallet_index = pallet.index();
+        let pallet_index = pallet.call_index();
         let call_index = call.index;
 
         pallet_index.encode_to(out);
@@ -178,3 +178,44 @@ pub fn dynamic(
 ) -> DynamicPayload {
     DefaultPayload::new(pallet_name, call_name, call_data.into())
 }
+
+/// Decode some SCALE encoded call data into a [`Value`], so that it can be given as the
+/// argument of another call which dispatches it, such as `Proxy::proxy`.
+pub fn call_data_to_value(call_data: &[u8], metadata: &Metadata) -> Result<Value<()>, Error> {
+    let call_ty = metadata.outer_enums().call_enum_ty();
+    Ok(Value::decode_with_metadata(
+        &mut &*call_data,
+        call_ty,
+        metadata,
+    )?)
+}
+
+/// Encode some argument for the given call, and decode it into a [`Value`] using the type
+/// that the call expects for it. This is useful for chain specific types such as account IDs
+/// and addresses, which can't otherwise be turned into a [`Value`] of the right shape.
+pub fn call_argument_to_value(
+    pallet_name: &str,
+    call_name: &str,
+    argument_name: &str,
+    argument: &impl Encode,
+    metadata: &Metadata,
+) -> Result<Value<()>, Error> {
+    let argument_ty = metadata
+        .pallet_by_name_err(pallet_name)?
+        .call_variant_by_name(call_name)
+        .ok_or_else(|| MetadataError::CallNameNotFound(call_name.to_owned()))?
+        .fields
+        .iter()
+        .find(|field| field.name.as_deref() == Some(argument_name))
+        .ok_or_else(|| MetadataError::CallArgumentNotFound {
+            call: call_name.to_owned(),
+            argument: argument_name.to_owned(),
+        })?
+        .ty
+        .id;
+    Ok(Value::decode_with_metadata(
+        &mut &*argument.encode(),
+        argument_ty,
+        metadata,
+    )?)
+}
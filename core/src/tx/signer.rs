@@ -6,7 +6,11 @@
 //! [substrate](https://github.com/paritytech/substrate) node via RPC.
 
 use crate::macros::cfg_substrate_compat;
-use crate::Config;
+use crate::metadata::Metadata;
+use crate::{Config, Error};
+use alloc::vec::Vec;
+
+pub use proxy_signer::ProxySigner;
 
 /// Signing transactions requires a [`Signer`]. This is responsible for
 /// providing the "from" account that the transaction is being signed by,
@@ -23,6 +27,123 @@ pub trait Signer<T: Config> {
     /// Some signers may fail, for instance because the hardware on which the keys are located has
     /// refused the operation.
     fn sign(&self, signer_payload: &[u8]) -> T::Signature;
+
+    /// Takes the SCALE encoded call data of a transaction that is about to be signed, and
+    /// returns the call data that should be signed and submitted in its place.
+    ///
+    /// Signers which act on behalf of some other account (see [`ProxySigner`]) can use this to
+    /// wrap each call accordingly. By default, the call data is returned unchanged.
+    fn wrap_call_data(&self, call_data: Vec<u8>, _metadata: &Metadata) -> Result<Vec<u8>, Error> {
+        Ok(call_data)
+    }
+}
+
+// A signer which dispatches calls on behalf of another account via `pallet_proxy`.
+mod proxy_signer {
+    use super::Signer;
+    use crate::metadata::Metadata;
+    use crate::tx::payload::{call_argument_to_value, call_data_to_value, dynamic, Payload};
+    use crate::{Config, Error};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use derive_where::derive_where;
+    use scale_value::{Composite, Value};
+
+    /// A [`Signer`] which signs transactions using some other [`Signer`], but dispatches each
+    /// call on behalf of a "real" account by wrapping it in a `Proxy::proxy` call. The account
+    /// of the inner signer must be registered as a proxy of the real account.
+    ///
+    /// The call is wrapped when a transaction is created via one of the methods which take a
+    /// signer, such as [`crate::tx::create_signed()`] or [`crate::tx::create_partial_signed_for()`].
+    /// Partial transactions created without the signer, such as via
+    /// [`crate::tx::create_partial_signed()`], are not wrapped.
+    ///
+    /// The inner signer must be a [`Signer`]. Signers which sign asynchronously (such as
+    /// `subxt::tx::AsyncSigner` implementations) can't be wrapped in a [`ProxySigner`], and
+    /// should wrap each call in `Proxy::proxy` themselves via their `wrap_call_data` method.
+    #[derive_where(Clone, Debug; S)]
+    pub struct ProxySigner<T: Config, S> {
+        signer: S,
+        real: T::AccountId,
+        force_proxy_type: Option<String>,
+    }
+
+    impl<T: Config, S: Signer<T>> ProxySigner<T, S> {
+        /// Create a new [`ProxySigner`], which signs transactions with the given signer, and
+        /// dispatches calls on behalf of the given real account.
+        pub fn new(signer: S, real: T::AccountId) -> Self {
+            Self {
+                signer,
+                real,
+                force_proxy_type: None,
+            }
+        }
+
+        /// Only dispatch calls via a proxy of the given type, for instance `"Staking"`. This is
+        /// the name of a variant of the runtime's `ProxyType` enum. If this isn't given, any
+        /// proxy type which permits the call can be used.
+        pub fn force_proxy_type(mut self, proxy_type: impl Into<String>) -> Self {
+            self.force_proxy_type = Some(proxy_type.into());
+            self
+        }
+
+        /// The account that calls are dispatched on behalf of.
+        pub fn real(&self) -> &T::AccountId {
+            &self.real
+        }
+
+        /// The signer used to sign transactions.
+        pub fn signer(&self) -> &S {
+            &self.signer
+        }
+    }
+
+    impl<T: Config, S: Signer<T>> Signer<T> for ProxySigner<T, S> {
+        fn account_id(&self) -> T::AccountId {
+            self.signer.account_id()
+        }
+
+        fn address(&self) -> T::Address {
+            self.signer.address()
+        }
+
+        fn sign(&self, signer_payload: &[u8]) -> T::Signature {
+            self.signer.sign(signer_payload)
+        }
+
+        fn wrap_call_data(
+            &self,
+            call_data: Vec<u8>,
+            metadata: &Metadata,
+        ) -> Result<Vec<u8>, Error> {
+            let call = call_data_to_value(&call_data, metadata)?;
+
+            // The real account is given as a lookup source (ie the chain's `Address`).
+            let real = T::Address::from(self.real.clone());
+            let real = call_argument_to_value("Proxy", "proxy", "real", &real, metadata)?;
+
+            let force_proxy_type = match &self.force_proxy_type {
+                Some(proxy_type) => {
+                    Value::unnamed_variant("Some", [Value::unnamed_variant(proxy_type, [])])
+                }
+                None => Value::unnamed_variant("None", []),
+            };
+
+            let proxy_call = dynamic(
+                "Proxy",
+                "proxy",
+                Composite::named([
+                    ("real", real),
+                    ("force_proxy_type", force_proxy_type),
+                    ("call", call),
+                ]),
+            );
+
+            // Give the inner signer the chance to wrap the call too.
+            let call_data = proxy_call.encode_call_data(metadata)?;
+            self.signer.wrap_call_data(call_data, metadata)
+        }
+    }
 }
 
 cfg_substrate_compat! {
@@ -11,6 +11,7 @@ mod multi_address;
 mod multi_signature;
 mod static_type;
 mod unchecked_extrinsic;
+mod weight;
 mod wrapper_opaque;
 
 use alloc::borrow::ToOwned;
@@ -27,6 +28,7 @@ pub use multi_signature::MultiSignature;
 pub use primitive_types::{H160, H256, H512};
 pub use static_type::Static;
 pub use unchecked_extrinsic::UncheckedExtrinsic;
+pub use weight::Weight;
 pub use wrapper_opaque::WrapperKeepOpaque;
 
 /// Wraps an already encoded byte vector, prevents being encoded as a raw byte vector as part of
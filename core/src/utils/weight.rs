@@ -0,0 +1,77 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use codec::{Decode, Encode};
+use scale_decode::DecodeAsType;
+use scale_encode::EncodeAsType;
+
+// Dev note: This and related bits taken from `sp_weights::Weight`
+/// The weight of some dispatchable, measured in two dimensions: the computational
+/// time it takes to execute, and the size of the storage proof it produces.
+#[derive(
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Hash,
+    Encode,
+    Decode,
+    DecodeAsType,
+    EncodeAsType,
+    scale_info::TypeInfo,
+)]
+pub struct Weight {
+    /// The weight of computational time used.
+    #[codec(compact)]
+    pub ref_time: u64,
+    /// The weight of storage space used by proof of validity.
+    #[codec(compact)]
+    pub proof_size: u64,
+}
+
+impl Weight {
+    /// A weight of zero in both dimensions.
+    pub const ZERO: Weight = Weight {
+        ref_time: 0,
+        proof_size: 0,
+    };
+
+    /// Construct a [`Weight`] from its two components.
+    pub const fn from_parts(ref_time: u64, proof_size: u64) -> Self {
+        Self {
+            ref_time,
+            proof_size,
+        }
+    }
+
+    /// Add two weights together, saturating at the numeric bounds instead of overflowing.
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self {
+            ref_time: self.ref_time.saturating_add(rhs.ref_time),
+            proof_size: self.proof_size.saturating_add(rhs.proof_size),
+        }
+    }
+
+    /// Subtract `rhs` from `self`, saturating at zero instead of underflowing.
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self {
+            ref_time: self.ref_time.saturating_sub(rhs.ref_time),
+            proof_size: self.proof_size.saturating_sub(rhs.proof_size),
+        }
+    }
+
+    /// Return `true` if both components of `self` are less than or equal to those of `other`.
+    pub const fn all_lte(self, other: Self) -> bool {
+        self.ref_time <= other.ref_time && self.proof_size <= other.proof_size
+    }
+
+    /// Return `true` if any component of `self` is greater than that of `other`.
+    pub const fn any_gt(self, other: Self) -> bool {
+        self.ref_time > other.ref_time || self.proof_size > other.proof_size
+    }
+}
@@ -0,0 +1,1262 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Convert legacy (pre-V14) metadata into [`Metadata`]. Legacy metadata only names the
+//! types that it uses, and so a set of [`LegacyTypes`] describing the shape of each of
+//! these names is needed in order to build the type registry that [`Metadata`] relies on.
+
+mod type_name;
+mod types;
+
+use super::TryFromError;
+use crate::Metadata;
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use codec::{Decode, Encode};
+use frame_metadata::decode_different::DecodeDifferent;
+use frame_metadata::{v11, v13, v14, RuntimeMetadata, RuntimeMetadataPrefixed};
+use hashbrown::HashMap;
+use scale_info::form::PortableForm;
+use scale_info::{
+    Field, Path, PortableRegistry, PortableType, Type, TypeDef, TypeDefArray, TypeDefCompact,
+    TypeDefComposite, TypeDefPrimitive, TypeDefSequence, TypeDefTuple, TypeDefVariant,
+    TypeParameter, Variant,
+};
+use type_name::TypeName;
+
+pub use types::{
+    LegacySignedExtension, LegacyTypeDef, LegacyTypes, LegacyVariant, LegacyVariantFields,
+};
+
+/// How deeply type aliases can refer to other aliases before we assume there's a cycle.
+const MAX_ALIAS_DEPTH: usize = 64;
+
+impl Metadata {
+    /// Convert legacy metadata into [`Metadata`], using the given [`LegacyTypes`] to work out
+    /// the shape of each of the types named in the metadata. V9 to V13 metadata are
+    /// supported. V14 and V15 metadata carry their own type information, and so are
+    /// converted as normal, ignoring the provided types.
+    ///
+    /// Prior to V12, pallets weren't given an index. Instead, calls were encoded with the
+    /// position of the pallet among those pallets having calls, events with its position among
+    /// those pallets having events, and errors with its position among all pallets. See
+    /// [`PalletMetadata::call_index()`] and [`PalletMetadata::event_index()`]. V9 and V10 metadata
+    /// don't describe the signed extensions in use, and so the ones that were in use at the time
+    /// are assumed.
+    ///
+    /// [`PalletMetadata::call_index()`]: crate::PalletMetadata::call_index
+    /// [`PalletMetadata::event_index()`]: crate::PalletMetadata::event_index
+    ///
+    /// Aside from the types named in the metadata, the `Address` and `ExtrinsicSignature` types
+    /// must be defined in order to describe the extrinsic format, and `Hash`, `Index` and `Balance`
+    /// must be defined to describe the default signed extensions. If a `DispatchError` type is
+    /// defined, it's used to decode dispatch errors.
+    pub fn from_legacy(
+        metadata: RuntimeMetadataPrefixed,
+        types: &LegacyTypes,
+    ) -> Result<Metadata, TryFromError> {
+        let version = metadata.1.version();
+        let v13 = match metadata.1 {
+            RuntimeMetadata::V9(m) => modules_to_v13(m.modules.encode(), default_extrinsic()),
+            RuntimeMetadata::V10(m) => modules_to_v13(m.modules.encode(), default_extrinsic()),
+            RuntimeMetadata::V11(m) => {
+                // V11 extrinsic metadata is identical to the V13 version.
+                let extrinsic = v13::ExtrinsicMetadata::decode(&mut &*m.extrinsic.encode())
+                    .expect("V11 extrinsic metadata is also valid V13 extrinsic metadata; qed");
+                modules_to_v13(m.modules.encode(), extrinsic)
+            }
+            RuntimeMetadata::V12(m) => {
+                // V13 only adds an extra storage entry type on the end of the V12 one,
+                // so anything encoded as V12 metadata decodes identically as V13.
+                v13::RuntimeMetadataV13::decode(&mut &*m.encode())
+                    .expect("V12 metadata is also valid V13 metadata; qed")
+            }
+            RuntimeMetadata::V13(m) => m,
+            RuntimeMetadata::V14(_) | RuntimeMetadata::V15(_) => return metadata.try_into(),
+            other => return Err(TryFromError::UnsupportedMetadataVersion(other.version())),
+        };
+
+        let (v14, indexes) = v13_to_v14(v13, types, version)?;
+        let mut metadata: Metadata = v14.try_into()?;
+        for (pallet, (call_index, event_index)) in
+            metadata.pallets.values_mut().iter_mut().zip(indexes)
+        {
+            pallet.call_index = call_index;
+            pallet.event_index = event_index;
+        }
+        metadata.index_pallets();
+        Ok(metadata)
+    }
+}
+
+/// Prior to V12, modules weren't given an index, but otherwise encode identically to V12 modules
+/// (which in turn decode as V13 modules). V9 and V10 modules encode identically to V11 modules.
+/// Each module is given its position as an index.
+fn modules_to_v13(modules: Vec<u8>, extrinsic: v13::ExtrinsicMetadata) -> v13::RuntimeMetadataV13 {
+    let modules = Vec::<v11::ModuleMetadata>::decode(&mut &*modules)
+        .expect("V9 and V10 modules are also valid V11 modules; qed")
+        .into_iter()
+        .enumerate()
+        .map(|(index, module)| {
+            let mut bytes = module.encode();
+            bytes.push(index as u8);
+            v13::ModuleMetadata::decode(&mut &*bytes)
+                .expect("V11 modules with an index appended are valid V13 modules; qed")
+        })
+        .collect();
+
+    v13::RuntimeMetadataV13 {
+        modules: DecodeDifferent::Decoded(modules),
+        extrinsic,
+    }
+}
+
+/// V9 and V10 metadata don't describe the extrinsic, so assume the version 4 extrinsics and the
+/// signed extensions that were in use at the time.
+fn default_extrinsic() -> v13::ExtrinsicMetadata {
+    let signed_extensions = [
+        "CheckVersion",
+        "CheckGenesis",
+        "CheckEra",
+        "CheckNonce",
+        "CheckWeight",
+        "ChargeTransactionPayment",
+    ];
+    v13::ExtrinsicMetadata {
+        version: 4,
+        signed_extensions: signed_extensions
+            .into_iter()
+            .map(|ext| DecodeDifferent::Decoded(ext.to_owned()))
+            .collect(),
+    }
+}
+
+/// Convert the metadata, which was originally of the given version, into V14 metadata. Alongside
+/// this, return the indexes that each pallet's calls and events are encoded with.
+fn v13_to_v14(
+    metadata: v13::RuntimeMetadataV13,
+    types: &LegacyTypes,
+    version: u32,
+) -> Result<(v14::RuntimeMetadataV14, Vec<(u8, u8)>), TryFromError> {
+    let mut registry = TypeRegistry::new(types);
+    let modules = decoded(metadata.modules);
+
+    let mut pallets = Vec::with_capacity(modules.len());
+    let mut indexes = Vec::with_capacity(modules.len());
+    let mut call_variants = Vec::new();
+    let mut event_variants = Vec::new();
+    for module in modules {
+        let name = decoded(module.name);
+
+        // Prior to V12, calls and events are indexed by position among the pallets having them.
+        let (call_index, event_index) = if version < 12 {
+            (call_variants.len() as u8, event_variants.len() as u8)
+        } else {
+            (module.index, module.index)
+        };
+        indexes.push((call_index, event_index));
+
+        let calls = match module.calls.map(decoded) {
+            Some(calls) => {
+                let variants = calls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, call)| {
+                        let fields = decoded(call.arguments)
+                            .into_iter()
+                            .map(|arg| {
+                                let ty_name = decoded(arg.ty);
+                                Ok(Field {
+                                    name: Some(decoded(arg.name)),
+                                    ty: registry.resolve_str(&ty_name)?.into(),
+                                    type_name: Some(ty_name),
+                                    docs: vec![],
+                                })
+                            })
+                            .collect::<Result<_, TryFromError>>()?;
+                        Ok(Variant {
+                            name: decoded(call.name),
+                            fields,
+                            index: index as u8,
+                            docs: decoded(call.documentation),
+                        })
+                    })
+                    .collect::<Result<_, TryFromError>>()?;
+                let ty = registry.add(pallet_enum_type(&name, "Call", variants));
+                call_variants.push(outer_enum_variant(&name, call_index, ty));
+                Some(v14::PalletCallMetadata { ty: ty.into() })
+            }
+            None => None,
+        };
+
+        let event = match module.event.map(decoded) {
+            Some(events) => {
+                let variants = events
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, event)| {
+                        let fields = decoded(event.arguments)
+                            .into_iter()
+                            .map(|ty_name| {
+                                Ok(Field {
+                                    name: None,
+                                    ty: registry.resolve_str(&ty_name)?.into(),
+                                    type_name: Some(ty_name),
+                                    docs: vec![],
+                                })
+                            })
+                            .collect::<Result<_, TryFromError>>()?;
+                        Ok(Variant {
+                            name: decoded(event.name),
+                            fields,
+                            index: index as u8,
+                            docs: decoded(event.documentation),
+                        })
+                    })
+                    .collect::<Result<_, TryFromError>>()?;
+                let ty = registry.add(pallet_enum_type(&name, "Event", variants));
+                event_variants.push(outer_enum_variant(&name, event_index, ty));
+                Some(v14::PalletEventMetadata { ty: ty.into() })
+            }
+            None => None,
+        };
+
+        let errors = decoded(module.errors);
+        let error = if errors.is_empty() {
+            None
+        } else {
+            let variants = errors
+                .into_iter()
+                .enumerate()
+                .map(|(index, error)| Variant {
+                    name: decoded(error.name),
+                    fields: vec![],
+                    index: index as u8,
+                    docs: decoded(error.documentation),
+                })
+                .collect();
+            let ty = registry.add(pallet_enum_type(&name, "Error", variants));
+            Some(v14::PalletErrorMetadata { ty: ty.into() })
+        };
+
+        let storage = match module.storage.map(decoded) {
+            Some(storage) => {
+                let entries = decoded(storage.entries)
+                    .into_iter()
+                    .map(|entry| storage_entry(&mut registry, entry, version))
+                    .collect::<Result<_, _>>()?;
+                Some(v14::PalletStorageMetadata {
+                    prefix: decoded(storage.prefix),
+                    entries,
+                })
+            }
+            None => None,
+        };
+
+        let constants = decoded(module.constants)
+            .into_iter()
+            .map(|constant| {
+                Ok(v14::PalletConstantMetadata {
+                    name: decoded(constant.name),
+                    ty: registry.resolve_str(&decoded(constant.ty))?.into(),
+                    value: decoded(constant.value),
+                    docs: decoded(constant.documentation),
+                })
+            })
+            .collect::<Result<_, TryFromError>>()?;
+
+        pallets.push(v14::PalletMetadata {
+            name,
+            storage,
+            calls,
+            event,
+            constants,
+            error,
+            index: module.index,
+        });
+    }
+
+    // Now that we know about every pallet, we can fill in the outer enums.
+    registry.set(
+        registry.call_ty,
+        outer_enum_type("RuntimeCall", call_variants),
+    );
+    registry.set(
+        registry.event_ty,
+        outer_enum_type("RuntimeEvent", event_variants),
+    );
+
+    if types.get("DispatchError").is_some() {
+        registry.resolve_str("DispatchError")?;
+    }
+
+    let extrinsic = extrinsic_metadata(&mut registry, metadata.extrinsic)?;
+    let runtime_ty = registry.add(Type {
+        path: Path {
+            segments: vec!["Runtime".to_owned()],
+        },
+        type_params: vec![],
+        type_def: TypeDef::Composite(TypeDefComposite { fields: vec![] }),
+        docs: vec![],
+    });
+
+    let metadata = v14::RuntimeMetadataV14 {
+        types: registry.finish(),
+        pallets,
+        extrinsic,
+        ty: runtime_ty.into(),
+    };
+    Ok((metadata, indexes))
+}
+
+fn storage_entry(
+    registry: &mut TypeRegistry,
+    entry: v13::StorageEntryMetadata,
+    version: u32,
+) -> Result<v14::StorageEntryMetadata<PortableForm>, TryFromError> {
+    let modifier = match entry.modifier {
+        v13::StorageEntryModifier::Optional => v14::StorageEntryModifier::Optional,
+        v13::StorageEntryModifier::Default => v14::StorageEntryModifier::Default,
+    };
+
+    let (hashers, keys, value) = match entry.ty {
+        v13::StorageEntryType::Plain(value) => {
+            let value = registry.resolve_str(&decoded(value))?;
+            return Ok(v14::StorageEntryMetadata {
+                name: decoded(entry.name),
+                modifier,
+                ty: v14::StorageEntryType::Plain(value.into()),
+                default: decoded(entry.default),
+                docs: decoded(entry.documentation),
+            });
+        }
+        v13::StorageEntryType::Map {
+            hasher,
+            key,
+            value,
+            unused: is_linked,
+        } => {
+            let (key, mut value) = (decoded(key), decoded(value));
+            // Prior to V11, linked maps store the previous and next keys alongside each value.
+            if version < 11 && is_linked {
+                value = alloc::format!("({value}, (Option<{key}>, Option<{key}>))");
+            }
+            (vec![hasher], vec![key], value)
+        }
+        v13::StorageEntryType::DoubleMap {
+            hasher,
+            key1,
+            key2,
+            value,
+            key2_hasher,
+        } => (
+            vec![hasher, key2_hasher],
+            vec![decoded(key1), decoded(key2)],
+            decoded(value),
+        ),
+        v13::StorageEntryType::NMap {
+            keys,
+            hashers,
+            value,
+        } => (decoded(hashers), decoded(keys), decoded(value)),
+    };
+
+    // As in V14 metadata, maps with more than one hasher have a tuple of keys.
+    let mut key_ids = keys
+        .iter()
+        .map(|key| registry.resolve_str(key))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = if key_ids.len() == 1 {
+        key_ids.remove(0)
+    } else {
+        registry.add(anonymous_type(TypeDef::Tuple(TypeDefTuple {
+            fields: key_ids.into_iter().map(Into::into).collect(),
+        })))
+    };
+
+    let hashers = hashers
+        .into_iter()
+        .map(|hasher| match hasher {
+            v13::StorageHasher::Blake2_128 => v14::StorageHasher::Blake2_128,
+            v13::StorageHasher::Blake2_256 => v14::StorageHasher::Blake2_256,
+            v13::StorageHasher::Blake2_128Concat => v14::StorageHasher::Blake2_128Concat,
+            v13::StorageHasher::Twox128 => v14::StorageHasher::Twox128,
+            v13::StorageHasher::Twox256 => v14::StorageHasher::Twox256,
+            v13::StorageHasher::Twox64Concat => v14::StorageHasher::Twox64Concat,
+            v13::StorageHasher::Identity => v14::StorageHasher::Identity,
+        })
+        .collect();
+
+    Ok(v14::StorageEntryMetadata {
+        name: decoded(entry.name),
+        modifier,
+        ty: v14::StorageEntryType::Map {
+            hashers,
+            key: key.into(),
+            value: registry.resolve_str(&value)?.into(),
+        },
+        default: decoded(entry.default),
+        docs: decoded(entry.documentation),
+    })
+}
+
+fn extrinsic_metadata(
+    registry: &mut TypeRegistry,
+    extrinsic: v13::ExtrinsicMetadata,
+) -> Result<v14::ExtrinsicMetadata<PortableForm>, TryFromError> {
+    let signed_extensions = extrinsic
+        .signed_extensions
+        .into_iter()
+        .map(|identifier| {
+            let identifier = decoded(identifier);
+            let Some(ext) = registry.legacy_types.signed_extension(&identifier) else {
+                return Err(TryFromError::TypeNameNotFound(identifier));
+            };
+            let (extra, additional_signed) = (ext.extra.clone(), ext.additional_signed.clone());
+            Ok(v14::SignedExtensionMetadata {
+                ty: registry.resolve_str(&extra)?.into(),
+                additional_signed: registry.resolve_str(&additional_signed)?.into(),
+                identifier,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let extra = registry.add(anonymous_type(TypeDef::Tuple(TypeDefTuple {
+        fields: signed_extensions.iter().map(|ext| ext.ty).collect(),
+    })));
+    let type_params = [
+        ("Address", registry.resolve_str("Address")?),
+        ("Call", registry.call_ty),
+        ("Signature", registry.resolve_str("ExtrinsicSignature")?),
+        ("Extra", extra),
+    ]
+    .into_iter()
+    .map(|(name, ty)| TypeParameter::<PortableForm> {
+        name: name.to_owned(),
+        ty: Some(ty.into()),
+    })
+    .collect();
+
+    let ty = registry.add(Type {
+        path: Path {
+            segments: vec!["UncheckedExtrinsic".to_owned()],
+        },
+        type_params,
+        type_def: TypeDef::Composite(TypeDefComposite { fields: vec![] }),
+        docs: vec![],
+    });
+
+    Ok(v14::ExtrinsicMetadata {
+        ty: ty.into(),
+        version: extrinsic.version,
+        signed_extensions,
+    })
+}
+
+/// Builds up a type registry from legacy type names.
+struct TypeRegistry<'a> {
+    legacy_types: &'a LegacyTypes,
+    // Types are reserved before they're defined, so that recursive types can refer to themselves.
+    types: Vec<Option<Type<PortableForm>>>,
+    // Type IDs for type names that we've already resolved.
+    resolved: HashMap<String, u32>,
+    call_ty: u32,
+    event_ty: u32,
+}
+
+impl<'a> TypeRegistry<'a> {
+    fn new(legacy_types: &'a LegacyTypes) -> Self {
+        let mut registry = TypeRegistry {
+            legacy_types,
+            types: Vec::new(),
+            resolved: HashMap::new(),
+            call_ty: 0,
+            event_ty: 0,
+        };
+        registry.call_ty = registry.reserve();
+        registry.event_ty = registry.reserve();
+        registry
+    }
+
+    fn reserve(&mut self) -> u32 {
+        self.types.push(None);
+        (self.types.len() - 1) as u32
+    }
+
+    fn set(&mut self, id: u32, ty: Type<PortableForm>) {
+        self.types[id as usize] = Some(ty);
+    }
+
+    fn add(&mut self, ty: Type<PortableForm>) -> u32 {
+        let id = self.reserve();
+        self.set(id, ty);
+        id
+    }
+
+    fn finish(self) -> PortableRegistry {
+        let types = self
+            .types
+            .into_iter()
+            .enumerate()
+            .map(|(id, ty)| PortableType {
+                id: id as u32,
+                ty: ty.expect("every reserved type is defined before finishing; qed"),
+            })
+            .collect();
+        PortableRegistry { types }
+    }
+
+    fn resolve_str(&mut self, name: &str) -> Result<u32, TryFromError> {
+        let Some(ty) = TypeName::parse(name) else {
+            return Err(TryFromError::InvalidLegacyTypeName(name.to_owned()));
+        };
+        self.resolve(&ty, 0)
+    }
+
+    fn resolve(&mut self, ty: &TypeName, depth: usize) -> Result<u32, TryFromError> {
+        let key = ty.to_string();
+        if let Some(id) = self.resolved.get(&key) {
+            return Ok(*id);
+        }
+        if depth > MAX_ALIAS_DEPTH {
+            return Err(TryFromError::InvalidLegacyTypeName(key));
+        }
+
+        let id = match ty {
+            TypeName::Tuple(items) => {
+                let fields = items
+                    .iter()
+                    .map(|item| self.resolve(item, depth + 1).map(Into::into))
+                    .collect::<Result<_, _>>()?;
+                self.add(anonymous_type(TypeDef::Tuple(TypeDefTuple { fields })))
+            }
+            TypeName::Array(item, len) => {
+                let type_param = self.resolve(item, depth + 1)?.into();
+                self.add(anonymous_type(TypeDef::Array(TypeDefArray {
+                    len: *len,
+                    type_param,
+                })))
+            }
+            TypeName::Slice(item) => {
+                let type_param = self.resolve(item, depth + 1)?.into();
+                self.add(anonymous_type(TypeDef::Sequence(TypeDefSequence {
+                    type_param,
+                })))
+            }
+            TypeName::Named { name, params } => self.resolve_named(name, params, depth)?,
+        };
+
+        self.resolved.insert(key, id);
+        Ok(id)
+    }
+
+    fn resolve_named(
+        &mut self,
+        name: &str,
+        params: &[TypeName],
+        depth: usize,
+    ) -> Result<u32, TryFromError> {
+        let invalid = || {
+            let ty = TypeName::Named {
+                name: name.to_owned(),
+                params: params.to_vec(),
+            };
+            TryFromError::InvalidLegacyTypeName(ty.to_string())
+        };
+        let depth = depth + 1;
+
+        // Generic types which are built into Rust or SCALE.
+        let def = match (name, params) {
+            ("Call", []) => return Ok(self.call_ty),
+            ("Event", []) => return Ok(self.event_ty),
+            ("Box" | "Arc" | "Rc", [inner]) => return self.resolve(inner, depth),
+            ("PhantomData", _) => Some(TypeDef::Tuple(TypeDefTuple { fields: vec![] })),
+            ("Vec" | "BTreeSet" | "VecDeque", [item]) => {
+                let type_param = self.resolve(item, depth)?.into();
+                Some(TypeDef::Sequence(TypeDefSequence { type_param }))
+            }
+            ("BTreeMap" | "HashMap", [key, value]) => {
+                let item = TypeName::Tuple(vec![key.clone(), value.clone()]);
+                let type_param = self.resolve(&item, depth)?.into();
+                Some(TypeDef::Sequence(TypeDefSequence { type_param }))
+            }
+            ("Compact", [inner]) => {
+                let type_param = self.resolve(inner, depth)?.into();
+                Some(TypeDef::Compact(TypeDefCompact { type_param }))
+            }
+            ("Option", [inner]) => {
+                let inner = self.resolve(inner, depth)?;
+                return Ok(self.add(Type {
+                    path: Path {
+                        segments: vec!["Option".to_owned()],
+                    },
+                    type_params: vec![type_param("T", inner)],
+                    type_def: TypeDef::Variant(TypeDefVariant {
+                        variants: vec![variant("None", 0, vec![]), variant("Some", 1, vec![inner])],
+                    }),
+                    docs: vec![],
+                }));
+            }
+            ("Result", [ok, err]) => {
+                let ok = self.resolve(ok, depth)?;
+                let err = self.resolve(err, depth)?;
+                return Ok(self.add(Type {
+                    path: Path {
+                        segments: vec!["Result".to_owned()],
+                    },
+                    type_params: vec![type_param("T", ok), type_param("E", err)],
+                    type_def: TypeDef::Variant(TypeDefVariant {
+                        variants: vec![variant("Ok", 0, vec![ok]), variant("Err", 1, vec![err])],
+                    }),
+                    docs: vec![],
+                }));
+            }
+            (
+                "Box" | "Arc" | "Rc" | "Vec" | "BTreeSet" | "VecDeque" | "BTreeMap" | "HashMap"
+                | "Compact" | "Option" | "Result",
+                _,
+            ) => return Err(invalid()),
+            _ => primitive(name).map(TypeDef::Primitive),
+        };
+        if let Some(def) = def {
+            return Ok(self.add(anonymous_type(def)));
+        }
+
+        // Types that have been defined by the user. Any generic parameters given to these
+        // are ignored, since they are expected to be concrete in the definitions.
+        if let Some(def) = self.legacy_types.get(name) {
+            if let Some(id) = self.resolved.get(name) {
+                return Ok(*id);
+            }
+            return self.resolve_legacy_def(name, def, depth);
+        }
+
+        // Common types which the user may have wanted to define differently.
+        let alias = match name {
+            "Null" => "()",
+            "Text" => "String",
+            "Bytes" => "Vec<u8>",
+            "H160" => "[u8; 20]",
+            "H256" => "[u8; 32]",
+            "H512" => "[u8; 64]",
+            "Era" | "ExtrinsicEra" => {
+                let u8_ty = self.resolve_str("u8")?;
+                return Ok(self.add(era_type(u8_ty)));
+            }
+            _ => return Err(TryFromError::TypeNameNotFound(name.to_owned())),
+        };
+        self.resolve_str(alias)
+    }
+
+    fn resolve_legacy_def(
+        &mut self,
+        name: &str,
+        def: &LegacyTypeDef,
+        depth: usize,
+    ) -> Result<u32, TryFromError> {
+        let path = Path {
+            segments: if name == "DispatchError" {
+                // This is where Subxt looks for the type that it decodes dispatch errors into.
+                vec!["sp_runtime".to_owned(), "DispatchError".to_owned()]
+            } else {
+                vec![name.to_owned()]
+            },
+        };
+
+        let type_def = match def {
+            LegacyTypeDef::Alias(alias) => {
+                let Some(alias) = TypeName::parse(alias) else {
+                    return Err(TryFromError::InvalidLegacyTypeName(alias.clone()));
+                };
+                return self.resolve(&alias, depth);
+            }
+            LegacyTypeDef::Set(bits) => {
+                let primitive = match bits {
+                    8 => TypeDefPrimitive::U8,
+                    16 => TypeDefPrimitive::U16,
+                    32 => TypeDefPrimitive::U32,
+                    64 => TypeDefPrimitive::U64,
+                    128 => TypeDefPrimitive::U128,
+                    _ => return Err(TryFromError::InvalidLegacyTypeName(name.to_owned())),
+                };
+                return Ok(self.add(anonymous_type(TypeDef::Primitive(primitive))));
+            }
+            LegacyTypeDef::Composite(fields) => {
+                // Reserve an ID first so that the type can refer to itself.
+                let id = self.reserve_named(name);
+                let fields = self.named_fields(fields, depth)?;
+                (id, TypeDef::Composite(TypeDefComposite { fields }))
+            }
+            LegacyTypeDef::Variant(variants) => {
+                let id = self.reserve_named(name);
+                let variants = variants
+                    .iter()
+                    .map(|v| {
+                        let fields = match &v.fields {
+                            LegacyVariantFields::Unit => vec![],
+                            LegacyVariantFields::Unnamed(ty) => {
+                                vec![Field {
+                                    name: None,
+                                    ty: self.resolve_str(ty)?.into(),
+                                    type_name: Some(ty.clone()),
+                                    docs: vec![],
+                                }]
+                            }
+                            LegacyVariantFields::Named(fields) => {
+                                self.named_fields(fields, depth)?
+                            }
+                        };
+                        Ok(Variant {
+                            name: v.name.clone(),
+                            fields,
+                            index: v.index,
+                            docs: vec![],
+                        })
+                    })
+                    .collect::<Result<_, TryFromError>>()?;
+                (id, TypeDef::Variant(TypeDefVariant { variants }))
+            }
+        };
+
+        let (id, type_def) = type_def;
+        self.set(
+            id,
+            Type {
+                path,
+                type_params: vec![],
+                type_def,
+                docs: vec![],
+            },
+        );
+        Ok(id)
+    }
+
+    fn reserve_named(&mut self, name: &str) -> u32 {
+        let id = self.reserve();
+        self.resolved.insert(name.to_owned(), id);
+        id
+    }
+
+    fn named_fields(
+        &mut self,
+        fields: &[(String, String)],
+        depth: usize,
+    ) -> Result<Vec<Field<PortableForm>>, TryFromError> {
+        fields
+            .iter()
+            .map(|(name, ty)| {
+                let Some(ty_name) = TypeName::parse(ty) else {
+                    return Err(TryFromError::InvalidLegacyTypeName(ty.clone()));
+                };
+                Ok(Field {
+                    name: Some(name.clone()),
+                    ty: self.resolve(&ty_name, depth)?.into(),
+                    type_name: Some(ty.clone()),
+                    docs: vec![],
+                })
+            })
+            .collect()
+    }
+}
+
+fn primitive(name: &str) -> Option<TypeDefPrimitive> {
+    Some(match name {
+        "bool" => TypeDefPrimitive::Bool,
+        "str" | "String" => TypeDefPrimitive::Str,
+        "u8" => TypeDefPrimitive::U8,
+        "u16" => TypeDefPrimitive::U16,
+        "u32" => TypeDefPrimitive::U32,
+        "u64" => TypeDefPrimitive::U64,
+        "u128" => TypeDefPrimitive::U128,
+        "u256" => TypeDefPrimitive::U256,
+        "i8" => TypeDefPrimitive::I8,
+        "i16" => TypeDefPrimitive::I16,
+        "i32" => TypeDefPrimitive::I32,
+        "i64" => TypeDefPrimitive::I64,
+        "i128" => TypeDefPrimitive::I128,
+        "i256" => TypeDefPrimitive::I256,
+        _ => return None,
+    })
+}
+
+/// The transaction mortality, which is encoded as one byte if immortal and two otherwise.
+/// Like `sp_runtime::generic::Era`, this is described as an enum with 256 variants.
+fn era_type(u8_ty: u32) -> Type<PortableForm> {
+    let mut variants = vec![variant("Immortal", 0, vec![])];
+    variants.extend((1..=255u8).map(|index| Variant {
+        name: alloc::format!("Mortal{index}"),
+        fields: vec![Field {
+            name: None,
+            ty: u8_ty.into(),
+            type_name: None,
+            docs: vec![],
+        }],
+        index,
+        docs: vec![],
+    }));
+    Type {
+        path: Path {
+            segments: vec!["Era".to_owned()],
+        },
+        type_params: vec![],
+        type_def: TypeDef::Variant(TypeDefVariant { variants }),
+        docs: vec![],
+    }
+}
+
+fn anonymous_type(type_def: TypeDef<PortableForm>) -> Type<PortableForm> {
+    Type {
+        path: Path::default(),
+        type_params: vec![],
+        type_def,
+        docs: vec![],
+    }
+}
+
+fn type_param(name: &str, ty: u32) -> TypeParameter<PortableForm> {
+    TypeParameter {
+        name: name.to_owned(),
+        ty: Some(ty.into()),
+    }
+}
+
+fn variant(name: &str, index: u8, fields: Vec<u32>) -> Variant<PortableForm> {
+    Variant {
+        name: name.to_owned(),
+        fields: fields
+            .into_iter()
+            .map(|ty| Field {
+                name: None,
+                ty: ty.into(),
+                type_name: None,
+                docs: vec![],
+            })
+            .collect(),
+        index,
+        docs: vec![],
+    }
+}
+
+fn pallet_enum_type(
+    pallet_name: &str,
+    enum_name: &str,
+    variants: Vec<Variant<PortableForm>>,
+) -> Type<PortableForm> {
+    Type {
+        path: Path {
+            segments: vec![pallet_name.to_owned(), enum_name.to_owned()],
+        },
+        type_params: vec![],
+        type_def: TypeDef::Variant(TypeDefVariant { variants }),
+        docs: vec![],
+    }
+}
+
+fn outer_enum_variant(pallet_name: &str, index: u8, ty: u32) -> Variant<PortableForm> {
+    Variant {
+        name: pallet_name.to_owned(),
+        fields: vec![Field {
+            name: None,
+            ty: ty.into(),
+            type_name: None,
+            docs: vec![],
+        }],
+        index,
+        docs: vec![],
+    }
+}
+
+fn outer_enum_type(name: &str, variants: Vec<Variant<PortableForm>>) -> Type<PortableForm> {
+    Type {
+        path: Path {
+            segments: vec![name.to_owned()],
+        },
+        type_params: vec![],
+        type_def: TypeDef::Variant(TypeDefVariant { variants }),
+        docs: vec![],
+    }
+}
+
+/// Legacy metadata which has been decoded always contains the "decoded" half of
+/// a [`DecodeDifferent`]. The "encode" half is only ever used when constructing
+/// metadata inside of a runtime, but in any case encodes identically.
+fn decoded<B, O>(value: DecodeDifferent<B, O>) -> O
+where
+    B: Encode + 'static,
+    O: Encode + Decode + 'static,
+{
+    match value {
+        DecodeDifferent::Decoded(o) => o,
+        DecodeDifferent::Encode(b) => {
+            O::decode(&mut &*b.encode()).expect("both halves encode identically; qed")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::decode_different::DecodeDifferent::Decoded;
+    use scale_info::TypeDef;
+
+    fn strings(items: &[&str]) -> DecodeDifferent<&'static [&'static str], Vec<String>> {
+        Decoded(items.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn call(name: &str, args: &[(&str, &str)]) -> v13::FunctionMetadata {
+        v13::FunctionMetadata {
+            name: Decoded(name.to_string()),
+            arguments: Decoded(
+                args.iter()
+                    .map(|(name, ty)| v13::FunctionArgumentMetadata {
+                        name: Decoded(name.to_string()),
+                        ty: Decoded(ty.to_string()),
+                    })
+                    .collect(),
+            ),
+            documentation: strings(&[]),
+        }
+    }
+
+    fn event(name: &str, args: &[&str]) -> v13::EventMetadata {
+        v13::EventMetadata {
+            name: Decoded(name.to_string()),
+            arguments: strings(args),
+            documentation: strings(&[]),
+        }
+    }
+
+    fn storage_entry(name: &str, ty: v13::StorageEntryType) -> v13::StorageEntryMetadata {
+        v13::StorageEntryMetadata {
+            name: Decoded(name.to_string()),
+            modifier: v13::StorageEntryModifier::Default,
+            ty,
+            default: Decoded(vec![0]),
+            documentation: strings(&[]),
+        }
+    }
+
+    fn legacy_modules() -> Vec<v13::ModuleMetadata> {
+        let system = v13::ModuleMetadata {
+            name: Decoded("System".to_string()),
+            storage: Some(Decoded(v13::StorageMetadata {
+                prefix: Decoded("System".to_string()),
+                entries: Decoded(vec![storage_entry(
+                    "Events",
+                    v13::StorageEntryType::Plain(Decoded(
+                        "Vec<EventRecord<T::Event, T::Hash>>".to_string(),
+                    )),
+                )]),
+            })),
+            calls: Some(Decoded(vec![call("remark", &[("_remark", "Vec<u8>")])])),
+            event: Some(Decoded(vec![
+                event("ExtrinsicSuccess", &["DispatchInfo"]),
+                event("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+            ])),
+            constants: Decoded(vec![]),
+            errors: Decoded(vec![]),
+            index: 0,
+        };
+        let randomness = v13::ModuleMetadata {
+            name: Decoded("Randomness".to_string()),
+            storage: None,
+            calls: None,
+            event: None,
+            constants: Decoded(vec![]),
+            errors: Decoded(vec![]),
+            index: 1,
+        };
+        let balances = v13::ModuleMetadata {
+            name: Decoded("Balances".to_string()),
+            storage: Some(Decoded(v13::StorageMetadata {
+                prefix: Decoded("Balances".to_string()),
+                entries: Decoded(vec![storage_entry(
+                    "Account",
+                    v13::StorageEntryType::Map {
+                        hasher: v13::StorageHasher::Blake2_128Concat,
+                        key: Decoded("T::AccountId".to_string()),
+                        value: Decoded("AccountData<T::Balance>".to_string()),
+                        unused: false,
+                    },
+                )]),
+            })),
+            calls: Some(Decoded(vec![call(
+                "transfer",
+                &[
+                    ("dest", "<T::Lookup as StaticLookup>::Source"),
+                    ("value", "Compact<T::Balance>"),
+                ],
+            )])),
+            event: Some(Decoded(vec![event(
+                "Transfer",
+                &["AccountId", "AccountId", "Balance"],
+            )])),
+            constants: Decoded(vec![v13::ModuleConstantMetadata {
+                name: Decoded("ExistentialDeposit".to_string()),
+                ty: Decoded("T::Balance".to_string()),
+                value: Decoded(1u128.encode()),
+                documentation: strings(&[]),
+            }]),
+            errors: Decoded(vec![v13::ErrorMetadata {
+                name: Decoded("InsufficientBalance".to_string()),
+                documentation: strings(&[]),
+            }]),
+            index: 5,
+        };
+        let utility = v13::ModuleMetadata {
+            name: Decoded("Utility".to_string()),
+            storage: None,
+            calls: Some(Decoded(vec![call(
+                "batch",
+                &[("calls", "Vec<<T as Trait>::Call>")],
+            )])),
+            event: None,
+            constants: Decoded(vec![]),
+            errors: Decoded(vec![]),
+            index: 6,
+        };
+        vec![system, randomness, balances, utility]
+    }
+
+    fn legacy_extrinsic() -> v13::ExtrinsicMetadata {
+        v13::ExtrinsicMetadata {
+            version: 4,
+            signed_extensions: vec![
+                Decoded("CheckSpecVersion".to_string()),
+                Decoded("CheckMortality".to_string()),
+                Decoded("CheckNonce".to_string()),
+                Decoded("ChargeTransactionPayment".to_string()),
+            ],
+        }
+    }
+
+    fn legacy_metadata() -> RuntimeMetadataPrefixed {
+        let metadata = v13::RuntimeMetadataV13 {
+            modules: Decoded(legacy_modules()),
+            extrinsic: legacy_extrinsic(),
+        };
+        RuntimeMetadataPrefixed(
+            frame_metadata::META_RESERVED,
+            RuntimeMetadata::V13(metadata),
+        )
+    }
+
+    /// V11 modules are V13 modules without the trailing index.
+    fn v11_modules(modules: Vec<v13::ModuleMetadata>) -> Vec<v11::ModuleMetadata> {
+        modules
+            .into_iter()
+            .map(|module| {
+                let mut bytes = module.encode();
+                bytes.pop();
+                v11::ModuleMetadata::decode(&mut &*bytes).unwrap()
+            })
+            .collect()
+    }
+
+    fn legacy_types() -> LegacyTypes {
+        LegacyTypes::from_json(
+            r#"{
+                "types": {
+                    "AccountId": "[u8; 32]",
+                    "Address": "AccountId",
+                    "LookupSource": "AccountId",
+                    "ExtrinsicSignature": "[u8; 64]",
+                    "Balance": "u128",
+                    "Index": "u32",
+                    "Hash": "H256",
+                    "AccountData": { "free": "Balance", "reserved": "Balance" },
+                    "DispatchInfo": { "weight": "u64", "class": "DispatchClass", "paysFee": "bool" },
+                    "DispatchClass": { "_enum": ["Normal", "Operational", "Mandatory"] },
+                    "DispatchError": { "_enum": { "Other": "Null", "Module": { "index": "u8", "error": "u8" } } },
+                    "Phase": { "_enum": { "ApplyExtrinsic": "u32", "Finalization": "Null", "Initialization": "Null" } },
+                    "EventRecord": { "phase": "Phase", "event": "Event", "topics": "Vec<Hash>" }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn converts_legacy_metadata() {
+        let metadata = Metadata::from_legacy(legacy_metadata(), &legacy_types()).unwrap();
+        let types = metadata.types();
+
+        let balances = metadata.pallet_by_name("Balances").unwrap();
+        assert_eq!(balances.index(), 5);
+        assert_eq!(metadata.pallet_by_index(5).unwrap().name(), "Balances");
+
+        let transfer = balances.call_variant_by_name("transfer").unwrap();
+        let value_ty = types.resolve(transfer.fields[1].ty.id).unwrap();
+        let TypeDef::Compact(compact) = &value_ty.type_def else {
+            panic!("value should be compact encoded");
+        };
+        assert_eq!(
+            types.resolve(compact.type_param.id).unwrap().type_def,
+            TypeDef::Primitive(TypeDefPrimitive::U128)
+        );
+
+        // The `Call` in `Utility::batch` refers to the outer call enum.
+        let batch = metadata
+            .pallet_by_name("Utility")
+            .unwrap()
+            .call_variant_by_name("batch")
+            .unwrap();
+        let TypeDef::Sequence(calls) = &types.resolve(batch.fields[0].ty.id).unwrap().type_def
+        else {
+            panic!("calls should be a sequence");
+        };
+        assert_eq!(calls.type_param.id, metadata.outer_enums().call_enum_ty());
+
+        assert!(balances.event_variant_by_index(0).is_some());
+        assert_eq!(
+            balances.error_variant_by_index(0).map(|v| v.name.as_str()),
+            Some("InsufficientBalance")
+        );
+        assert_eq!(
+            balances
+                .constant_by_name("ExistentialDeposit")
+                .unwrap()
+                .value(),
+            &1u128.encode()
+        );
+        assert!(balances
+            .storage()
+            .unwrap()
+            .entry_by_name("Account")
+            .is_some());
+
+        assert!(metadata.dispatch_error_ty().is_some());
+        let extensions: Vec<_> = metadata
+            .extrinsic()
+            .signed_extensions()
+            .iter()
+            .map(|ext| ext.identifier())
+            .collect();
+        assert_eq!(
+            extensions,
+            [
+                "CheckSpecVersion",
+                "CheckMortality",
+                "CheckNonce",
+                "ChargeTransactionPayment"
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_types_are_reported() {
+        let mut types = legacy_types();
+        types.insert("AccountData", LegacyTypeDef::Alias("Unknown".to_string()));
+        assert_eq!(
+            Metadata::from_legacy(legacy_metadata(), &types).unwrap_err(),
+            TryFromError::TypeNameNotFound("Unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn pre_v12_metadata_is_indexed_by_position() {
+        let metadata = RuntimeMetadataPrefixed(
+            frame_metadata::META_RESERVED,
+            RuntimeMetadata::V11(v11::RuntimeMetadataV11 {
+                modules: Decoded(v11_modules(legacy_modules())),
+                extrinsic: v11::ExtrinsicMetadata::decode(&mut &*legacy_extrinsic().encode())
+                    .unwrap(),
+            }),
+        );
+        let metadata = Metadata::from_legacy(metadata, &legacy_types()).unwrap();
+
+        // Randomness has no calls or events, and so Balances is the second pallet with each.
+        let balances = metadata.pallet_by_name("Balances").unwrap();
+        assert_eq!(
+            (
+                balances.index(),
+                balances.call_index(),
+                balances.event_index()
+            ),
+            (2, 1, 1)
+        );
+        assert_eq!(metadata.pallet_by_index(2).unwrap().name(), "Balances");
+        assert_eq!(metadata.pallet_by_call_index(1).unwrap().name(), "Balances");
+        assert_eq!(
+            metadata.pallet_by_event_index(1).unwrap().name(),
+            "Balances"
+        );
+        assert_eq!(metadata.pallet_by_call_index(2).unwrap().name(), "Utility");
+        assert!(metadata.pallet_by_event_index(2).is_none());
+
+        // The outer enums are encoded using the same indexes.
+        let outer_index = |ty: u32, name: &str| {
+            let TypeDef::Variant(outer) = &metadata.types().resolve(ty).unwrap().type_def else {
+                panic!("outer enums should be variants");
+            };
+            outer
+                .variants
+                .iter()
+                .find(|v| v.name == name)
+                .unwrap()
+                .index
+        };
+        let outer_enums = metadata.outer_enums();
+        assert_eq!(outer_index(outer_enums.call_enum_ty(), "Utility"), 2);
+        assert_eq!(outer_index(outer_enums.event_enum_ty(), "Balances"), 1);
+    }
+
+    #[test]
+    fn pre_v11_linked_maps_store_linkage() {
+        let mut modules = legacy_modules();
+        let Some(Decoded(storage)) = &mut modules[2].storage else {
+            panic!("Balances has storage");
+        };
+        let Decoded(entries) = &mut storage.entries else {
+            panic!("storage entries are decoded");
+        };
+        let v13::StorageEntryType::Map { unused, .. } = &mut entries[0].ty else {
+            panic!("Balances::Account is a map");
+        };
+        *unused = true;
+
+        // V10 modules are encoded identically to V11 modules.
+        let modules = Vec::<frame_metadata::v10::ModuleMetadata>::decode(
+            &mut &*v11_modules(modules).encode(),
+        )
+        .unwrap();
+        let metadata = RuntimeMetadataPrefixed(
+            frame_metadata::META_RESERVED,
+            RuntimeMetadata::V10(frame_metadata::v10::RuntimeMetadataV10 {
+                modules: Decoded(modules),
+            }),
+        );
+        let metadata = Metadata::from_legacy(metadata, &legacy_types()).unwrap();
+
+        let account = metadata
+            .pallet_by_name("Balances")
+            .unwrap()
+            .storage()
+            .unwrap()
+            .entry_by_name("Account")
+            .unwrap();
+        let value_ty = metadata.types().resolve(account.entry_type().value_ty());
+        let TypeDef::Tuple(value) = &value_ty.unwrap().type_def else {
+            panic!("linked map values should be stored alongside their linkage");
+        };
+        assert_eq!(value.fields.len(), 2);
+
+        let extensions: Vec<_> = metadata
+            .extrinsic()
+            .signed_extensions()
+            .iter()
+            .map(|ext| ext.identifier())
+            .collect();
+        assert_eq!(extensions[0], "CheckVersion");
+    }
+
+    #[test]
+    fn default_types_know_signed_extensions() {
+        assert!(LegacyTypes::default()
+            .signed_extension("CheckNonce")
+            .is_some());
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Parse the Rust-like type names found in legacy metadata, for instance
+//! `Vec<(T::AccountId, Compact<BalanceOf<T>>)>`, into a [`TypeName`].
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A parsed legacy type name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeName {
+    /// A named type with any generic parameters it was given. Only the last
+    /// segment of a path is kept, so `T::Balance` becomes `Balance`.
+    Named { name: String, params: Vec<TypeName> },
+    /// A tuple of types, for instance `(u32, bool)` or `()`.
+    Tuple(Vec<TypeName>),
+    /// A fixed size array, for instance `[u8; 32]`.
+    Array(Box<TypeName>, u32),
+    /// A slice, for instance `&[u8]`, which is encoded like a `Vec`.
+    Slice(Box<TypeName>),
+}
+
+impl TypeName {
+    /// Parse a legacy type name.
+    pub fn parse(input: &str) -> Option<TypeName> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let ty = parser.parse_type()?;
+        parser.at_end().then_some(ty)
+    }
+}
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_list(f: &mut fmt::Formatter<'_>, items: &[TypeName]) -> fmt::Result {
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{item}")?;
+            }
+            Ok(())
+        }
+
+        match self {
+            TypeName::Named { name, params } if params.is_empty() => f.write_str(name),
+            TypeName::Named { name, params } => {
+                write!(f, "{name}<")?;
+                write_list(f, params)?;
+                f.write_str(">")
+            }
+            TypeName::Tuple(items) => {
+                f.write_str("(")?;
+                write_list(f, items)?;
+                f.write_str(")")
+            }
+            TypeName::Array(item, len) => write!(f, "[{item}; {len}]"),
+            TypeName::Slice(item) => write!(f, "&[{item}]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(u32),
+    Lifetime,
+    PathSep,
+    Punct(char),
+}
+
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            ':' => {
+                // Only `::` is valid; a lone `:` is not part of a type name.
+                if chars.next() != Some(':') {
+                    return None;
+                }
+                tokens.push(Token::PathSep);
+            }
+            '\'' => {
+                // Lifetimes (ie `&'static [u8]`) have no bearing on the shape of a type.
+                while chars
+                    .next_if(|c| c.is_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+                tokens.push(Token::Lifetime);
+            }
+            '<' | '>' | '(' | ')' | '[' | ']' | ';' | ',' | '&' => tokens.push(Token::Punct(c)),
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(d) = chars.next_if(|c| c.is_ascii_digit()) {
+                    digits.push(d);
+                }
+                tokens.push(Token::Number(digits.parse().ok()?));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            _ => return None,
+        }
+    }
+
+    Some(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn at_end(&self) -> bool {
+        self.pos == self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Option<()> {
+        self.eat(token).then_some(())
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        match self.next()? {
+            Token::Ident(ident) => Some(ident.clone()),
+            _ => None,
+        }
+    }
+
+    fn parse_type(&mut self) -> Option<TypeName> {
+        match self.peek()? {
+            Token::Punct('(') => {
+                self.pos += 1;
+                let items = self.parse_list(')')?;
+                Some(TypeName::Tuple(items))
+            }
+            Token::Punct('[') => {
+                self.pos += 1;
+                let item = Box::new(self.parse_type()?);
+                if self.eat(&Token::Punct(']')) {
+                    return Some(TypeName::Slice(item));
+                }
+                self.expect(&Token::Punct(';'))?;
+                let Token::Number(len) = *self.next()? else {
+                    return None;
+                };
+                self.expect(&Token::Punct(']'))?;
+                Some(TypeName::Array(item, len))
+            }
+            Token::Punct('&') => {
+                self.pos += 1;
+                self.eat(&Token::Lifetime);
+                self.parse_type()
+            }
+            Token::Punct('<') => {
+                // A qualified path like `<T::Lookup as StaticLookup>::Source`.
+                self.pos += 1;
+                self.parse_type()?;
+                if self.ident()? != "as" {
+                    return None;
+                }
+                let trait_name = self.parse_type()?;
+                self.expect(&Token::Punct('>'))?;
+                let mut name = None;
+                while self.eat(&Token::PathSep) {
+                    name = Some(self.ident()?);
+                }
+                let name = name?;
+
+                // Follow the lead of other tooling and give this commonly seen
+                // associated type a more useful name to look up.
+                let is_static_lookup = matches!(
+                    &trait_name,
+                    TypeName::Named { name, .. } if name == "StaticLookup"
+                );
+                let name = if is_static_lookup && name == "Source" {
+                    "LookupSource".to_string()
+                } else {
+                    name
+                };
+
+                let params = self.parse_params()?;
+                Some(TypeName::Named { name, params })
+            }
+            Token::Ident(_) => {
+                let mut name = self.ident()?;
+                while self.eat(&Token::PathSep) {
+                    name = self.ident()?;
+                }
+                let params = self.parse_params()?;
+                Some(TypeName::Named { name, params })
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_params(&mut self) -> Option<Vec<TypeName>> {
+        if self.eat(&Token::Punct('<')) {
+            self.parse_list('>')
+        } else {
+            Some(Vec::new())
+        }
+    }
+
+    /// Parse a comma separated list of types up to and including the closing character,
+    /// allowing for a trailing comma.
+    fn parse_list(&mut self, close: char) -> Option<Vec<TypeName>> {
+        let close = Token::Punct(close);
+        let mut items = Vec::new();
+        loop {
+            if self.eat(&close) {
+                return Some(items);
+            }
+            items.push(self.parse_type()?);
+            if !self.eat(&Token::Punct(',')) {
+                self.expect(&close)?;
+                return Some(items);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn named(name: &str, params: Vec<TypeName>) -> TypeName {
+        TypeName::Named {
+            name: name.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn parses_paths_and_generics() {
+        assert_eq!(
+            TypeName::parse("Vec<(T::AccountId, Compact<BalanceOf<T>>)>").unwrap(),
+            named(
+                "Vec",
+                vec![TypeName::Tuple(vec![
+                    named("AccountId", vec![]),
+                    named(
+                        "Compact",
+                        vec![named("BalanceOf", vec![named("T", vec![])])]
+                    ),
+                ])]
+            )
+        );
+    }
+
+    #[test]
+    fn parses_arrays_slices_and_tuples() {
+        assert_eq!(
+            TypeName::parse("[u8; 32]").unwrap(),
+            TypeName::Array(Box::new(named("u8", vec![])), 32)
+        );
+        assert_eq!(
+            TypeName::parse("&'static [u8]").unwrap(),
+            TypeName::Slice(Box::new(named("u8", vec![])))
+        );
+        assert_eq!(TypeName::parse("()").unwrap(), TypeName::Tuple(vec![]));
+        assert_eq!(
+            TypeName::parse("(u32,)").unwrap(),
+            TypeName::Tuple(vec![named("u32", vec![])])
+        );
+    }
+
+    #[test]
+    fn parses_qualified_paths() {
+        assert_eq!(
+            TypeName::parse("<T::Lookup as StaticLookup>::Source").unwrap(),
+            named("LookupSource", vec![])
+        );
+        assert_eq!(
+            TypeName::parse("Box<<T as Trait>::Call>").unwrap(),
+            named("Box", vec![named("Call", vec![])])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert_eq!(TypeName::parse("Vec<u8"), None);
+        assert_eq!(TypeName::parse("[u8; N]"), None);
+        assert_eq!(TypeName::parse("a: u8"), None);
+        assert_eq!(TypeName::parse(""), None);
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use hashbrown::HashMap;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+/// Legacy (pre-V14) metadata describes types by name only, for instance `T::Balance` or
+/// `Vec<AccountId>`. [`LegacyTypes`] provides the SCALE shape of each of these names, so
+/// that legacy metadata can be converted into [`crate::Metadata`].
+///
+/// Type definitions are typically loaded from a JSON file via [`LegacyTypes::from_json()`],
+/// and can also be added by hand via [`LegacyTypes::insert()`].
+///
+/// Names like `u32`, `bool`, `Vec<T>`, `Option<T>`, `Compact<T>`, `Box<T>`, `BTreeMap<K, V>`,
+/// tuples and arrays are understood without being defined, as are `Call` and `Event`, which
+/// refer to the outer call and event enums that are generated from the metadata.
+#[derive(Debug, Clone)]
+pub struct LegacyTypes {
+    types: HashMap<String, LegacyTypeDef>,
+    signed_extensions: HashMap<String, LegacySignedExtension>,
+}
+
+/// The shape of a single legacy type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegacyTypeDef {
+    /// This type is encoded identically to another named type, for instance `"u128"`.
+    Alias(String),
+    /// A struct with named fields, each given as a field name and type name.
+    Composite(Vec<(String, String)>),
+    /// An enum with the given variants.
+    Variant(Vec<LegacyVariant>),
+    /// A set of flags which is encoded as an unsigned integer with this many bits.
+    Set(u32),
+}
+
+/// A single variant of a [`LegacyTypeDef::Variant`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyVariant {
+    /// The name of the variant.
+    pub name: String,
+    /// The index that the variant is encoded with.
+    pub index: u8,
+    /// The fields of the variant.
+    pub fields: LegacyVariantFields,
+}
+
+/// The fields of a [`LegacyVariant`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegacyVariantFields {
+    /// The variant has no fields.
+    Unit,
+    /// The variant has a single unnamed field of the given type.
+    Unnamed(String),
+    /// The variant has named fields, each given as a field name and type name.
+    Named(Vec<(String, String)>),
+}
+
+/// The types that a signed extension adds to each extrinsic and to the signer payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacySignedExtension {
+    /// The name of the type that this signed extension adds to the extrinsic.
+    pub extra: String,
+    /// The name of the type that this signed extension adds to the signer payload.
+    pub additional_signed: String,
+}
+
+impl LegacyTypes {
+    /// Create a new, empty set of type definitions. The signed extensions provided by
+    /// `frame_system` and `pallet_transaction_payment` are known about already.
+    pub fn new() -> Self {
+        let mut types = LegacyTypes {
+            types: HashMap::new(),
+            signed_extensions: HashMap::new(),
+        };
+        for (name, extra, additional_signed) in [
+            ("CheckSpecVersion", "()", "u32"),
+            ("CheckTxVersion", "()", "u32"),
+            ("CheckVersion", "()", "u32"),
+            ("CheckGenesis", "()", "Hash"),
+            ("CheckMortality", "Era", "Hash"),
+            ("CheckEra", "Era", "Hash"),
+            ("CheckNonce", "Compact<Index>", "()"),
+            ("CheckWeight", "()", "()"),
+            ("ChargeTransactionPayment", "Compact<Balance>", "()"),
+        ] {
+            types.insert_signed_extension(name, extra, additional_signed);
+        }
+        types
+    }
+
+    /// Parse type definitions from JSON. The JSON is expected to be an object with a `types`
+    /// field and an optional `signedExtensions` field, using the same format as the type
+    /// definitions given to polkadot.js. For example:
+    ///
+    /// ```json
+    /// {
+    ///   "types": {
+    ///     "Balance": "u128",
+    ///     "AccountId": "[u8; 32]",
+    ///     "Weight": "u64",
+    ///     "Transfer": { "from": "AccountId", "to": "AccountId", "amount": "Balance" },
+    ///     "Vote": { "_enum": ["Aye", "Nay"] },
+    ///     "MultiAddress": { "_enum": { "Id": "AccountId", "Index": "Compact<u32>" } }
+    ///   },
+    ///   "signedExtensions": {
+    ///     "ChargeAssetTxPayment": {
+    ///       "extrinsic": { "tip": "Compact<Balance>", "assetId": "Option<u32>" },
+    ///       "payload": {}
+    ///     }
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// Struct fields and enum variants are kept in the order that they appear in the JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let value: Json = serde_json::from_str(json)?;
+        let mut types = LegacyTypes::new();
+
+        let Json::Object(fields) = value else {
+            return Err(de::Error::custom("expected an object of type definitions"));
+        };
+        for (key, value) in fields {
+            match key.as_str() {
+                "types" => {
+                    for (name, def) in value.into_object("types")? {
+                        let def = LegacyTypeDef::from_json(&name, def)?;
+                        types.insert(name, def);
+                    }
+                }
+                "signedExtensions" => {
+                    for (name, ext) in value.into_object("signedExtensions")? {
+                        let mut extra = String::from("()");
+                        let mut additional_signed = String::from("()");
+                        for (key, value) in ext.into_object(&name)? {
+                            let ty = value.into_tuple_type_name(&name)?;
+                            match key.as_str() {
+                                "extrinsic" => extra = ty,
+                                "payload" => additional_signed = ty,
+                                _ => {}
+                            }
+                        }
+                        types.insert_signed_extension(name, extra, additional_signed);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(types)
+    }
+
+    /// Add a type definition, replacing any existing definition with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, def: LegacyTypeDef) -> &mut Self {
+        self.types.insert(name.into(), def);
+        self
+    }
+
+    /// Describe the types added by a signed extension, replacing any existing description.
+    pub fn insert_signed_extension(
+        &mut self,
+        name: impl Into<String>,
+        extra: impl Into<String>,
+        additional_signed: impl Into<String>,
+    ) -> &mut Self {
+        self.signed_extensions.insert(
+            name.into(),
+            LegacySignedExtension {
+                extra: extra.into(),
+                additional_signed: additional_signed.into(),
+            },
+        );
+        self
+    }
+
+    /// Return the definition of the given type name, if one exists.
+    pub fn get(&self, name: &str) -> Option<&LegacyTypeDef> {
+        self.types.get(name)
+    }
+
+    /// Return the types added by the given signed extension, if they are known.
+    pub fn signed_extension(&self, name: &str) -> Option<&LegacySignedExtension> {
+        self.signed_extensions.get(name)
+    }
+}
+
+impl Default for LegacyTypes {
+    fn default() -> Self {
+        LegacyTypes::new()
+    }
+}
+
+impl LegacyTypeDef {
+    fn from_json(name: &str, value: Json) -> Result<Self, serde_json::Error> {
+        let fields = match value {
+            Json::String(alias) => return Ok(LegacyTypeDef::Alias(alias)),
+            Json::Object(fields) => fields,
+            _ => return Err(invalid_def(name)),
+        };
+
+        if let Some((_, variants)) = fields.iter().find(|(key, _)| key == "_enum") {
+            let variants = match variants.clone() {
+                // `["A", "B"]`: unit variants indexed in order.
+                Json::Array(names) => names
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, name_value)| {
+                        let Json::String(variant_name) = name_value else {
+                            return Err(invalid_def(name));
+                        };
+                        Ok(LegacyVariant {
+                            name: variant_name,
+                            index: variant_index(name, index as u64)?,
+                            fields: LegacyVariantFields::Unit,
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+                // `{ "A": "Null", "B": "u32", "C": { "x": "u8" } }` or `{ "A": 0, "B": 4 }`.
+                Json::Object(entries) => entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (variant_name, value))| {
+                        let (index, fields) = match value {
+                            Json::Number(explicit) => (explicit, LegacyVariantFields::Unit),
+                            Json::String(ty) if ty == "Null" || ty == "()" => {
+                                (index as u64, LegacyVariantFields::Unit)
+                            }
+                            Json::String(ty) => (index as u64, LegacyVariantFields::Unnamed(ty)),
+                            Json::Object(fields) => (
+                                index as u64,
+                                LegacyVariantFields::Named(struct_fields(name, fields)?),
+                            ),
+                            _ => return Err(invalid_def(name)),
+                        };
+                        Ok(LegacyVariant {
+                            name: variant_name,
+                            index: variant_index(name, index)?,
+                            fields,
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(invalid_def(name)),
+            };
+            return Ok(LegacyTypeDef::Variant(variants));
+        }
+
+        if let Some((_, flags)) = fields.iter().find(|(key, _)| key == "_set") {
+            let Json::Object(flags) = flags else {
+                return Err(invalid_def(name));
+            };
+            let bits = flags
+                .iter()
+                .find_map(|(key, value)| match (key.as_str(), value) {
+                    ("_bitLength", Json::Number(bits)) => Some(*bits as u32),
+                    _ => None,
+                })
+                .unwrap_or(8);
+            return Ok(LegacyTypeDef::Set(bits));
+        }
+
+        Ok(LegacyTypeDef::Composite(struct_fields(name, fields)?))
+    }
+}
+
+fn struct_fields(
+    name: &str,
+    fields: Vec<(String, Json)>,
+) -> Result<Vec<(String, String)>, serde_json::Error> {
+    fields
+        .into_iter()
+        // Keys like `_alias` and `_fallback` tell polkadot.js how to name and
+        // decode things, and don't describe fields.
+        .filter(|(key, _)| !key.starts_with('_'))
+        .map(|(key, value)| match value {
+            Json::String(ty) => Ok((key, ty)),
+            _ => Err(invalid_def(name)),
+        })
+        .collect()
+}
+
+fn variant_index(name: &str, index: u64) -> Result<u8, serde_json::Error> {
+    u8::try_from(index).map_err(|_| invalid_def(name))
+}
+
+fn invalid_def(name: &str) -> serde_json::Error {
+    de::Error::custom(alloc::format!("invalid type definition for {name}"))
+}
+
+/// A JSON value which, unlike [`serde_json::Value`], always preserves the order of object
+/// keys. The order of struct fields and enum variants is significant when encoding them.
+#[derive(Debug, Clone)]
+enum Json {
+    Null,
+    Bool,
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn into_object(self, name: &str) -> Result<Vec<(String, Json)>, serde_json::Error> {
+        match self {
+            Json::Object(fields) => Ok(fields),
+            _ => Err(invalid_def(name)),
+        }
+    }
+
+    /// Turn a type name, or an object of named fields, into a single type name.
+    fn into_tuple_type_name(self, name: &str) -> Result<String, serde_json::Error> {
+        let fields = match self {
+            Json::String(ty) => return Ok(ty),
+            Json::Object(fields) => struct_fields(name, fields)?,
+            _ => return Err(invalid_def(name)),
+        };
+        Ok(match fields.as_slice() {
+            [(_, ty)] => ty.to_owned(),
+            fields => {
+                let tys: Vec<&str> = fields.iter().map(|(_, ty)| ty.as_str()).collect();
+                alloc::format!("({})", tys.join(", "))
+            }
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct JsonVisitor;
+
+        impl<'de> Visitor<'de> for JsonVisitor {
+            type Value = Json;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON value")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+                Ok(Json::Null)
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Json, E> {
+                Ok(Json::Bool)
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Json, E> {
+                Ok(Json::Number(n))
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Json, E> {
+                u64::try_from(n)
+                    .map(Json::Number)
+                    .map_err(|_| E::custom("negative numbers are not expected"))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Json, E> {
+                Err(E::custom("floating point numbers are not expected"))
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Json, E> {
+                Ok(Json::String(s.to_owned()))
+            }
+
+            fn visit_string<E: de::Error>(self, s: String) -> Result<Json, E> {
+                Ok(Json::String(s))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(Json::Array(items))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    fields.push(entry);
+                }
+                Ok(Json::Object(fields))
+            }
+        }
+
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn field(name: &str, ty: &str) -> (String, String) {
+        (name.to_string(), ty.to_string())
+    }
+
+    #[test]
+    fn parses_json_type_definitions_in_order() {
+        let types = LegacyTypes::from_json(
+            r#"{
+                "types": {
+                    "Balance": "u128",
+                    "Transfer": { "to": "AccountId", "amount": "Balance", "_alias": { "to": "dest" } },
+                    "Vote": { "_enum": ["Nay", "Aye"] },
+                    "Address": { "_enum": { "Id": "AccountId", "Nothing": "Null", "Raw": { "data": "Vec<u8>" } } },
+                    "Indexed": { "_enum": { "First": 1, "Second": 5 } },
+                    "Flags": { "_set": { "_bitLength": 64, "A": 1, "B": 2 } }
+                },
+                "signedExtensions": {
+                    "ChargeAssetTxPayment": {
+                        "extrinsic": { "tip": "Compact<Balance>", "assetId": "Option<u32>" },
+                        "payload": {}
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            types.get("Balance"),
+            Some(&LegacyTypeDef::Alias("u128".to_string()))
+        );
+        assert_eq!(
+            types.get("Transfer"),
+            Some(&LegacyTypeDef::Composite(vec![
+                field("to", "AccountId"),
+                field("amount", "Balance")
+            ]))
+        );
+
+        let Some(LegacyTypeDef::Variant(vote)) = types.get("Vote") else {
+            panic!("Vote should be an enum");
+        };
+        assert_eq!(vote[0].name, "Nay");
+        assert_eq!(vote[1].index, 1);
+
+        let Some(LegacyTypeDef::Variant(address)) = types.get("Address") else {
+            panic!("Address should be an enum");
+        };
+        assert_eq!(
+            address.iter().map(|v| &v.fields).collect::<Vec<_>>(),
+            vec![
+                &LegacyVariantFields::Unnamed("AccountId".to_string()),
+                &LegacyVariantFields::Unit,
+                &LegacyVariantFields::Named(vec![field("data", "Vec<u8>")]),
+            ]
+        );
+
+        let Some(LegacyTypeDef::Variant(indexed)) = types.get("Indexed") else {
+            panic!("Indexed should be an enum");
+        };
+        assert_eq!(indexed[1].index, 5);
+
+        assert_eq!(types.get("Flags"), Some(&LegacyTypeDef::Set(64)));
+
+        let ext = types.signed_extension("ChargeAssetTxPayment").unwrap();
+        assert_eq!(ext.extra, "(Compact<Balance>, Option<u32>)");
+        assert_eq!(ext.additional_signed, "()");
+        assert!(types.signed_extension("CheckNonce").is_some());
+    }
+
+    #[test]
+    fn rejects_invalid_definitions() {
+        assert!(LegacyTypes::from_json(r#"{ "types": { "Foo": 1 } }"#).is_err());
+        assert!(LegacyTypes::from_json(r#"{ "types": { "Foo": { "_enum": 1 } } }"#).is_err());
+        assert!(LegacyTypes::from_json(r#"[]"#).is_err());
+    }
+}
@@ -5,6 +5,8 @@
 use alloc::string::String;
 use derive_more::Display;
 
+#[cfg(feature = "legacy")]
+pub(crate) mod legacy;
 mod v14;
 mod v15;
 
@@ -28,6 +30,9 @@ pub enum TryFromError {
     /// Invalid type path.
     #[display(fmt = "Type has an invalid path {_0}")]
     InvalidTypePath(String),
+    /// A type name in legacy metadata could not be parsed or resolved.
+    #[display(fmt = "Legacy type name {_0} could not be parsed or resolved")]
+    InvalidLegacyTypeName(String),
 }
 
 #[cfg(feature = "std")]
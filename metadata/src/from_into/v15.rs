@@ -24,8 +24,7 @@ mod from_v15 {
         type Error = TryFromError;
         fn try_from(m: v15::RuntimeMetadataV15) -> Result<Self, TryFromError> {
             let mut pallets = OrderedMap::new();
-            let mut pallets_by_index = HashMap::new();
-            for (pos, p) in m.pallets.into_iter().enumerate() {
+            for p in m.pallets {
                 let name: ArcStr = p.name.into();
 
                 let storage = p.storage.map(|s| StorageMetadata {
@@ -51,12 +50,13 @@ mod from_v15 {
                 let event_variant_index =
                     VariantIndex::build(p.event.as_ref().map(|e| e.ty.id), &m.types);
 
-                pallets_by_index.insert(p.index, pos);
                 pallets.push_insert(
                     name.clone(),
                     PalletMetadataInner {
                         name,
                         index: p.index,
+                        call_index: p.index,
+                        event_index: p.index,
                         storage,
                         call_ty: p.calls.map(|c| c.ty.id),
                         call_variant_index,
@@ -82,10 +82,12 @@ mod from_v15 {
                 .find(|ty| ty.ty.path.segments == ["sp_runtime", "DispatchError"])
                 .map(|ty| ty.id);
 
-            Ok(Metadata {
+            let mut metadata = Metadata {
                 types: m.types,
                 pallets,
-                pallets_by_index,
+                pallets_by_index: HashMap::new(),
+                pallets_by_call_index: HashMap::new(),
+                pallets_by_event_index: HashMap::new(),
                 extrinsic: from_extrinsic_metadata(m.extrinsic),
                 runtime_ty: m.ty.id,
                 dispatch_error_ty,
@@ -96,7 +98,9 @@ mod from_v15 {
                     error_enum_ty: m.outer_enums.error_enum_ty.id,
                 },
                 custom: m.custom,
-            })
+            };
+            metadata.index_pallets();
+            Ok(metadata)
         }
     }
 
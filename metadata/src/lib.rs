@@ -13,6 +13,8 @@
 //!    from a node (this uses [`codec::Decode`]).
 //! 2. Obtaining [`frame_metadata::RuntimeMetadataPrefixed`], and then
 //!    using `.try_into()` to convert it into [`Metadata`].
+//! 3. With the `legacy` feature enabled, obtaining V9 to V13 metadata and some type
+//!    definitions describing the types it names, and then calling `Metadata::from_legacy()`.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
@@ -33,6 +35,10 @@ use utils::{ordered_map::OrderedMap, validation::outer_enum_hashes::OuterEnumHas
 type ArcStr = Arc<str>;
 
 use crate::utils::validation::{get_custom_value_hash, HASH_LEN};
+#[cfg(feature = "legacy")]
+pub use from_into::legacy::{
+    LegacySignedExtension, LegacyTypeDef, LegacyTypes, LegacyVariant, LegacyVariantFields,
+};
 pub use from_into::TryFromError;
 pub use utils::validation::MetadataHasher;
 
@@ -47,6 +53,10 @@ pub struct Metadata {
     pallets: OrderedMap<ArcStr, PalletMetadataInner>,
     /// Find the location in the pallet Vec by pallet index.
     pallets_by_index: HashMap<u8, usize>,
+    /// Find the location in the pallet Vec by the index that calls are encoded with.
+    pallets_by_call_index: HashMap<u8, usize>,
+    /// Find the location in the pallet Vec by the index that events are encoded with.
+    pallets_by_event_index: HashMap<u8, usize>,
     /// Metadata of the extrinsic.
     extrinsic: ExtrinsicMetadata,
     /// The type ID of the `Runtime` type.
@@ -102,8 +112,27 @@ impl Metadata {
 
     /// Access a pallet given its encoded variant index.
     pub fn pallet_by_index(&self, variant_index: u8) -> Option<PalletMetadata<'_>> {
-        let inner = self
-            .pallets_by_index
+        self.pallet_by_position(&self.pallets_by_index, variant_index)
+    }
+
+    /// Access a pallet given the index that its calls are encoded with. This is identical
+    /// to [`Metadata::pallet_by_index()`] unless the metadata was converted from V11 or earlier.
+    pub fn pallet_by_call_index(&self, variant_index: u8) -> Option<PalletMetadata<'_>> {
+        self.pallet_by_position(&self.pallets_by_call_index, variant_index)
+    }
+
+    /// Access a pallet given the index that its events are encoded with. This is identical
+    /// to [`Metadata::pallet_by_index()`] unless the metadata was converted from V11 or earlier.
+    pub fn pallet_by_event_index(&self, variant_index: u8) -> Option<PalletMetadata<'_>> {
+        self.pallet_by_position(&self.pallets_by_event_index, variant_index)
+    }
+
+    fn pallet_by_position(
+        &self,
+        positions: &HashMap<u8, usize>,
+        variant_index: u8,
+    ) -> Option<PalletMetadata<'_>> {
+        let inner = positions
             .get(&variant_index)
             .and_then(|i| self.pallets.get_by_index(*i))?;
 
@@ -113,6 +142,21 @@ impl Metadata {
         })
     }
 
+    /// Rebuild the lookups from each kind of pallet index to the pallet's position.
+    fn index_pallets(&mut self) {
+        let pallets = self.pallets.values().iter().enumerate();
+        self.pallets_by_index = pallets.clone().map(|(pos, p)| (p.index, pos)).collect();
+        self.pallets_by_call_index = pallets
+            .clone()
+            .filter(|(_, p)| p.call_ty.is_some())
+            .map(|(pos, p)| (p.call_index, pos))
+            .collect();
+        self.pallets_by_event_index = pallets
+            .filter(|(_, p)| p.event_ty.is_some())
+            .map(|(pos, p)| (p.event_index, pos))
+            .collect();
+    }
+
     /// Access a pallet given its name.
     pub fn pallet_by_name(&self, pallet_name: &str) -> Option<PalletMetadata<'_>> {
         let inner = self.pallets.get_by_key(pallet_name)?;
@@ -191,6 +235,18 @@ impl<'a> PalletMetadata<'a> {
         self.inner.index
     }
 
+    /// The index that this pallet's calls are encoded with. This is identical to
+    /// [`PalletMetadata::index()`] unless the metadata was converted from V11 or earlier.
+    pub fn call_index(&self) -> u8 {
+        self.inner.call_index
+    }
+
+    /// The index that this pallet's events are encoded with. This is identical to
+    /// [`PalletMetadata::index()`] unless the metadata was converted from V11 or earlier.
+    pub fn event_index(&self) -> u8 {
+        self.inner.event_index
+    }
+
     /// The pallet docs.
     pub fn docs(&self) -> &'a [String] {
         &self.inner.docs
@@ -300,6 +356,10 @@ struct PalletMetadataInner {
     name: ArcStr,
     /// Pallet index.
     index: u8,
+    /// The index that calls are encoded with.
+    call_index: u8,
+    /// The index that events are encoded with.
+    event_index: u8,
     /// Pallet storage metadata.
     storage: Option<StorageMetadata>,
     /// Type ID for the pallet Call enum.
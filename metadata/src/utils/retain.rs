@@ -228,14 +228,8 @@ pub fn retain_metadata<F, G>(
         should_retain
     });
 
-    // We index pallets by their u8 index for easy access. Rebuild this index.
-    metadata.pallets_by_index = metadata
-        .pallets
-        .values()
-        .iter()
-        .enumerate()
-        .map(|(pos, p)| (p.index, pos))
-        .collect();
+    // We index pallets by their u8 indexes for easy access. Rebuild these indexes.
+    metadata.index_pallets();
 
     // Keep the extrinsic stuff referenced in our metadata.
     collect_extrinsic_types(&metadata.extrinsic, &mut type_ids);
@@ -8,11 +8,11 @@ mod derive_junction;
 mod secret_uri;
 
 // No need for the cfg other than to avoid an unused_imports lint warning.
-#[cfg(any(feature = "sr25519", feature = "ecdsa"))]
+#[cfg(any(feature = "sr25519", feature = "ecdsa", feature = "ed25519"))]
 mod seed_from_entropy;
 
 pub use derive_junction::DeriveJunction;
 pub use secret_uri::{SecretUri, SecretUriError, DEV_PHRASE};
 
-#[cfg(any(feature = "sr25519", feature = "ecdsa"))]
+#[cfg(any(feature = "sr25519", feature = "ecdsa", feature = "ed25519"))]
 pub use seed_from_entropy::seed_from_entropy;
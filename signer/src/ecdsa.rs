@@ -338,6 +338,48 @@ mod subxt_compat {
     }
 }
 
+// Import and export `Keypair`s in the encrypted JSON format used by polkadot-js.
+#[cfg(feature = "polkadot-js-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "polkadot-js-compat")))]
+mod polkadot_js_compat {
+    use super::*;
+
+    use crate::polkadot_js_compat::{self, Error};
+    use alloc::string::String;
+    use secrecy::SecretString;
+
+    const KEY_TYPE: &str = "ecdsa";
+
+    impl Keypair {
+        /// Decrypt a keypair from the encrypted JSON that polkadot-js exports accounts as,
+        /// given the password that it was encrypted with.
+        pub fn from_polkadot_js_json(json: &str, password: &SecretString) -> Result<Self, Error> {
+            let secret = polkadot_js_compat::decrypt(json, password, KEY_TYPE)?;
+            let secret: SecretKeyBytes = secret[..].try_into().map_err(|_| Error::InvalidKey)?;
+            Self::from_secret_key(secret).map_err(|_| Error::InvalidKey)
+        }
+
+        /// Encrypt this keypair with the given password into JSON that polkadot-js can import.
+        /// The account is given the name provided.
+        pub fn to_polkadot_js_json(
+            &self,
+            password: &SecretString,
+            name: &str,
+        ) -> Result<String, Error> {
+            let secret = zeroize::Zeroizing::new(self.0.secret_bytes());
+            let public = self.public_key().0;
+            polkadot_js_compat::encrypt(
+                &secret[..],
+                &public,
+                KEY_TYPE,
+                polkadot_js_compat::ss58_address(&sp_crypto_hashing::blake2_256(&public)),
+                name,
+                password,
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
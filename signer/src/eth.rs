@@ -292,6 +292,47 @@ mod subxt_compat {
     }
 }
 
+// Import and export `Keypair`s in the encrypted JSON format used by polkadot-js.
+#[cfg(feature = "polkadot-js-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "polkadot-js-compat")))]
+mod polkadot_js_compat {
+    use super::*;
+
+    use crate::polkadot_js_compat::{self, Error};
+    use alloc::string::ToString;
+    use secrecy::SecretString;
+
+    const KEY_TYPE: &str = "ethereum";
+
+    impl Keypair {
+        /// Decrypt a keypair from the encrypted JSON that polkadot-js exports accounts as,
+        /// given the password that it was encrypted with.
+        pub fn from_polkadot_js_json(json: &str, password: &SecretString) -> Result<Self, Error> {
+            let secret = polkadot_js_compat::decrypt(json, password, KEY_TYPE)?;
+            let secret: SecretKeyBytes = secret[..].try_into().map_err(|_| Error::InvalidKey)?;
+            Self::from_secret_key(secret).map_err(|_| Error::InvalidKey)
+        }
+
+        /// Encrypt this keypair with the given password into JSON that polkadot-js can import.
+        /// The account is given the name provided.
+        pub fn to_polkadot_js_json(
+            &self,
+            password: &SecretString,
+            name: &str,
+        ) -> Result<String, Error> {
+            let secret = zeroize::Zeroizing::new(self.0 .0.secret_bytes());
+            polkadot_js_compat::encrypt(
+                &secret[..],
+                &self.public_key().0,
+                KEY_TYPE,
+                self.account_id().to_string(),
+                name,
+                password,
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use bip39::Mnemonic;
@@ -32,11 +32,21 @@ pub mod sr25519;
 #[cfg_attr(docsrs, doc(cfg(feature = "ecdsa")))]
 pub mod ecdsa;
 
+// An ed25519 key pair implementation.
+#[cfg(feature = "ed25519")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ed25519")))]
+pub mod ed25519;
+
 // An ethereum signer implementation.
 #[cfg(feature = "unstable-eth")]
 #[cfg_attr(docsrs, doc(cfg(feature = "unstable-eth")))]
 pub mod eth;
 
+// Import and export keys in the encrypted JSON format used by polkadot-js.
+#[cfg(feature = "polkadot-js-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "polkadot-js-compat")))]
+pub mod polkadot_js_compat;
+
 // Re-export useful bits and pieces for generating a Pair from a phrase,
 // namely the Mnemonic struct.
 pub use bip39;
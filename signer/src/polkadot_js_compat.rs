@@ -0,0 +1,380 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Support for the encrypted JSON keystore format that polkadot-js uses to import and
+//! export accounts. Keys are encrypted with `xsalsa20-poly1305`, using a key derived
+//! from a password via `scrypt`.
+//!
+//! Use functions like [`crate::sr25519::Keypair::from_polkadot_js_json()`] and
+//! [`crate::sr25519::Keypair::to_polkadot_js_json()`] to work with this format.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use base64::Engine;
+use crypto_secretbox::{aead::Aead, Key, KeyInit, Nonce, XSalsa20Poly1305};
+use derive_more::{Display, From};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// polkadot-js wraps the secret and public key in a PKCS8 header and divider like so:
+// `PKCS8_HEADER ++ secret ++ PKCS8_DIVIDER ++ public`.
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+
+// The scrypt parameters that polkadot-js uses by default.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_SALT_LENGTH: usize = 32;
+// The salt followed by the scrypt N, p and r parameters, each as little endian u32s.
+const SCRYPT_PARAMS_LENGTH: usize = SCRYPT_SALT_LENGTH + 12;
+const NONCE_LENGTH: usize = 24;
+
+// The SS58 prefix that polkadot-js uses when the address format isn't specified.
+const SS58_PREFIX: u8 = 42;
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreJson {
+    encoded: String,
+    encoding: Encoding,
+    address: String,
+    #[serde(default)]
+    meta: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Encoding {
+    content: Vec<String>,
+    #[serde(rename = "type")]
+    ty: Vec<String>,
+    version: String,
+}
+
+/// Decrypt the given keystore JSON, checking that it contains a key of the type given,
+/// and return the secret key bytes.
+pub(crate) fn decrypt(
+    json: &str,
+    password: &SecretString,
+    key_type: &str,
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let keystore: KeystoreJson = serde_json::from_str(json)?;
+    if keystore.encoding.version != "3" || keystore.encoding.ty != ["scrypt", "xsalsa20-poly1305"] {
+        return Err(Error::UnsupportedEncoding);
+    }
+    match keystore.encoding.content.get(1) {
+        Some(found) if found == key_type => {}
+        found => {
+            return Err(Error::WrongKeyType {
+                expected: key_type.into(),
+                found: found.cloned().unwrap_or_default(),
+            })
+        }
+    }
+
+    let encoded = base64::engine::general_purpose::STANDARD.decode(&keystore.encoded)?;
+    if encoded.len() < SCRYPT_PARAMS_LENGTH + NONCE_LENGTH {
+        return Err(Error::InvalidFormat);
+    }
+    let (scrypt_params, rest) = encoded.split_at(SCRYPT_PARAMS_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    let (salt, params) = scrypt_params.split_at(SCRYPT_SALT_LENGTH);
+    let param = |idx: usize| {
+        let bytes = params[idx * 4..idx * 4 + 4]
+            .try_into()
+            .expect("4 bytes; qed");
+        u32::from_le_bytes(bytes)
+    };
+    // The parameters come from untrusted input, and could ask for an arbitrary amount of memory
+    // and time to derive the key. Like polkadot-js, we only accept the parameters that it uses.
+    let (n, p, r) = (param(0), param(1), param(2));
+    if (n, p, r) != (1 << SCRYPT_LOG_N, SCRYPT_P, SCRYPT_R) {
+        return Err(Error::InvalidScryptParams);
+    }
+    let key = derive_key(password, salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+
+    let plaintext = XSalsa20Poly1305::new(Key::from_slice(&key[..32]))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::Decryption)?;
+    let plaintext = Zeroizing::new(plaintext);
+
+    // The secret key must be the length that the key type says it is.
+    let pkcs8 = plaintext
+        .strip_prefix(&PKCS8_HEADER)
+        .ok_or(Error::InvalidFormat)?;
+    let secret_len = secret_key_len(key_type);
+    if pkcs8.get(secret_len..secret_len + PKCS8_DIVIDER.len()) != Some(&PKCS8_DIVIDER) {
+        return Err(Error::InvalidFormat);
+    }
+
+    Ok(Zeroizing::new(pkcs8[..secret_len].to_vec()))
+}
+
+/// Encrypt the given secret and public key bytes into keystore JSON.
+pub(crate) fn encrypt(
+    secret: &[u8],
+    public: &[u8],
+    key_type: &str,
+    address: String,
+    name: &str,
+    password: &SecretString,
+) -> Result<String, Error> {
+    let mut salt = [0u8; SCRYPT_SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    getrandom::getrandom(&mut salt)?;
+    getrandom::getrandom(&mut nonce)?;
+
+    let key = derive_key(password, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let plaintext = Zeroizing::new([&PKCS8_HEADER, secret, &PKCS8_DIVIDER, public].concat());
+    let ciphertext = XSalsa20Poly1305::new(Key::from_slice(&key[..32]))
+        .encrypt(Nonce::from_slice(&nonce), &plaintext[..])
+        .map_err(|_| Error::Encryption)?;
+
+    let encoded = [
+        &salt[..],
+        &(1u32 << SCRYPT_LOG_N).to_le_bytes(),
+        &SCRYPT_P.to_le_bytes(),
+        &SCRYPT_R.to_le_bytes(),
+        &nonce,
+        &ciphertext,
+    ]
+    .concat();
+
+    // polkadot-js also records `whenCreated`, but doesn't need it in order to import a key,
+    // and the current time isn't available everywhere (eg wasm32-unknown-unknown).
+    let mut meta = serde_json::Map::new();
+    meta.insert("genesisHash".into(), "".into());
+    meta.insert("name".into(), name.into());
+
+    let keystore = KeystoreJson {
+        encoded: base64::engine::general_purpose::STANDARD.encode(encoded),
+        encoding: Encoding {
+            content: ["pkcs8".into(), key_type.into()].into(),
+            ty: ["scrypt".into(), "xsalsa20-poly1305".into()].into(),
+            version: "3".into(),
+        },
+        address,
+        meta,
+    };
+    Ok(serde_json::to_string(&keystore)?)
+}
+
+/// Secret keys are 64 bytes for sr25519, and 32 bytes for everything else.
+fn secret_key_len(key_type: &str) -> usize {
+    match key_type {
+        "sr25519" => 64,
+        _ => 32,
+    }
+}
+
+/// Encode a 32 byte account ID as an SS58 address, as polkadot-js does.
+pub(crate) fn ss58_address(account_id: &[u8; 32]) -> String {
+    use base58::ToBase58;
+
+    let mut bytes = Vec::with_capacity(35);
+    bytes.push(SS58_PREFIX);
+    bytes.extend_from_slice(account_id);
+    let hash = sp_crypto_hashing::blake2_512(&[&b"SS58PRE"[..], &bytes].concat());
+    bytes.extend_from_slice(&hash[..2]);
+    bytes.to_base58()
+}
+
+fn derive_key(
+    password: &SecretString,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<Zeroizing<[u8; 64]>, Error> {
+    let params = scrypt::Params::new(log_n, r, p, 64).map_err(|_| Error::InvalidScryptParams)?;
+    let mut key = Zeroizing::new([0u8; 64]);
+    scrypt::scrypt(
+        password.expose_secret().as_bytes(),
+        salt,
+        &params,
+        &mut key[..],
+    )
+    .map_err(|_| Error::InvalidScryptParams)?;
+    Ok(key)
+}
+
+/// An error handed back if importing or exporting polkadot-js keystore JSON fails.
+#[derive(Debug, Display, From)]
+pub enum Error {
+    /// The JSON could not be parsed.
+    #[display(fmt = "Cannot parse keystore JSON: {_0}")]
+    Json(serde_json::Error),
+    /// The encoded key is not valid base64.
+    #[display(fmt = "Cannot decode base64 encoded key: {_0}")]
+    Base64(base64::DecodeError),
+    /// The key is encrypted in a way that we don't support.
+    #[display(fmt = "Unsupported key encoding; expected version 3 scrypt and xsalsa20-poly1305")]
+    UnsupportedEncoding,
+    /// The keystore holds a different type of key than was expected.
+    #[display(fmt = "Expected a {expected} key but found a {found} key")]
+    #[from(ignore)]
+    WrongKeyType {
+        /// The type of key expected.
+        expected: String,
+        /// The type of key found.
+        found: String,
+    },
+    /// The scrypt parameters are invalid, or not those that polkadot-js uses.
+    #[display(fmt = "Invalid or unsupported scrypt parameters")]
+    InvalidScryptParams,
+    /// The key could not be decrypted.
+    #[display(fmt = "Cannot decrypt key; is the password correct?")]
+    Decryption,
+    /// The key could not be encrypted.
+    #[display(fmt = "Cannot encrypt key")]
+    Encryption,
+    /// The decrypted key is not in the expected format.
+    #[display(fmt = "The decrypted key is not in the expected format")]
+    InvalidFormat,
+    /// The decrypted key is not a valid key of the expected type.
+    #[display(fmt = "The decrypted key is not valid")]
+    InvalidKey,
+    /// Random bytes could not be generated.
+    #[display(fmt = "Cannot generate random bytes: {_0}")]
+    Random(getrandom::Error),
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn password(password: &str) -> SecretString {
+        SecretString::new(password.into())
+    }
+
+    #[cfg(feature = "sr25519")]
+    #[test]
+    fn check_decrypts_polkadot_js_json() {
+        use crate::sr25519;
+
+        // The `//Alice` dev account, exported from polkadot-js with the password "whoisalice".
+        let json = r#"{"encoded":"DumgApKCTqoCty1OZW/8WS+sgo6RdpHhCwAkA2IoDBMAgAAAAQAAAAgAAAB6IG/q24EeVf0JqWqcBd5m2tKq5BlyY84IQ8oamLn9DZe9Ouhgunr7i36J1XxUnTI801axqL/ym1gil0U8440Qvj0lFVKwGuxq38zuifgoj0B3Yru0CI6QKEvQPU5xxj4MpyxdSxP+2PnTzYao0HDH0fulaGvlAYXfqtU89xrx2/z9z7IjSwS3oDFPXRQ9kAdDebtyCVreZ9Otw9v3","encoding":{"content":["pkcs8","sr25519"],"type":["scrypt","xsalsa20-poly1305"],"version":"3"},"address":"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY","meta":{"genesisHash":"","name":"Alice","whenCreated":1718265838755}}"#;
+
+        let keypair = sr25519::Keypair::from_polkadot_js_json(json, &password("whoisalice"))
+            .expect("should decrypt");
+        assert_eq!(keypair.public_key().0, sr25519::dev::alice().public_key().0);
+        assert_eq!(
+            ss58_address(&keypair.public_key().0),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+
+        assert!(matches!(
+            sr25519::Keypair::from_polkadot_js_json(json, &password("wrong")),
+            Err(Error::Decryption)
+        ));
+    }
+
+    #[cfg(feature = "sr25519")]
+    #[test]
+    fn check_rejects_other_scrypt_params() {
+        use crate::sr25519;
+
+        let json = sr25519::dev::alice()
+            .to_polkadot_js_json(&password("whoisalice"), "Alice")
+            .unwrap();
+        let mut keystore: KeystoreJson = serde_json::from_str(&json).unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD
+            .decode(&keystore.encoded)
+            .unwrap();
+
+        // N, p and r follow the salt. Anything which would need more memory or time to derive
+        // the key than the polkadot-js defaults (or less) is rejected.
+        for (idx, value) in [(0, 1u32 << 20), (0, 1 << 10), (1, 2), (2, 1 << 16)] {
+            let mut encoded = encoded.clone();
+            let offset = SCRYPT_SALT_LENGTH + idx * 4;
+            encoded[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            keystore.encoded = base64::engine::general_purpose::STANDARD.encode(encoded);
+
+            let json = serde_json::to_string(&keystore).unwrap();
+            assert!(matches!(
+                sr25519::Keypair::from_polkadot_js_json(&json, &password("whoisalice")),
+                Err(Error::InvalidScryptParams)
+            ));
+        }
+    }
+
+    #[cfg(feature = "sr25519")]
+    #[test]
+    fn check_sr25519_round_trip() {
+        use crate::sr25519;
+
+        let keypair = sr25519::dev::alice();
+        let json = keypair
+            .to_polkadot_js_json(&password("secret"), "Alice")
+            .unwrap();
+        let decrypted =
+            sr25519::Keypair::from_polkadot_js_json(&json, &password("secret")).unwrap();
+        assert_eq!(keypair.public_key().0, decrypted.public_key().0);
+
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            json["address"],
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(json["meta"]["name"], "Alice");
+    }
+
+    #[cfg(all(feature = "sr25519", feature = "ecdsa"))]
+    #[test]
+    fn check_rejects_secret_of_wrong_length_for_key_type() {
+        use crate::{ecdsa, sr25519};
+
+        // Claim that an (encrypted 64 byte) sr25519 secret is a 32 byte ecdsa one.
+        let json = sr25519::dev::alice()
+            .to_polkadot_js_json(&password("secret"), "Alice")
+            .unwrap();
+        let mut keystore: KeystoreJson = serde_json::from_str(&json).unwrap();
+        keystore.encoding.content[1] = "ecdsa".into();
+        let json = serde_json::to_string(&keystore).unwrap();
+
+        assert!(matches!(
+            ecdsa::Keypair::from_polkadot_js_json(&json, &password("secret")),
+            Err(Error::InvalidFormat)
+        ));
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn check_ecdsa_round_trip() {
+        use crate::ecdsa;
+
+        let keypair = ecdsa::dev::alice();
+        let json = keypair
+            .to_polkadot_js_json(&password("secret"), "Alice")
+            .unwrap();
+        let decrypted = ecdsa::Keypair::from_polkadot_js_json(&json, &password("secret")).unwrap();
+        assert_eq!(keypair, decrypted);
+
+        // Keys of the wrong type are rejected.
+        #[cfg(feature = "sr25519")]
+        assert!(matches!(
+            crate::sr25519::Keypair::from_polkadot_js_json(&json, &password("secret")),
+            Err(Error::WrongKeyType { .. })
+        ));
+    }
+
+    #[cfg(feature = "unstable-eth")]
+    #[test]
+    fn check_eth_round_trip() {
+        use crate::eth;
+
+        let keypair = eth::dev::alith();
+        let json = keypair
+            .to_polkadot_js_json(&password("secret"), "Alith")
+            .unwrap();
+        let decrypted = eth::Keypair::from_polkadot_js_json(&json, &password("secret")).unwrap();
+        assert_eq!(keypair, decrypted);
+
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["address"], keypair.account_id().to_string());
+    }
+}
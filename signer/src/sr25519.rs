@@ -314,6 +314,49 @@ mod subxt_compat {
     }
 }
 
+// Import and export `Keypair`s in the encrypted JSON format used by polkadot-js.
+#[cfg(feature = "polkadot-js-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "polkadot-js-compat")))]
+mod polkadot_js_compat {
+    use super::*;
+
+    use crate::polkadot_js_compat::{self, Error};
+    use alloc::string::String;
+    use secrecy::SecretString;
+
+    const KEY_TYPE: &str = "sr25519";
+
+    impl Keypair {
+        /// Decrypt a keypair from the encrypted JSON that polkadot-js exports accounts as,
+        /// given the password that it was encrypted with.
+        pub fn from_polkadot_js_json(json: &str, password: &SecretString) -> Result<Self, Error> {
+            let secret = polkadot_js_compat::decrypt(json, password, KEY_TYPE)?;
+            let secret = schnorrkel::SecretKey::from_ed25519_bytes(&secret)
+                .map_err(|_| Error::InvalidKey)?;
+            Ok(Keypair(secret.into()))
+        }
+
+        /// Encrypt this keypair with the given password into JSON that polkadot-js can import.
+        /// The account is given the name provided.
+        pub fn to_polkadot_js_json(
+            &self,
+            password: &SecretString,
+            name: &str,
+        ) -> Result<String, Error> {
+            let secret = zeroize::Zeroizing::new(self.0.secret.to_ed25519_bytes());
+            let public = self.public_key().0;
+            polkadot_js_compat::encrypt(
+                &secret[..],
+                &public,
+                KEY_TYPE,
+                polkadot_js_compat::ss58_address(&public),
+                name,
+                password,
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -1,6 +1,6 @@
 #![cfg(target_arch = "wasm32")]
 
-use subxt_signer::{ecdsa, eth, sr25519};
+use subxt_signer::{ecdsa, ed25519, eth, sr25519};
 use wasm_bindgen_test::*;
 
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
@@ -43,6 +43,18 @@ async fn wasm_ecdsa_signing_works() {
     ));
 }
 
+#[wasm_bindgen_test]
+async fn wasm_ed25519_signing_works() {
+    let alice = ed25519::dev::alice();
+
+    let signature = alice.sign(b"Hello there");
+    assert!(ed25519::verify(
+        &signature,
+        b"Hello there",
+        &alice.public_key()
+    ));
+}
+
 #[wasm_bindgen_test]
 async fn wasm_eth_signing_works() {
     let alice = eth::dev::alith();
@@ -0,0 +1,537 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! This module exposes a backend implementation which relies on the `archive_unstable_*`
+//! RPC methods to look up historic blocks, making it suitable for indexers and other tools
+//! which need access to arbitrary blocks rather than just those at the head of the chain.
+
+use crate::backend::legacy::LegacyBackend;
+use crate::backend::unstable::rpc_methods::{
+    ArchiveStorageQuery, StorageQueryType, StorageResult, StorageResultType, UnstableRpcMethods,
+};
+use crate::backend::{
+    rpc::RpcClient, Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::error::{Error, RpcError};
+use crate::Config;
+use async_trait::async_trait;
+use derive_where::derive_where;
+use futures::{future, stream, Stream, TryStreamExt};
+use std::sync::Arc;
+
+/// Configure and build an [`ArchiveBackend`].
+pub struct ArchiveBackendBuilder<T> {
+    live_backend: Option<Arc<dyn Backend<T>>>,
+}
+
+impl<T: Config + Send + Sync + 'static> Default for ArchiveBackendBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync + 'static> ArchiveBackendBuilder<T> {
+    /// Create a new [`ArchiveBackendBuilder`].
+    pub fn new() -> Self {
+        Self { live_backend: None }
+    }
+
+    /// The `archive_unstable_*` methods can only look up existing blocks, and so subscribing
+    /// to new blocks and runtime versions, submitting transactions and asking for the latest
+    /// finalized block is handed off to another backend. By default, this is a
+    /// [`LegacyBackend`] which uses the same [`RpcClient`].
+    pub fn live_backend<B: Backend<T>>(mut self, backend: B) -> Self {
+        self.live_backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Given an [`RpcClient`] to use to make requests, this returns an [`ArchiveBackend`],
+    /// which implements the [`Backend`] trait.
+    pub fn build(self, client: impl Into<RpcClient>) -> ArchiveBackend<T> {
+        let client = client.into();
+        let live_backend = self
+            .live_backend
+            .unwrap_or_else(|| Arc::new(LegacyBackend::builder().build(client.clone())));
+
+        ArchiveBackend {
+            methods: UnstableRpcMethods::new(client),
+            live_backend,
+        }
+    }
+}
+
+/// A backend which uses the `archive_unstable_*` RPC methods to fetch storage, headers and
+/// bodies, and to call runtime APIs, at any block known to the node. Unlike the
+/// [`crate::backend::unstable::UnstableBackend`], blocks do not need to be pinned in order
+/// to be accessed.
+#[derive_where(Clone)]
+pub struct ArchiveBackend<T> {
+    methods: UnstableRpcMethods<T>,
+    live_backend: Arc<dyn Backend<T>>,
+}
+
+impl<T> std::fmt::Debug for ArchiveBackend<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveBackend").finish_non_exhaustive()
+    }
+}
+
+impl<T: Config + Send + Sync + 'static> ArchiveBackend<T> {
+    /// Configure and construct an [`ArchiveBackend`].
+    pub fn builder() -> ArchiveBackendBuilder<T> {
+        ArchiveBackendBuilder::new()
+    }
+
+    async fn fetch_storage_values(
+        &self,
+        child_key: Option<&[u8]>,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let items =
+            fetch_storage_items(&self.methods, child_key, keys, at, StorageQueryType::Value)
+                .await?;
+        let values = items.into_iter().filter_map(|item| match item.result {
+            StorageResultType::Value(value) => Some(Ok(StorageResponse {
+                key: item.key.0,
+                value: value.0,
+            })),
+            _ => None,
+        });
+
+        Ok(StreamOf(Box::pin(stream::iter(values))))
+    }
+
+    async fn fetch_storage_descendant_keys(
+        &self,
+        child_key: Option<&[u8]>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        // Ask for hashes rather than values, since they are likely to be smaller.
+        let keys = storage_descendants(
+            self.methods.clone(),
+            child_key.map(|k| k.to_vec()),
+            key,
+            None,
+            at,
+            StorageQueryType::DescendantsHashes,
+        )
+        .map_ok(|item| item.key.0);
+
+        Ok(StreamOf(Box::pin(keys)))
+    }
+
+    async fn fetch_storage_descendant_values(
+        &self,
+        child_key: Option<&[u8]>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let values = storage_descendants(
+            self.methods.clone(),
+            child_key.map(|k| k.to_vec()),
+            key,
+            None,
+            at,
+            StorageQueryType::DescendantsValues,
+        )
+        .try_filter_map(|item| {
+            future::ready(Ok(match item.result {
+                StorageResultType::Value(value) => Some(StorageResponse {
+                    key: item.key.0,
+                    value: value.0,
+                }),
+                _ => None,
+            }))
+        });
+
+        Ok(StreamOf(Box::pin(values)))
+    }
+}
+
+impl<T: Config> super::sealed::Sealed for ArchiveBackend<T> {}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static> Backend<T> for ArchiveBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_values(None, keys, at).await
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.fetch_storage_descendant_keys(None, key, at).await
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_descendant_values(None, key, at).await
+    }
+
+    async fn storage_fetch_descendant_values_after(
+        &self,
+        key: Vec<u8>,
+        start_key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        let values = storage_descendants(
+            self.methods.clone(),
+            None,
+            key,
+            Some(start_key),
+            at,
+            StorageQueryType::DescendantsValues,
+        )
+        .try_filter_map(|item| {
+            future::ready(Ok(match item.result {
+                StorageResultType::Value(value) => Some(StorageResponse {
+                    key: item.key.0,
+                    value: value.0,
+                }),
+                _ => None,
+            }))
+        });
+
+        Ok(Some(StreamOf(Box::pin(values))))
+    }
+
+    async fn storage_fetch_descendant_hashes(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let hashes = storage_descendants(
+            self.methods.clone(),
+            None,
+            key,
+            None,
+            at,
+            StorageQueryType::DescendantsHashes,
+        )
+        .try_filter_map(|item| {
+            future::ready(Ok(match item.result {
+                StorageResultType::Hash(hash) => Some(StorageResponse {
+                    key: item.key.0,
+                    value: hash.0,
+                }),
+                _ => None,
+            }))
+        });
+
+        Ok(StreamOf(Box::pin(hashes)))
+    }
+
+    async fn storage_fetch_closest_descendant_merkle_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        let items = fetch_storage_items(
+            &self.methods,
+            None,
+            keys,
+            at,
+            StorageQueryType::ClosestDescendantMerkleValue,
+        )
+        .await?;
+        let values = items.into_iter().filter_map(|item| match item.result {
+            StorageResultType::ClosestDescendantMerkleValue(value) => Some(Ok(StorageResponse {
+                key: item.key.0,
+                value: value.0,
+            })),
+            _ => None,
+        });
+
+        Ok(Some(StreamOf(Box::pin(stream::iter(values)))))
+    }
+
+    async fn storage_fetch_read_proof(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.live_backend.storage_fetch_read_proof(keys, at).await
+    }
+
+    async fn stream_storage_value(
+        &self,
+        key: Vec<u8>,
+    ) -> Result<StreamOfResults<(BlockRef<T::Hash>, Option<Vec<u8>>)>, Error> {
+        self.live_backend.stream_storage_value(key).await
+    }
+
+    async fn storage_fetch_child_values(
+        &self,
+        child_key: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_values(Some(&child_key), keys, at).await
+    }
+
+    async fn storage_fetch_child_descendant_keys(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.fetch_storage_descendant_keys(Some(&child_key), key, at)
+            .await
+    }
+
+    async fn storage_fetch_child_descendant_values(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_descendant_values(Some(&child_key), key, at)
+            .await
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        self.methods
+            .archive_unstable_hash_by_height(0)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Other("The node did not return a genesis hash".into()))
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        self.methods.archive_unstable_header(at).await
+    }
+
+    async fn block_body(&self, at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        self.methods.archive_unstable_body(at).await
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        self.live_backend.latest_finalized_block_ref().await
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        self.live_backend.current_runtime_version().await
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        self.live_backend.stream_runtime_version().await
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.live_backend.stream_all_block_headers().await
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.live_backend.stream_best_block_headers().await
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        self.live_backend.stream_finalized_block_headers().await
+    }
+
+    async fn submit_transaction(
+        &self,
+        extrinsic: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        self.live_backend.submit_transaction(extrinsic).await
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        call_parameters: Option<&[u8]>,
+        at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        self.methods
+            .archive_unstable_call(at, method, call_parameters.unwrap_or_default())
+            .await
+    }
+}
+
+/// Make the same type of query for each of the given keys.
+async fn fetch_storage_items<T: Config>(
+    methods: &UnstableRpcMethods<T>,
+    child_key: Option<&[u8]>,
+    keys: Vec<Vec<u8>>,
+    at: T::Hash,
+    query_type: StorageQueryType,
+) -> Result<Vec<StorageResult>, Error> {
+    let mut pending = keys;
+    let mut items = Vec::new();
+
+    // The node may only handle some of the queries we give it each time,
+    // so keep sending the rest until they have all been handled.
+    while !pending.is_empty() {
+        let queries = pending.iter().map(|key| ArchiveStorageQuery {
+            key: &**key,
+            query_type: query_type.clone(),
+            pagination_start_key: None,
+        });
+        let res = methods
+            .archive_unstable_storage(at, queries, child_key)
+            .await?;
+
+        if res.discarded_items >= pending.len() {
+            return Err(RpcError::request_rejected("limit reached").into());
+        }
+        pending.drain(..pending.len() - res.discarded_items);
+        items.extend(res.items);
+    }
+
+    Ok(items)
+}
+
+/// Stream the results of a descendant query, requesting further pages
+/// from the node until it hands back no more results.
+fn storage_descendants<T: Config>(
+    methods: UnstableRpcMethods<T>,
+    child_key: Option<Vec<u8>>,
+    key: Vec<u8>,
+    start_key: Option<Vec<u8>>,
+    at: T::Hash,
+    query_type: StorageQueryType,
+) -> impl Stream<Item = Result<StorageResult, Error>> + Send + 'static {
+    let state = Some((methods, key, start_key));
+
+    stream::try_unfold(state, move |state| {
+        let query_type = query_type.clone();
+        let child_key = child_key.clone();
+        async move {
+            let Some((methods, key, start_key)) = state else {
+                return Ok(None);
+            };
+
+            let query = ArchiveStorageQuery {
+                key: &*key,
+                query_type,
+                pagination_start_key: start_key.as_deref(),
+            };
+            let res = methods
+                .archive_unstable_storage(at, [query], child_key.as_deref())
+                .await?;
+            if res.discarded_items > 0 {
+                return Err(RpcError::request_rejected("limit reached").into());
+            }
+
+            // Pages should begin after the start key, but don't rely on this.
+            let items: Vec<_> = res
+                .items
+                .into_iter()
+                .filter(|item| Some(&*item.key.0) != start_key.as_deref())
+                .collect();
+
+            let next_state = items
+                .last()
+                .map(|last| (methods, key, Some(last.key.0.clone())));
+            Ok::<_, Error>(Some((items, next_state)))
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
+    use crate::SubstrateConfig;
+    use futures::FutureExt;
+    use std::collections::BTreeMap;
+
+    /// Serves `archive_unstable_storage` descendant queries from a map, a couple of items at a time.
+    struct MockArchive(BTreeMap<Vec<u8>, Vec<u8>>);
+
+    impl RpcClientT for MockArchive {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            assert_eq!(method, "archive_unstable_storage");
+            let (_, queries, _): (String, Vec<ArchiveStorageQuery<String>>, Option<String>) =
+                serde_json::from_str(params.unwrap().get()).unwrap();
+            let query = &queries[0];
+            let prefix = hex::decode(query.key.trim_start_matches("0x")).unwrap();
+            let start = query
+                .pagination_start_key
+                .as_ref()
+                .map(|k| hex::decode(k.trim_start_matches("0x")).unwrap());
+
+            let items: Vec<_> = self
+                .0
+                .iter()
+                .filter(|(k, _)| k.starts_with(&prefix) && start.as_ref() < Some(*k))
+                .take(2)
+                .map(|(k, v)| {
+                    serde_json::json!({
+                        "key": format!("0x{}", hex::encode(k)),
+                        "value": format!("0x{}", hex::encode(v)),
+                    })
+                })
+                .collect();
+
+            let res = serde_json::json!({ "result": items, "discardedItems": 0 });
+            let res = RawValue::from_string(res.to_string()).unwrap();
+            async move { Ok(res) }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            unimplemented!("Not used in this test")
+        }
+    }
+
+    #[tokio::test]
+    async fn paginates_descendant_values() {
+        let entries: BTreeMap<_, _> = [
+            (vec![1, 1], vec![10]),
+            (vec![1, 2], vec![20]),
+            (vec![1, 3], vec![30]),
+            (vec![1, 4, 1], vec![40]),
+            (vec![1, 5], vec![50]),
+            (vec![2, 1], vec![60]),
+        ]
+        .into_iter()
+        .collect();
+        let expected: Vec<_> = entries
+            .iter()
+            .filter(|(k, _)| k[0] == 1)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let backend = ArchiveBackend::<SubstrateConfig>::builder().build(MockArchive(entries));
+        let values: Vec<_> = backend
+            .storage_fetch_descendant_values(vec![1], Default::default())
+            .await
+            .unwrap()
+            .map_ok(|res| (res.key, res.value))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(values, expected);
+    }
+}
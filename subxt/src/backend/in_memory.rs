@@ -0,0 +1,139 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Storage and headers held in memory, for backends which serve them without a node.
+
+use super::{BlockRef, StorageResponse, StreamOf, StreamOfResults};
+use crate::config::{Config, Header};
+use codec::{Decode, Encode};
+use futures::stream;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// Storage entries held in memory, in key order.
+#[derive(Clone, Debug)]
+pub(crate) struct InMemoryStorage(BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryStorage {
+    /// Storage with nothing in it.
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.0.insert(key, value);
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
+        self.0.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Every entry, in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &Vec<u8>)> + '_ {
+        self.0.iter()
+    }
+
+    /// Every entry whose key starts with the given key, in key order.
+    pub fn descendants<'a>(
+        &'a self,
+        key: &'a [u8],
+    ) -> impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)> + 'a {
+        self.0
+            .range(key.to_vec()..)
+            .take_while(move |(k, _)| k.starts_with(key))
+    }
+
+    /// Hand back the values at the given keys, omitting any which don't exist.
+    pub fn values(&self, keys: Vec<Vec<u8>>) -> StreamOfResults<StorageResponse> {
+        let values: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.get(&key)?.clone();
+                Some(Ok(StorageResponse { key, value }))
+            })
+            .collect();
+        StreamOf(Box::pin(stream::iter(values)))
+    }
+
+    /// Hand back the keys underneath the given key.
+    pub fn descendant_keys(&self, key: Vec<u8>) -> StreamOfResults<Vec<u8>> {
+        let keys: Vec<_> = self.descendants(&key).map(|(k, _)| Ok(k.clone())).collect();
+        StreamOf(Box::pin(stream::iter(keys)))
+    }
+
+    /// Hand back the keys and values underneath the given key.
+    pub fn descendant_values(&self, key: Vec<u8>) -> StreamOfResults<StorageResponse> {
+        responses(self.descendants(&key))
+    }
+
+    /// Hand back the keys and values underneath the given key which come after the start key.
+    pub fn descendant_values_after(
+        &self,
+        key: Vec<u8>,
+        start_key: Vec<u8>,
+    ) -> StreamOfResults<StorageResponse> {
+        let after_start = self
+            .0
+            .range::<Vec<u8>, _>((Bound::Excluded(&start_key), Bound::Unbounded))
+            .skip_while(|(k, _)| **k < key)
+            .take_while(|(k, _)| k.starts_with(&key));
+        responses(after_start)
+    }
+}
+
+fn responses<'a>(
+    entries: impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)>,
+) -> StreamOfResults<StorageResponse> {
+    let values: Vec<_> = entries
+        .map(|(k, v)| {
+            Ok(StorageResponse {
+                key: k.clone(),
+                value: v.clone(),
+            })
+        })
+        .collect();
+    StreamOf(Box::pin(stream::iter(values)))
+}
+
+impl FromIterator<(Vec<u8>, Vec<u8>)> for InMemoryStorage {
+    fn from_iter<I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<(Vec<u8>, Vec<u8>)> for InMemoryStorage {
+    fn extend<I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+/// Headers aren't required to be `Clone`, but they are `Encode + Decode`.
+pub(crate) fn clone_header<T: Config>(header: &T::Header) -> T::Header {
+    Decode::decode(&mut &*header.encode()).expect("header can be decoded from its encoding")
+}
+
+/// Hand back a copy of each of the given headers alongside a reference to its block.
+pub(crate) fn headers_stream<'a, T: Config>(
+    headers: impl IntoIterator<Item = &'a T::Header>,
+) -> StreamOfResults<(T::Header, BlockRef<T::Hash>)> {
+    let headers: Vec<_> = headers
+        .into_iter()
+        .map(|header| {
+            let header = clone_header::<T>(header);
+            let hash = header.hash();
+            Ok((header, BlockRef::from_hash(hash)))
+        })
+        .collect();
+    StreamOf(Box::pin(stream::iter(headers)))
+}
@@ -112,6 +112,7 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
         let keys = StorageFetchDescendantKeysStream {
             at,
             key,
+            child_storage_key: None,
             storage_page_size: self.storage_page_size,
             methods: self.methods.clone(),
             done: Default::default(),
@@ -143,6 +144,136 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
         let keys_stream = StorageFetchDescendantKeysStream {
             at,
             key,
+            child_storage_key: None,
+            storage_page_size: self.storage_page_size,
+            methods: self.methods.clone(),
+            done: Default::default(),
+            keys_fut: Default::default(),
+            pagination_start_key: None,
+        };
+
+        Ok(StreamOf(Box::pin(StorageFetchDescendantValuesStream {
+            keys: keys_stream,
+            results_fut: None,
+            results: Default::default(),
+        })))
+    }
+
+    async fn storage_fetch_descendant_values_after(
+        &self,
+        key: Vec<u8>,
+        start_key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        let keys_stream = StorageFetchDescendantKeysStream {
+            at,
+            key,
+            child_storage_key: None,
+            storage_page_size: self.storage_page_size,
+            methods: self.methods.clone(),
+            done: Default::default(),
+            keys_fut: Default::default(),
+            pagination_start_key: Some(start_key),
+        };
+
+        Ok(Some(StreamOf(Box::pin(
+            StorageFetchDescendantValuesStream {
+                keys: keys_stream,
+                results_fut: None,
+                results: Default::default(),
+            },
+        ))))
+    }
+
+    async fn storage_fetch_read_proof(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let keys = keys.iter().map(|key| &**key);
+        let read_proof = self.methods.state_get_read_proof(keys, Some(at)).await?;
+        Ok(read_proof.proof.into_iter().map(|node| node.0).collect())
+    }
+
+    async fn stream_storage_value(
+        &self,
+        key: Vec<u8>,
+    ) -> Result<StreamOfResults<(BlockRef<T::Hash>, Option<Vec<u8>>)>, Error> {
+        let sub = self.methods.state_subscribe_storage([&*key]).await?;
+        let sub = sub.filter_map(move |r| {
+            let change = match r {
+                Err(e) => Some(Err(e)),
+                Ok(change_set) => {
+                    let block_ref = BlockRef::from_hash(change_set.block);
+                    change_set
+                        .changes
+                        .into_iter()
+                        .find(|(k, _)| k.0 == key)
+                        .map(|(_, value)| Ok((block_ref, value.map(|v| v.0))))
+                }
+            };
+            future::ready(change)
+        });
+        Ok(StreamOf(Box::pin(sub)))
+    }
+
+    async fn storage_fetch_child_values(
+        &self,
+        child_key: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let child_storage_key = prefixed_child_storage_key(&child_key);
+        let values = self
+            .methods
+            .childstate_get_storage_entries(&child_storage_key, keys.iter().map(|k| &**k), Some(at))
+            .await?;
+
+        // Filter out any keys that we didn't find a value for.
+        let values: Vec<_> = keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some(Ok(StorageResponse { key, value: value? })))
+            .collect();
+
+        Ok(StreamOf(Box::pin(stream::iter(values))))
+    }
+
+    async fn storage_fetch_child_descendant_keys(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        let keys = StorageFetchDescendantKeysStream {
+            at,
+            key,
+            child_storage_key: Some(prefixed_child_storage_key(&child_key)),
+            storage_page_size: self.storage_page_size,
+            methods: self.methods.clone(),
+            done: Default::default(),
+            keys_fut: Default::default(),
+            pagination_start_key: None,
+        };
+
+        let keys = keys.flat_map(|keys| match keys {
+            Err(e) => Either::Left(stream::iter(std::iter::once(Err(e)))),
+            Ok(keys) => Either::Right(stream::iter(keys.into_iter().map(Ok))),
+        });
+
+        Ok(StreamOf(Box::pin(keys)))
+    }
+
+    async fn storage_fetch_child_descendant_values(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let keys_stream = StorageFetchDescendantKeysStream {
+            at,
+            key,
+            child_storage_key: Some(prefixed_child_storage_key(&child_key)),
             storage_page_size: self.storage_page_size,
             methods: self.methods.clone(),
             done: Default::default(),
@@ -322,6 +453,11 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for LegacyBackend<T> {
             .state_call(method, call_parameters, Some(at))
             .await
     }
+
+    async fn dry_run(&self, extrinsic: &[u8], at: T::Hash) -> Result<Vec<u8>, Error> {
+        let result = self.methods.dry_run(extrinsic, Some(at)).await?;
+        Ok(result.0)
+    }
 }
 
 /// Note: This is exposed for testing but is not considered stable and may change
@@ -378,6 +514,8 @@ where
 pub struct StorageFetchDescendantKeysStream<T: Config> {
     methods: LegacyRpcMethods<T>,
     key: Vec<u8>,
+    // The prefixed key of the child trie to look in, if not the main trie.
+    child_storage_key: Option<Vec<u8>>,
     at: T::Hash,
     // How many entries to ask for each time.
     storage_page_size: u32,
@@ -443,21 +581,44 @@ impl<T: Config> Stream for StorageFetchDescendantKeysStream<T> {
             let at = this.at;
             let storage_page_size = this.storage_page_size;
             let pagination_start_key = this.pagination_start_key.clone();
+            let child_storage_key = this.child_storage_key.clone();
             let keys_fut = async move {
-                methods
-                    .state_get_keys_paged(
-                        &key,
-                        storage_page_size,
-                        pagination_start_key.as_deref(),
-                        Some(at),
-                    )
-                    .await
+                match child_storage_key {
+                    Some(child_storage_key) => {
+                        methods
+                            .childstate_get_keys_paged(
+                                &child_storage_key,
+                                &key,
+                                storage_page_size,
+                                pagination_start_key.as_deref(),
+                                Some(at),
+                            )
+                            .await
+                    }
+                    None => {
+                        methods
+                            .state_get_keys_paged(
+                                &key,
+                                storage_page_size,
+                                pagination_start_key.as_deref(),
+                                Some(at),
+                            )
+                            .await
+                    }
+                }
             };
             this.keys_fut = Some(Box::pin(keys_fut));
         }
     }
 }
 
+/// The legacy RPC methods expect the key of a child trie in the default
+/// namespace to be prefixed, as it would be in the main trie.
+fn prefixed_child_storage_key(child_key: &[u8]) -> Vec<u8> {
+    const DEFAULT_CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+    [DEFAULT_CHILD_STORAGE_KEY_PREFIX, child_key].concat()
+}
+
 /// This provides a stream of values given some stream of keys.
 #[allow(clippy::type_complexity)]
 pub struct StorageFetchDescendantValuesStream<T: Config> {
@@ -511,7 +672,24 @@ impl<T: Config> Stream for StorageFetchDescendantValuesStream<T> {
                 Poll::Ready(Some(Ok(keys))) => {
                     let methods = this.keys.methods.clone();
                     let at = this.keys.at;
+                    let child_storage_key = this.keys.child_storage_key.clone();
                     let results_fut = async move {
+                        if let Some(child_storage_key) = child_storage_key {
+                            let values = methods
+                                .childstate_get_storage_entries(
+                                    &child_storage_key,
+                                    keys.iter().map(|k| &**k),
+                                    Some(at),
+                                )
+                                .await?;
+                            let values: VecDeque<_> = keys
+                                .into_iter()
+                                .zip(values)
+                                .filter_map(|(k, v)| Some((k, v?)))
+                                .collect();
+                            return Ok(Some(values));
+                        }
+
                         let keys = keys.iter().map(|k| &**k);
                         let values = methods.state_query_storage_at(keys, Some(at)).await?;
                         let values: VecDeque<_> = values
@@ -536,3 +714,225 @@ impl<T: Config> Stream for StorageFetchDescendantValuesStream<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
+    use crate::SubstrateConfig;
+    use futures::TryStreamExt;
+    use std::collections::BTreeMap;
+
+    /// Serves `childstate_*` requests from a single child trie.
+    struct MockChildState {
+        child_key: Vec<u8>,
+        entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        hex::decode(s.trim_start_matches("0x")).unwrap()
+    }
+
+    impl RpcClientT for MockChildState {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            let params: Vec<serde_json::Value> =
+                serde_json::from_str(params.unwrap().get()).unwrap();
+            let child_key = from_hex(params[0].as_str().unwrap());
+            assert_eq!(child_key, prefixed_child_storage_key(&self.child_key));
+
+            let res = match method {
+                "childstate_getKeysPaged" => {
+                    let prefix = from_hex(params[1].as_str().unwrap());
+                    let count = params[2].as_u64().unwrap() as usize;
+                    let start = params[3].as_str().map(from_hex);
+                    let keys: Vec<_> = self
+                        .entries
+                        .keys()
+                        .filter(|k| k.starts_with(&prefix) && start.as_ref() < Some(*k))
+                        .take(count)
+                        .map(|k| format!("0x{}", hex::encode(k)))
+                        .collect();
+                    serde_json::json!(keys)
+                }
+                "childstate_getStorageEntries" => {
+                    let values: Vec<_> = params[1]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|k| {
+                            let key = from_hex(k.as_str().unwrap());
+                            self.entries
+                                .get(&key)
+                                .map(|v| format!("0x{}", hex::encode(v)))
+                        })
+                        .collect();
+                    serde_json::json!(values)
+                }
+                _ => panic!("Unexpected method {method}"),
+            };
+
+            let res = RawValue::from_string(res.to_string()).unwrap();
+            async move { Ok(res) }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            unimplemented!("Not used in this test")
+        }
+    }
+
+    /// Serves `state_*` storage requests from the main trie, noting the start key of each page.
+    struct MockState {
+        entries: BTreeMap<Vec<u8>, Vec<u8>>,
+        start_keys: std::sync::Mutex<Vec<Option<Vec<u8>>>>,
+    }
+
+    impl RpcClientT for MockState {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            let params: Vec<serde_json::Value> =
+                serde_json::from_str(params.unwrap().get()).unwrap();
+
+            let res = match method {
+                "state_getKeysPaged" => {
+                    let prefix = from_hex(params[0].as_str().unwrap());
+                    let count = params[1].as_u64().unwrap() as usize;
+                    let start = params[2].as_str().map(from_hex);
+                    self.start_keys.lock().unwrap().push(start.clone());
+                    let keys: Vec<_> = self
+                        .entries
+                        .keys()
+                        .filter(|k| k.starts_with(&prefix) && start.as_ref() < Some(*k))
+                        .take(count)
+                        .map(|k| format!("0x{}", hex::encode(k)))
+                        .collect();
+                    serde_json::json!(keys)
+                }
+                "state_queryStorageAt" => {
+                    let changes: Vec<_> = params[0]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|k| {
+                            let key = from_hex(k.as_str().unwrap());
+                            let value = self
+                                .entries
+                                .get(&key)
+                                .map(|v| format!("0x{}", hex::encode(v)));
+                            serde_json::json!([k, value])
+                        })
+                        .collect();
+                    serde_json::json!([{ "block": params[1], "changes": changes }])
+                }
+                _ => panic!("Unexpected method {method}"),
+            };
+
+            let res = RawValue::from_string(res.to_string()).unwrap();
+            async move { Ok(res) }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            unimplemented!("Not used in this test")
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_values_after_start_key() {
+        let entries: BTreeMap<_, _> = (1..=5u8).map(|n| (vec![1, n], vec![n * 10])).collect();
+        let state = std::sync::Arc::new(MockState {
+            entries,
+            start_keys: Default::default(),
+        });
+
+        let backend = LegacyBackend::<SubstrateConfig>::builder()
+            .storage_page_size(2)
+            .build(state.clone());
+
+        let values: Vec<_> = backend
+            .storage_fetch_descendant_values_after(vec![1], vec![1, 2], Default::default())
+            .await
+            .unwrap()
+            .expect("legacy backend can begin from a start key")
+            .map_ok(|res| (res.key, res.value))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (vec![1, 3], vec![30]),
+                (vec![1, 4], vec![40]),
+                (vec![1, 5], vec![50])
+            ]
+        );
+
+        // Pagination begins at the start key rather than at the beginning:
+        assert_eq!(
+            *state.start_keys.lock().unwrap(),
+            vec![Some(vec![1, 2]), Some(vec![1, 4]), Some(vec![1, 5])]
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_child_storage() {
+        let entries: BTreeMap<_, _> = [
+            (vec![1, 1], vec![10]),
+            (vec![1, 2], vec![20]),
+            (vec![1, 3], vec![30]),
+            (vec![2, 1], vec![40]),
+        ]
+        .into_iter()
+        .collect();
+        let child_key = b"child".to_vec();
+
+        let backend = LegacyBackend::<SubstrateConfig>::builder()
+            .storage_page_size(2)
+            .build(MockChildState {
+                child_key: child_key.clone(),
+                entries,
+            });
+
+        let values: Vec<_> = backend
+            .storage_fetch_child_descendant_values(child_key.clone(), vec![1], Default::default())
+            .await
+            .unwrap()
+            .map_ok(|res| (res.key, res.value))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (vec![1, 1], vec![10]),
+                (vec![1, 2], vec![20]),
+                (vec![1, 3], vec![30])
+            ]
+        );
+
+        let values: Vec<_> = backend
+            .storage_fetch_child_values(child_key, vec![vec![2, 1], vec![3]], Default::default())
+            .await
+            .unwrap()
+            .map_ok(|res| (res.key, res.value))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(values, vec![(vec![2, 1], vec![40])]);
+    }
+}
@@ -91,6 +91,57 @@ impl<T: Config> LegacyRpcMethods<T> {
             .map_err(Into::into)
     }
 
+    /// Fetch the raw bytes for a given key in a child trie. The child storage key is the
+    /// prefixed key of the child trie, ie beginning with `:child_storage:default:`.
+    pub async fn childstate_get_storage(
+        &self,
+        child_storage_key: &[u8],
+        key: &[u8],
+        hash: Option<T::Hash>,
+    ) -> Result<Option<StorageData>, Error> {
+        let params = rpc_params![to_hex(child_storage_key), to_hex(key), hash];
+        let data: Option<Bytes> = self.client.request("childstate_getStorage", params).await?;
+        Ok(data.map(|b| b.0))
+    }
+
+    /// Fetch the raw bytes for each of the given keys in a child trie, handing back `None`
+    /// for any keys which have no value. See [`LegacyRpcMethods::childstate_get_storage()`].
+    pub async fn childstate_get_storage_entries(
+        &self,
+        child_storage_key: &[u8],
+        keys: impl IntoIterator<Item = &[u8]>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<Option<StorageData>>, Error> {
+        let keys: Vec<String> = keys.into_iter().map(to_hex).collect();
+        let params = rpc_params![to_hex(child_storage_key), keys, hash];
+        let data: Vec<Option<Bytes>> = self
+            .client
+            .request("childstate_getStorageEntries", params)
+            .await?;
+        Ok(data.into_iter().map(|b| b.map(|b| b.0)).collect())
+    }
+
+    /// Returns the keys with prefix in a child trie with pagination support.
+    /// Up to `count` keys will be returned.
+    /// If `start_key` is passed, return next keys in storage in lexicographic order.
+    /// See [`LegacyRpcMethods::childstate_get_storage()`].
+    pub async fn childstate_get_keys_paged(
+        &self,
+        child_storage_key: &[u8],
+        key: &[u8],
+        count: u32,
+        start_key: Option<&[u8]>,
+        at: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        let start_key = start_key.map(to_hex);
+        let params = rpc_params![to_hex(child_storage_key), to_hex(key), count, start_key, at];
+        let data: Vec<Bytes> = self
+            .client
+            .request("childstate_getKeysPaged", params)
+            .await?;
+        Ok(data.into_iter().map(|b| b.0).collect())
+    }
+
     /// Fetch the genesis hash
     pub async fn genesis_hash(&self) -> Result<T::Hash, Error> {
         let block_zero = 0u32;
@@ -297,6 +348,25 @@ impl<T: Config> LegacyRpcMethods<T> {
         Ok(subscription)
     }
 
+    /// Subscribe to changes in the values at the given storage keys. The first item emitted by
+    /// the stream contains the current values at every key, and subsequent items contain the
+    /// values of any keys which have changed in newly imported best blocks.
+    pub async fn state_subscribe_storage(
+        &self,
+        keys: impl IntoIterator<Item = &[u8]>,
+    ) -> Result<RpcSubscription<StorageChangeSet<T::Hash>>, Error> {
+        let keys: Vec<String> = keys.into_iter().map(to_hex).collect();
+        let subscription = self
+            .client
+            .subscribe(
+                "state_subscribeStorage",
+                rpc_params![keys],
+                "state_unsubscribeStorage",
+            )
+            .await?;
+        Ok(subscription)
+    }
+
     /// Create and submit an extrinsic and return corresponding Hash if successful
     pub async fn author_submit_extrinsic(&self, extrinsic: &[u8]) -> Result<T::Hash, Error> {
         let params = rpc_params![to_hex(extrinsic)];
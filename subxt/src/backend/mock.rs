@@ -0,0 +1,750 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! This module exposes a [`MockBackend`], which implements the [`Backend`] trait by
+//! handing back scripted responses rather than talking to a node. It's useful for
+//! testing code which uses an [`crate::OnlineClient`] without needing a running node.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use subxt::backend::mock::MockBackend;
+//! use subxt::backend::TransactionStatus;
+//! use subxt::config::substrate::{Digest, SubstrateHeader, H256};
+//! use subxt::{OnlineClient, PolkadotConfig};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let metadata_bytes = std::fs::read("polkadot_metadata.scale")?;
+//! let genesis_header = SubstrateHeader {
+//!     parent_hash: H256::zero(),
+//!     number: 0,
+//!     state_root: H256::zero(),
+//!     extrinsics_root: H256::zero(),
+//!     digest: Digest::default(),
+//! };
+//!
+//! let backend = MockBackend::<PolkadotConfig>::builder()
+//!     .metadata(metadata_bytes)
+//!     .block(genesis_header, vec![])
+//!     .transaction_statuses([TransactionStatus::Validated])
+//!     .build();
+//!
+//! // The client will fetch everything it needs from the mock backend:
+//! let api = OnlineClient::<PolkadotConfig>::from_backend(Arc::new(backend)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::backend::in_memory::{clone_header, headers_stream, InMemoryStorage as Storage};
+use crate::backend::{
+    Backend, BlockRef, RuntimeVersion, StorageResponse, StreamOf, StreamOfResults,
+    TransactionStatus,
+};
+use crate::config::Header;
+use crate::error::{Error, RpcError};
+use crate::Config;
+use async_trait::async_trait;
+use codec::Encode;
+use futures::stream;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+use crate::config::substrate::{BlakeTwo256, Digest, SubstrateHeader, H256};
+
+/// Configure and build a [`MockBackend`].
+pub struct MockBackendBuilder<T: Config> {
+    genesis_hash: Option<T::Hash>,
+    runtime_version: RuntimeVersion,
+    runtime_version_updates: Vec<RuntimeVersion>,
+    blocks: Vec<(T::Header, Vec<Vec<u8>>)>,
+    storage: HashMap<T::Hash, Storage>,
+    child_storage: HashMap<(T::Hash, Vec<u8>), Storage>,
+    read_proofs: HashMap<T::Hash, Vec<Vec<u8>>>,
+    calls: HashMap<String, Vec<MockCall<T::Hash>>>,
+    transaction_statuses: VecDeque<Vec<TransactionStatus<T::Hash>>>,
+}
+
+// A scripted runtime API response, optionally only matching calls at some specific block
+// or with some specific call parameters.
+#[derive(Clone, Debug)]
+struct MockCall<Hash> {
+    at: Option<Hash>,
+    call_parameters: Option<Vec<u8>>,
+    response: Vec<u8>,
+}
+
+impl<Hash: PartialEq> MockCall<Hash> {
+    // How specifically this response matches the call, or `None` if it doesn't match at all.
+    fn specificity(&self, at: &Hash, call_parameters: &[u8]) -> Option<u8> {
+        let at_matches = self.at.as_ref().map(|a| a == at);
+        let params_match = self
+            .call_parameters
+            .as_deref()
+            .map(|p| p == call_parameters);
+        match (at_matches, params_match) {
+            (Some(false), _) | (_, Some(false)) => None,
+            (at, params) => Some(2 * at.is_some() as u8 + params.is_some() as u8),
+        }
+    }
+}
+
+impl<T: Config> Default for MockBackendBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> MockBackendBuilder<T> {
+    /// Create a new [`MockBackendBuilder`].
+    pub fn new() -> Self {
+        Self {
+            genesis_hash: None,
+            runtime_version: RuntimeVersion {
+                spec_version: 0,
+                transaction_version: 0,
+            },
+            runtime_version_updates: Vec::new(),
+            blocks: Vec::new(),
+            storage: HashMap::new(),
+            child_storage: HashMap::new(),
+            read_proofs: HashMap::new(),
+            calls: HashMap::new(),
+            transaction_statuses: VecDeque::new(),
+        }
+    }
+
+    /// The genesis hash to hand back. If this isn't set, the hash of the first
+    /// block added via [`MockBackendBuilder::block()`] is used.
+    pub fn genesis_hash(mut self, genesis_hash: T::Hash) -> Self {
+        self.genesis_hash = Some(genesis_hash);
+        self
+    }
+
+    /// The runtime version to hand back from [`Backend::current_runtime_version()`].
+    pub fn runtime_version(mut self, runtime_version: RuntimeVersion) -> Self {
+        self.runtime_version = runtime_version;
+        self
+    }
+
+    /// The runtime versions to emit from [`Backend::stream_runtime_version()`].
+    pub fn runtime_version_updates(
+        mut self,
+        updates: impl IntoIterator<Item = RuntimeVersion>,
+    ) -> Self {
+        self.runtime_version_updates = updates.into_iter().collect();
+        self
+    }
+
+    /// Serve the given SCALE encoded metadata from the runtime API calls that Subxt uses
+    /// to obtain metadata.
+    pub fn metadata(self, metadata_bytes: impl Into<Vec<u8>>) -> Self {
+        let metadata_bytes = metadata_bytes.into();
+        let at_version = Some(frame_metadata::OpaqueMetadata(metadata_bytes.clone())).encode();
+        let legacy = frame_metadata::OpaqueMetadata(metadata_bytes).encode();
+        self.runtime_api_call("Metadata_metadata_at_version", at_version)
+            .runtime_api_call("Metadata_metadata", legacy)
+    }
+
+    /// Like [`MockBackendBuilder::metadata()`], but only serve the given metadata in the state of
+    /// the given block hash. This takes precedence over metadata which isn't tied to a block.
+    pub fn metadata_at(self, at: T::Hash, metadata_bytes: impl Into<Vec<u8>>) -> Self {
+        let metadata_bytes = metadata_bytes.into();
+        let at_version = Some(frame_metadata::OpaqueMetadata(metadata_bytes.clone())).encode();
+        let legacy = frame_metadata::OpaqueMetadata(metadata_bytes).encode();
+        self.runtime_api_call_at(at, "Metadata_metadata_at_version", at_version)
+            .runtime_api_call_at(at, "Metadata_metadata", legacy)
+    }
+
+    /// Add a block with the given header and extrinsics. Blocks are handed back from the header
+    /// subscriptions in the order that they are added, and the last block added is considered
+    /// to be the latest finalized block.
+    pub fn block(mut self, header: T::Header, extrinsics: Vec<Vec<u8>>) -> Self {
+        self.blocks.push((header, extrinsics));
+        self
+    }
+
+    /// Add a storage entry which will exist at the given block hash.
+    pub fn storage_entry(mut self, at: T::Hash, key: Vec<u8>, value: Vec<u8>) -> Self {
+        self.storage.entry(at).or_default().insert(key, value);
+        self
+    }
+
+    /// Add a number of storage entries which will exist at the given block hash.
+    pub fn storage_entries(
+        mut self,
+        at: T::Hash,
+        entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Self {
+        self.storage.entry(at).or_default().extend(entries);
+        self
+    }
+
+    /// Add a number of storage entries which will exist in the child trie with the given
+    /// (unprefixed) key at the given block hash.
+    pub fn child_storage_entries(
+        mut self,
+        at: T::Hash,
+        child_key: Vec<u8>,
+        entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Self {
+        self.child_storage
+            .entry((at, child_key))
+            .or_default()
+            .extend(entries);
+        self
+    }
+
+    /// Hand back the given encoded trie nodes from [`Backend::storage_fetch_read_proof()`]
+    /// when asked for a proof of any keys at the given block hash.
+    pub fn read_proof(mut self, at: T::Hash, nodes: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        self.read_proofs.insert(at, nodes.into_iter().collect());
+        self
+    }
+
+    /// Respond to calls to the given runtime API method with the given bytes, regardless
+    /// of the call parameters given. Responses given for the method `system_dryRun` are
+    /// used to answer calls to [`Backend::dry_run()`].
+    pub fn runtime_api_call(mut self, method: impl Into<String>, response: Vec<u8>) -> Self {
+        self.calls.entry(method.into()).or_default().push(MockCall {
+            at: None,
+            call_parameters: None,
+            response,
+        });
+        self
+    }
+
+    /// Respond to calls to the given runtime API method in the state of the given block hash with
+    /// the given bytes, regardless of the call parameters given. This can be used to mimic runtime
+    /// upgrades. These take precedence over responses which aren't tied to a block.
+    pub fn runtime_api_call_at(
+        mut self,
+        at: T::Hash,
+        method: impl Into<String>,
+        response: Vec<u8>,
+    ) -> Self {
+        self.calls.entry(method.into()).or_default().push(MockCall {
+            at: Some(at),
+            call_parameters: None,
+            response,
+        });
+        self
+    }
+
+    /// Respond to calls to the given runtime API method with the given bytes, but only when the
+    /// call parameters match those given. These take precedence over responses added via
+    /// [`MockBackendBuilder::runtime_api_call()`].
+    pub fn runtime_api_call_with_params(
+        mut self,
+        method: impl Into<String>,
+        call_parameters: Vec<u8>,
+        response: Vec<u8>,
+    ) -> Self {
+        self.calls.entry(method.into()).or_default().push(MockCall {
+            at: None,
+            call_parameters: Some(call_parameters),
+            response,
+        });
+        self
+    }
+
+    /// Add a sequence of statuses to hand back when a transaction is submitted. Each
+    /// submitted transaction consumes the next sequence that was added.
+    pub fn transaction_statuses(
+        mut self,
+        statuses: impl IntoIterator<Item = TransactionStatus<T::Hash>>,
+    ) -> Self {
+        self.transaction_statuses
+            .push_back(statuses.into_iter().collect());
+        self
+    }
+
+    /// Build the [`MockBackend`].
+    pub fn build(self) -> MockBackend<T> {
+        let genesis_hash = self
+            .genesis_hash
+            .or_else(|| self.blocks.first().map(|(header, _)| header.hash()));
+
+        MockBackend {
+            inner: Arc::new(MockBackendInner {
+                genesis_hash,
+                runtime_version: self.runtime_version,
+                runtime_version_updates: self.runtime_version_updates,
+                blocks: self.blocks,
+                storage: self.storage,
+                child_storage: self.child_storage,
+                read_proofs: self.read_proofs,
+                calls: self.calls,
+                transaction_statuses: Mutex::new(self.transaction_statuses),
+                submitted_transactions: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+}
+
+/// Build a header with the given number and parent hash, for use in tests.
+#[cfg(test)]
+pub(crate) fn test_header(number: u32, parent_hash: H256) -> SubstrateHeader<u32, BlakeTwo256> {
+    SubstrateHeader {
+        parent_hash,
+        number,
+        state_root: H256::zero(),
+        extrinsics_root: H256::zero(),
+        digest: Digest::default(),
+    }
+}
+
+#[cfg(test)]
+impl<T: Config<Header = SubstrateHeader<u32, BlakeTwo256>>> MockBackendBuilder<T> {
+    /// Add a genesis block (see [`test_header()`]) and serve the small Polkadot metadata from
+    /// `artifacts`. This is enough to build an [`crate::OnlineClient`] in tests.
+    pub(crate) fn with_genesis_and_small_metadata(self) -> Self {
+        let metadata = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        self.metadata(metadata)
+            .block(test_header(0, H256::zero()), vec![])
+    }
+}
+
+// Storage at blocks which haven't had any added.
+static EMPTY_STORAGE: Storage = Storage::new();
+
+/// A backend which hands back scripted responses. See the [module docs](self) for more.
+pub struct MockBackend<T: Config> {
+    inner: Arc<MockBackendInner<T>>,
+}
+
+struct MockBackendInner<T: Config> {
+    genesis_hash: Option<T::Hash>,
+    runtime_version: RuntimeVersion,
+    runtime_version_updates: Vec<RuntimeVersion>,
+    blocks: Vec<(T::Header, Vec<Vec<u8>>)>,
+    storage: HashMap<T::Hash, Storage>,
+    child_storage: HashMap<(T::Hash, Vec<u8>), Storage>,
+    read_proofs: HashMap<T::Hash, Vec<Vec<u8>>>,
+    calls: HashMap<String, Vec<MockCall<T::Hash>>>,
+    transaction_statuses: Mutex<VecDeque<Vec<TransactionStatus<T::Hash>>>>,
+    submitted_transactions: Mutex<Vec<Vec<u8>>>,
+}
+
+impl<T: Config> Clone for MockBackend<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Config> std::fmt::Debug for MockBackend<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockBackend")
+            .field("blocks", &self.inner.blocks.len())
+            .finish()
+    }
+}
+
+impl<T: Config> MockBackend<T> {
+    /// Configure and construct a [`MockBackend`].
+    pub fn builder() -> MockBackendBuilder<T> {
+        MockBackendBuilder::new()
+    }
+
+    /// Return the bytes of each transaction that has been submitted to this backend so far,
+    /// in the order that they were submitted.
+    pub fn submitted_transactions(&self) -> Vec<Vec<u8>> {
+        self.inner.submitted_transactions.lock().unwrap().clone()
+    }
+
+    fn find_block(&self, at: T::Hash) -> Option<&(T::Header, Vec<Vec<u8>>)> {
+        self.inner
+            .blocks
+            .iter()
+            .find(|(header, _)| header.hash() == at)
+    }
+
+    fn storage_at(&self, at: T::Hash) -> &Storage {
+        self.inner.storage.get(&at).unwrap_or(&EMPTY_STORAGE)
+    }
+
+    fn child_storage_at(&self, at: T::Hash, child_key: Vec<u8>) -> &Storage {
+        self.inner
+            .child_storage
+            .get(&(at, child_key))
+            .unwrap_or(&EMPTY_STORAGE)
+    }
+
+    fn headers_stream(&self) -> StreamOfResults<(T::Header, BlockRef<T::Hash>)> {
+        headers_stream::<T>(self.inner.blocks.iter().map(|(header, _)| header))
+    }
+}
+
+impl<T: Config> super::sealed::Sealed for MockBackend<T> {}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static> Backend<T> for MockBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.storage_at(at).values(keys))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        Ok(self.storage_at(at).descendant_keys(key))
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.storage_at(at).descendant_values(key))
+    }
+
+    async fn storage_fetch_descendant_values_after(
+        &self,
+        key: Vec<u8>,
+        start_key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        Ok(Some(self.storage_at(at).descendant_values_after(key, start_key)))
+    }
+
+    async fn storage_fetch_closest_descendant_merkle_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        // We don't have a trie, so stand in for a merkle value with a
+        // hash of all of the entries underneath each key.
+        let storage = self.storage_at(at);
+        let values: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let descendants: Vec<_> = storage.descendants(&key).collect();
+                if descendants.is_empty() {
+                    return None;
+                }
+                let value = sp_crypto_hashing::blake2_256(&descendants.encode()).to_vec();
+                Some(Ok(StorageResponse { key, value }))
+            })
+            .collect();
+        Ok(Some(StreamOf(Box::pin(stream::iter(values)))))
+    }
+
+    async fn storage_fetch_read_proof(
+        &self,
+        _keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.inner.read_proofs.get(&at).cloned().ok_or_else(|| {
+            RpcError::request_rejected("MockBackend: no read proof provided for this block").into()
+        })
+    }
+
+    async fn stream_storage_value(
+        &self,
+        key: Vec<u8>,
+    ) -> Result<StreamOfResults<(BlockRef<T::Hash>, Option<Vec<u8>>)>, Error> {
+        // Hand back the value in the first block, and then each time it changes.
+        let mut last_value = None;
+        let values: Vec<_> = self
+            .inner
+            .blocks
+            .iter()
+            .filter_map(|(header, _)| {
+                let hash = header.hash();
+                let value = self.storage_at(hash).get(&key).cloned();
+                if last_value.as_ref() == Some(&value) {
+                    return None;
+                }
+                last_value = Some(value.clone());
+                Some(Ok((BlockRef::from_hash(hash), value)))
+            })
+            .collect();
+        Ok(StreamOf(Box::pin(stream::iter(values))))
+    }
+
+    async fn storage_fetch_child_values(
+        &self,
+        child_key: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.child_storage_at(at, child_key).values(keys))
+    }
+
+    async fn storage_fetch_child_descendant_keys(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        Ok(self.child_storage_at(at, child_key).descendant_keys(key))
+    }
+
+    async fn storage_fetch_child_descendant_values(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.child_storage_at(at, child_key).descendant_values(key))
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        self.inner
+            .genesis_hash
+            .ok_or_else(|| Error::Other("MockBackend: no genesis hash or blocks provided".into()))
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        Ok(self
+            .find_block(at)
+            .map(|(header, _)| clone_header::<T>(header)))
+    }
+
+    async fn block_body(&self, at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        Ok(self.find_block(at).map(|(_, exts)| exts.clone()))
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        self.inner
+            .blocks
+            .last()
+            .map(|(header, _)| BlockRef::from_hash(header.hash()))
+            .ok_or_else(|| Error::Other("MockBackend: no blocks provided".into()))
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        Ok(self.inner.runtime_version)
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        let updates: Vec<_> = self
+            .inner
+            .runtime_version_updates
+            .iter()
+            .copied()
+            .map(Ok)
+            .collect();
+        Ok(StreamOf(Box::pin(stream::iter(updates))))
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn submit_transaction(
+        &self,
+        bytes: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        self.inner
+            .submitted_transactions
+            .lock()
+            .unwrap()
+            .push(bytes.to_vec());
+
+        let statuses = self
+            .inner
+            .transaction_statuses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| {
+                RpcError::request_rejected("MockBackend: no transaction statuses left to hand out")
+            })?;
+
+        let statuses: Vec<_> = statuses.into_iter().map(Ok).collect();
+        Ok(StreamOf(Box::pin(stream::iter(statuses))))
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        call_parameters: Option<&[u8]>,
+        at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        let calls = self.inner.calls.get(method).ok_or_else(|| {
+            RpcError::request_rejected(format!("MockBackend: no response for call {method}"))
+        })?;
+
+        // Use the most specific response that matches, and the first added if there's a tie.
+        let call_parameters = call_parameters.unwrap_or_default();
+        calls
+            .iter()
+            .rev()
+            .filter_map(|c| Some((c.specificity(&at, call_parameters)?, c)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, c)| c.response.clone())
+            .ok_or_else(|| {
+                RpcError::request_rejected(format!(
+                    "MockBackend: no response for call {method} with the given parameters and block"
+                ))
+                .into()
+            })
+    }
+
+    async fn dry_run(&self, extrinsic: &[u8], at: T::Hash) -> Result<Vec<u8>, Error> {
+        self.call("system_dryRun", Some(extrinsic), at).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::BackendExt;
+    use crate::{OnlineClient, SubstrateConfig};
+    use futures::StreamExt;
+
+    fn metadata_bytes() -> Vec<u8> {
+        std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_blocks_storage_and_calls() {
+        let genesis = test_header(0, H256::zero());
+        let block_1 = test_header(1, genesis.hash());
+        let block_1_hash = block_1.hash();
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .block(genesis.clone(), vec![])
+            .block(block_1, vec![vec![1, 2, 3]])
+            .storage_entries(
+                block_1_hash,
+                [
+                    (b"foo1".to_vec(), b"a".to_vec()),
+                    (b"foo2".to_vec(), b"b".to_vec()),
+                    (b"other".to_vec(), b"c".to_vec()),
+                ],
+            )
+            .runtime_api_call("Api_method", vec![1])
+            .runtime_api_call_with_params("Api_method", vec![2], vec![3])
+            .build();
+
+        assert_eq!(backend.genesis_hash().await.unwrap(), genesis.hash());
+        assert_eq!(
+            backend.latest_finalized_block_ref().await.unwrap().hash(),
+            block_1_hash
+        );
+        assert_eq!(
+            backend.block_body(block_1_hash).await.unwrap(),
+            Some(vec![vec![1, 2, 3]])
+        );
+
+        let value = backend
+            .storage_fetch_value(b"foo2".to_vec(), block_1_hash)
+            .await
+            .unwrap();
+        assert_eq!(value, Some(b"b".to_vec()));
+
+        let keys: Vec<_> = backend
+            .storage_fetch_descendant_keys(b"foo".to_vec(), block_1_hash)
+            .await
+            .unwrap()
+            .map(|k| k.unwrap())
+            .collect()
+            .await;
+        assert_eq!(keys, vec![b"foo1".to_vec(), b"foo2".to_vec()]);
+
+        let res = backend.call("Api_method", None, block_1_hash).await.unwrap();
+        assert_eq!(res, vec![1]);
+        let res = backend
+            .call("Api_method", Some(&[2]), block_1_hash)
+            .await
+            .unwrap();
+        assert_eq!(res, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn streams_storage_changes() {
+        let block_0 = test_header(0, H256::zero());
+        let block_1 = test_header(1, block_0.hash());
+        let block_2 = test_header(2, block_1.hash());
+        let block_3 = test_header(3, block_2.hash());
+        let hashes = [block_0.hash(), block_1.hash(), block_2.hash(), block_3.hash()];
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .block(block_0, vec![])
+            .block(block_1, vec![])
+            .block(block_2, vec![])
+            .block(block_3, vec![])
+            .storage_entry(hashes[1], b"foo".to_vec(), b"a".to_vec())
+            .storage_entry(hashes[2], b"foo".to_vec(), b"a".to_vec())
+            .storage_entry(hashes[3], b"foo".to_vec(), b"b".to_vec())
+            .build();
+
+        let changes: Vec<_> = backend
+            .stream_storage_value(b"foo".to_vec())
+            .await
+            .unwrap()
+            .map(|c| c.map(|(block_ref, value)| (block_ref.hash(), value)).unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            changes,
+            vec![
+                (hashes[0], None),
+                (hashes[1], Some(b"a".to_vec())),
+                (hashes[3], Some(b"b".to_vec())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn online_client_can_be_built_and_submit() {
+        let genesis = test_header(0, H256::zero());
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata_bytes())
+            .block(genesis.clone(), vec![])
+            .transaction_statuses([
+                TransactionStatus::Validated,
+                TransactionStatus::Dropped {
+                    message: "dropped".into(),
+                },
+            ])
+            .build();
+
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+        assert_eq!(api.genesis_hash(), genesis.hash());
+
+        let statuses: Vec<_> = api
+            .backend()
+            .submit_transaction(&[1, 2, 3])
+            .await
+            .unwrap()
+            .map(|s| s.unwrap())
+            .collect()
+            .await;
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(backend.submitted_transactions(), vec![vec![1, 2, 3]]);
+
+        // No more statuses have been scripted, so the next submission fails:
+        assert!(api.backend().submit_transaction(&[4]).await.is_err());
+    }
+}
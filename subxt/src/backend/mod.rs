@@ -6,10 +6,16 @@
 //! the necessary information (probably from a JSON-RPC API, but that's up to the
 //! implementation).
 
+pub mod archive;
+pub(crate) mod in_memory;
 pub mod legacy;
 pub mod rpc;
 pub mod unstable;
 
+crate::macros::cfg_mock_backend! {
+    pub mod mock;
+}
+
 use subxt_core::client::RuntimeVersion;
 
 use crate::error::Error;
@@ -52,6 +58,110 @@ pub trait Backend<T: Config>: sealed::Sealed + Send + Sync + 'static {
         at: T::Hash,
     ) -> Result<StreamOfResults<StorageResponse>, Error>;
 
+    /// Fetch values underneath the given key from storage, beginning after the given start key
+    /// (which is not itself handed back). This allows iteration to be resumed from some point,
+    /// or split into parts.
+    ///
+    /// Backends which cannot begin fetching from a start key will return `None`.
+    async fn storage_fetch_descendant_values_after(
+        &self,
+        _key: Vec<u8>,
+        _start_key: Vec<u8>,
+        _at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        Ok(None)
+    }
+
+    /// Fetch the hashes of the values underneath the given key from storage, handing each
+    /// back as the `value` of a [`StorageResponse`]. This is useful for comparing values
+    /// without needing to download them. By default, values are fetched and hashed locally.
+    async fn storage_fetch_descendant_hashes(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let values = self.storage_fetch_descendant_values(key, at).await?;
+        let hashes = values.map(|res| {
+            res.map(|StorageResponse { key, value }| StorageResponse {
+                key,
+                value: sp_crypto_hashing::blake2_256(&value).to_vec(),
+            })
+        });
+        Ok(StreamOf(Box::pin(hashes)))
+    }
+
+    /// Fetch the closest descendant merkle value of each of the given keys, handing each back as
+    /// the `value` of a [`StorageResponse`]. This identifies the trie node at or below each key,
+    /// and so if it's the same in two blocks then so is all of the storage underneath that key.
+    /// No response is handed back for keys with nothing underneath them.
+    ///
+    /// Backends which cannot provide these values will return `None`.
+    async fn storage_fetch_closest_descendant_merkle_values(
+        &self,
+        _keys: Vec<Vec<u8>>,
+        _at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        Ok(None)
+    }
+
+    /// Fetch the encoded trie nodes which prove the values (or absence of values) at the
+    /// given keys. Backends which cannot provide storage proofs will return an error.
+    async fn storage_fetch_read_proof(
+        &self,
+        _keys: Vec<Vec<u8>>,
+        _at: T::Hash,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        Err(Error::Other(
+            "Fetching storage proofs is not supported by this backend".into(),
+        ))
+    }
+
+    /// A stream of the value at the given key, alongside the block in which it was seen. The
+    /// first item is the value when the stream starts, and subsequent items are only handed back
+    /// when the value changes. Backends which cannot watch storage will return an error.
+    async fn stream_storage_value(
+        &self,
+        _key: Vec<u8>,
+    ) -> Result<StreamOfResults<(BlockRef<T::Hash>, Option<Vec<u8>>)>, Error> {
+        Err(Error::Other(
+            "Watching storage is not supported by this backend".into(),
+        ))
+    }
+
+    /// Fetch values from the child trie with the given key. This is the unprefixed key of a
+    /// child trie in the default namespace, ie without `:child_storage:default:` at the front.
+    /// Backends which cannot access child tries will return an error.
+    async fn storage_fetch_child_values(
+        &self,
+        _child_key: Vec<u8>,
+        _keys: Vec<Vec<u8>>,
+        _at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Err(child_storage_not_supported())
+    }
+
+    /// Fetch keys underneath the given key from the child trie with the given key. See
+    /// [`Backend::storage_fetch_child_values()`].
+    async fn storage_fetch_child_descendant_keys(
+        &self,
+        _child_key: Vec<u8>,
+        _key: Vec<u8>,
+        _at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        Err(child_storage_not_supported())
+    }
+
+    /// Fetch values underneath the given key from the child trie with the given key. See
+    /// [`Backend::storage_fetch_child_values()`].
+    async fn storage_fetch_child_descendant_values(
+        &self,
+        _child_key: Vec<u8>,
+        _key: Vec<u8>,
+        _at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Err(child_storage_not_supported())
+    }
+
     /// Fetch the genesis hash
     async fn genesis_hash(&self) -> Result<T::Hash, Error>;
 
@@ -101,6 +211,19 @@ pub trait Backend<T: Config>: sealed::Sealed + Send + Sync + 'static {
         call_parameters: Option<&[u8]>,
         at: T::Hash,
     ) -> Result<Vec<u8>, Error>;
+
+    /// Dry run the given extrinsic bytes at some block, returning the SCALE encoded
+    /// `ApplyExtrinsicResult` that would result from executing it. Prefer the `DryRunApi`
+    /// runtime API via [`Backend::call()`] where available; this is a fallback for when it isn't.
+    async fn dry_run(&self, _extrinsic: &[u8], _at: T::Hash) -> Result<Vec<u8>, Error> {
+        Err(Error::Other(
+            "Dry running extrinsics is not supported by this backend".into(),
+        ))
+    }
+}
+
+fn child_storage_not_supported() -> Error {
+    Error::Other("Child trie storage is not supported by this backend".into())
 }
 
 /// helpful utility methods derived from those provided on [`Backend`]
@@ -120,6 +243,21 @@ pub trait BackendExt<T: Config>: Backend<T> {
             .map(|o| o.map(|s| s.value))
     }
 
+    /// Fetch a single value from the child trie with the given key.
+    async fn storage_fetch_child_value(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.storage_fetch_child_values(child_key, vec![key], at)
+            .await?
+            .next()
+            .await
+            .transpose()
+            .map(|o| o.map(|s| s.value))
+    }
+
     /// The same as a [`Backend::call()`], but it will also attempt to decode the
     /// result into the given type, which is a fairly common operation.
     async fn call_decoding<D: codec::Decode>(
@@ -14,6 +14,8 @@
 //!   methods.
 //! - [`RpcClient`] is the higher level wrapper around this, offering
 //!   the [`RpcClient::request`] and [`RpcClient::subscribe`] methods.
+//! - [`MultiRpcClient`] is an [`RpcClientT`] implementation which spreads requests
+//!   across several endpoints, failing over between them when connections are lost.
 //!
 //! # Example
 //!
@@ -69,8 +71,10 @@ crate::macros::cfg_reconnecting_rpc_client! {
     pub use reconnecting_jsonrpsee_ws_client as reconnecting_rpc_client;
 }
 
+mod multi_rpc_client;
 mod rpc_client;
 mod rpc_client_t;
 
+pub use multi_rpc_client::{MultiRpcClient, MultiRpcClientBuilder, Routing};
 pub use rpc_client::{rpc_params, RpcClient, RpcParams, RpcSubscription};
 pub use rpc_client_t::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
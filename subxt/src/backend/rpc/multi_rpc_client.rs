@@ -0,0 +1,579 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::{RawRpcFuture, RawRpcSubscription, RpcClient, RpcClientT};
+use crate::error::RpcError;
+use futures::StreamExt;
+use instant::Instant;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How a [`MultiRpcClient`] decides which endpoint to send a request to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Routing {
+    /// Send each request to the next endpoint in turn.
+    #[default]
+    RoundRobin,
+    /// Send each request to the endpoint which has been responding the fastest. Endpoints
+    /// which haven't responded to anything yet are tried first, so that every endpoint
+    /// has its latency measured.
+    LowestLatency,
+}
+
+/// An [`RpcClientT`] implementation which spreads requests across several endpoints, and
+/// fails over to another endpoint when one of them has connection problems. Construct one
+/// via [`MultiRpcClient::builder()`].
+///
+/// - Requests are routed to an endpoint according to [`Routing`]. If the request fails
+///   because of a problem with the connection, the endpoint is put aside for a while (see
+///   [`MultiRpcClientBuilder::retry_after()`]) and the request is retried on another one.
+///   Errors returned by the node itself, for example because the request was invalid, are
+///   handed back without trying any other endpoints.
+/// - Subscriptions are established in the same way, but once established they stay with the
+///   endpoint that they were made on, since the subscription ID and any subsequent calls
+///   related to it are only meaningful to that node. If the connection to that endpoint is
+///   lost then the subscription stream ends with an error, and resubscribing will pick a
+///   healthy endpoint.
+/// - Requests which refer to a subscription or operation that an endpoint handed back, such as
+///   the `chainHead_v1_*` methods (which are given the ID of a `chainHead_v1_follow`
+///   subscription) and `transaction_v1_stop` (which is given the ID that
+///   `transaction_v1_broadcast` handed back), are sent to that endpoint without failing over.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::backend::rpc::{MultiRpcClient, RpcClient, Routing};
+/// use subxt::{OnlineClient, PolkadotConfig};
+///
+/// let mut builder = MultiRpcClient::builder().routing(Routing::LowestLatency);
+/// for url in ["wss://rpc.polkadot.io:443", "wss://polkadot-rpc.dwellir.com:443"] {
+///     builder = builder.endpoint(RpcClient::from_url(url).await.unwrap());
+/// }
+///
+/// let rpc_client = RpcClient::new(builder.build());
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct MultiRpcClient {
+    endpoints: Vec<Endpoint>,
+    routing: Routing,
+    retry_after: Duration,
+    next: AtomicUsize,
+    // The endpoint that each live subscription or operation ID was handed back by.
+    owners: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+struct Endpoint {
+    client: RpcClient,
+    state: Mutex<EndpointState>,
+}
+
+#[derive(Default)]
+struct EndpointState {
+    /// A moving average of how long this endpoint takes to respond.
+    latency: Option<Duration>,
+    /// When this endpoint last failed because of a connection error.
+    failed_at: Option<Instant>,
+}
+
+impl MultiRpcClient {
+    /// Configure and construct a [`MultiRpcClient`].
+    pub fn builder() -> MultiRpcClientBuilder {
+        MultiRpcClientBuilder::new()
+    }
+
+    /// The indexes of the endpoints to try, in the order that we should try them in.
+    /// Endpoints which have recently failed are only tried once all others have been.
+    fn candidates(&self) -> Vec<usize> {
+        let len = self.endpoints.len();
+        let mut candidates: Vec<usize> = match self.routing {
+            Routing::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..len).map(|n| (start + n) % len).collect()
+            }
+            Routing::LowestLatency => {
+                let mut candidates: Vec<_> = (0..len).collect();
+                candidates.sort_by_key(|&idx| self.endpoints[idx].state().latency);
+                candidates
+            }
+        };
+
+        let now = Instant::now();
+        candidates.sort_by_key(|&idx| {
+            self.endpoints[idx]
+                .state()
+                .failed_at
+                .is_some_and(|at| now.duration_since(at) < self.retry_after)
+        });
+        candidates
+    }
+
+    /// The subscription or operation ID that this request refers to, if it refers to one,
+    /// and the endpoint which handed it back.
+    fn owner(&self, method: &str, params: Option<&RawValue>) -> Option<(String, usize)> {
+        if !refers_to_owned_id(method) {
+            return None;
+        }
+        let id = first_param(params?)?;
+        let idx = *lock(&self.owners).get(&id)?;
+        Some((id, idx))
+    }
+}
+
+/// Do requests to this method refer to a subscription or operation, given by their first
+/// parameter, which is only meaningful to the endpoint that handed it back?
+fn refers_to_owned_id(method: &str) -> bool {
+    let is_chain_head = method.starts_with("chainHead_") && !method.ends_with("_follow");
+    let is_transaction_stop = method.starts_with("transaction_") && method.ends_with("_stop");
+    is_chain_head || is_transaction_stop
+}
+
+/// Do requests to this method hand back the ID of an operation which later requests refer to?
+fn returns_owned_id(method: &str) -> bool {
+    method.starts_with("transaction_") && method.ends_with("_broadcast")
+}
+
+fn first_param(params: &RawValue) -> Option<String> {
+    let params: Vec<serde_json::Value> = serde_json::from_str(params.get()).ok()?;
+    params.into_iter().next()?.as_str().map(ToOwned::to_owned)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Forgets which endpoint owns a subscription ID once the subscription is dropped.
+struct OwnedId {
+    id: String,
+    owners: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for OwnedId {
+    fn drop(&mut self) {
+        lock(&self.owners).remove(&self.id);
+    }
+}
+
+impl Endpoint {
+    fn state(&self) -> std::sync::MutexGuard<'_, EndpointState> {
+        lock(&self.state)
+    }
+
+    fn record_success(&self, elapsed: Duration) {
+        let mut state = self.state();
+        state.failed_at = None;
+        state.latency = Some(match state.latency {
+            // Weight the latest measurement at 1/4 to smooth out spikes.
+            Some(latency) => (latency * 3 + elapsed) / 4,
+            None => elapsed,
+        });
+    }
+
+    fn record_failure(&self) {
+        self.state().failed_at = Some(Instant::now());
+    }
+}
+
+impl RpcClientT for MultiRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            if let Some((id, idx)) = self.owner(method, params.as_deref()) {
+                // Once an operation is stopped, its ID is no longer needed.
+                if method.starts_with("transaction_") {
+                    lock(&self.owners).remove(&id);
+                }
+                return self.endpoints[idx].client.request_raw(method, params).await;
+            }
+
+            let mut last_err = None;
+            for idx in self.candidates() {
+                let endpoint = &self.endpoints[idx];
+                let started = Instant::now();
+                match endpoint.client.request_raw(method, params.clone()).await {
+                    Ok(res) => {
+                        endpoint.record_success(started.elapsed());
+                        if returns_owned_id(method) {
+                            if let Ok(Some(id)) = serde_json::from_str::<Option<String>>(res.get())
+                            {
+                                lock(&self.owners).insert(id, idx);
+                            }
+                        }
+                        return Ok(res);
+                    }
+                    Err(e) if is_connection_error(&e) => {
+                        tracing::debug!("RPC endpoint {idx} failed; trying another: {e}");
+                        endpoint.record_failure();
+                        last_err = Some(e);
+                    }
+                    Err(e) => {
+                        endpoint.record_success(started.elapsed());
+                        return Err(e);
+                    }
+                }
+            }
+            Err(last_err.unwrap_or_else(no_endpoints))
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let mut last_err = None;
+            for idx in self.candidates() {
+                let endpoint = &self.endpoints[idx];
+                let started = Instant::now();
+                match endpoint
+                    .client
+                    .subscribe_raw(sub, params.clone(), unsub)
+                    .await
+                {
+                    Ok(mut res) => {
+                        endpoint.record_success(started.elapsed());
+                        if let Some(id) = res.id.clone() {
+                            lock(&self.owners).insert(id.clone(), idx);
+                            let owned_id = OwnedId {
+                                id,
+                                owners: self.owners.clone(),
+                            };
+                            res.stream = res
+                                .stream
+                                .inspect(move |_| {
+                                    let _ = &owned_id;
+                                })
+                                .boxed();
+                        }
+                        return Ok(res);
+                    }
+                    Err(e) if is_connection_error(&e) => {
+                        tracing::debug!("RPC endpoint {idx} failed; trying another: {e}");
+                        endpoint.record_failure();
+                        last_err = Some(e);
+                    }
+                    Err(e) => {
+                        endpoint.record_success(started.elapsed());
+                        return Err(e);
+                    }
+                }
+            }
+            Err(last_err.unwrap_or_else(no_endpoints))
+        })
+    }
+}
+
+/// Configure and construct a [`MultiRpcClient`].
+pub struct MultiRpcClientBuilder {
+    endpoints: Vec<Endpoint>,
+    routing: Routing,
+    retry_after: Duration,
+}
+
+impl Default for MultiRpcClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiRpcClientBuilder {
+    /// Create a new [`MultiRpcClientBuilder`].
+    pub fn new() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            routing: Routing::default(),
+            retry_after: Duration::from_secs(30),
+        }
+    }
+
+    /// Add an endpoint to send requests to. This can be an [`RpcClient`] or
+    /// any other [`RpcClientT`] implementation.
+    pub fn endpoint(mut self, client: impl Into<RpcClient>) -> Self {
+        self.endpoints.push(Endpoint {
+            client: client.into(),
+            state: Mutex::new(EndpointState::default()),
+        });
+        self
+    }
+
+    /// How to decide which endpoint each request goes to. Defaults to [`Routing::RoundRobin`].
+    pub fn routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// How long to avoid an endpoint for after a connection error. The endpoint is still
+    /// used during this time if every other endpoint is also failing. Defaults to 30 seconds.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Construct the [`MultiRpcClient`].
+    pub fn build(self) -> MultiRpcClient {
+        MultiRpcClient {
+            endpoints: self.endpoints,
+            routing: self.routing,
+            retry_after: self.retry_after,
+            next: AtomicUsize::new(0),
+            owners: Default::default(),
+        }
+    }
+}
+
+fn no_endpoints() -> RpcError {
+    RpcError::ClientError("No RPC endpoints have been configured".into())
+}
+
+/// Is this error caused by the connection to an endpoint rather than by the request itself?
+/// Errors from unknown [`RpcClientT`] implementations are assumed not to be, so that requests
+/// aren't needlessly repeated on every endpoint.
+fn is_connection_error(err: &RpcError) -> bool {
+    match err {
+        RpcError::DisconnectedWillReconnect(_) | RpcError::SubscriptionDropped => true,
+        #[cfg(feature = "jsonrpsee")]
+        RpcError::ClientError(e) => match e.downcast_ref::<jsonrpsee::core::client::Error>() {
+            Some(e) => {
+                use jsonrpsee::core::client::Error;
+                matches!(
+                    e,
+                    Error::Transport(_)
+                        | Error::RestartNeeded(_)
+                        | Error::RequestTimeout
+                        | Error::MaxSlotsExceeded
+                )
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::{FutureExt, StreamExt};
+    use std::sync::Arc;
+
+    /// Responds to requests with its name, or fails with the given error.
+    struct MockEndpoint {
+        name: &'static str,
+        error: Option<fn() -> RpcError>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl MockEndpoint {
+        fn new(name: &'static str, error: Option<fn() -> RpcError>) -> (Self, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let endpoint = MockEndpoint {
+                name,
+                error,
+                calls: calls.clone(),
+            };
+            (endpoint, calls)
+        }
+    }
+
+    impl RpcClientT for MockEndpoint {
+        fn request_raw<'a>(
+            &'a self,
+            _method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            // Subscription and operation IDs handed back are also the endpoint name.
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let res = match self.error {
+                Some(err) => Err(err()),
+                None => Ok(RawValue::from_string(format!("\"{}\"", self.name)).unwrap()),
+            };
+            async move { res }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let res = match self.error {
+                Some(err) => Err(err()),
+                None => Ok(RawRpcSubscription {
+                    stream: futures::stream::empty().boxed(),
+                    id: Some(self.name.to_string()),
+                }),
+            };
+            async move { res }.boxed()
+        }
+    }
+
+    fn disconnected() -> RpcError {
+        RpcError::DisconnectedWillReconnect("gone".into())
+    }
+
+    fn rejected() -> RpcError {
+        RpcError::RequestRejected("bad request".into())
+    }
+
+    async fn request(client: &MultiRpcClient) -> Result<String, RpcError> {
+        request_with(client, "foo", None).await
+    }
+
+    async fn request_with(
+        client: &MultiRpcClient,
+        method: &str,
+        first_param: Option<&str>,
+    ) -> Result<String, RpcError> {
+        let params = first_param
+            .map(|p| RawValue::from_string(serde_json::json!([p, "0x00"]).to_string()).unwrap());
+        let res = client.request_raw(method, params).await?;
+        Ok(serde_json::from_str(res.get()).unwrap())
+    }
+
+    fn client_error() -> RpcError {
+        RpcError::ClientError("something unknown went wrong".into())
+    }
+
+    #[tokio::test]
+    async fn round_robins_requests() {
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", None).0)
+            .endpoint(MockEndpoint::new("b", None).0)
+            .build();
+
+        assert_eq!(request(&client).await.unwrap(), "a");
+        assert_eq!(request(&client).await.unwrap(), "b");
+        assert_eq!(request(&client).await.unwrap(), "a");
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_connection_errors() {
+        let (broken, broken_calls) = MockEndpoint::new("a", Some(disconnected));
+        let client = MultiRpcClient::builder()
+            .endpoint(broken)
+            .endpoint(MockEndpoint::new("b", None).0)
+            .build();
+
+        // The broken endpoint is tried once, and then avoided.
+        for _ in 0..4 {
+            assert_eq!(request(&client).await.unwrap(), "b");
+        }
+        assert_eq!(broken_calls.load(Ordering::Relaxed), 1);
+
+        // Subscriptions fail over too.
+        let sub = client.subscribe_raw("foo", None, "unfoo").await.unwrap();
+        assert_eq!(sub.id.as_deref(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn does_not_fail_over_on_request_errors() {
+        let (healthy, healthy_calls) = MockEndpoint::new("b", None);
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", Some(rejected)).0)
+            .endpoint(healthy)
+            .build();
+
+        assert!(matches!(
+            request(&client).await,
+            Err(RpcError::RequestRejected(_))
+        ));
+        assert_eq!(healthy_calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn errors_when_every_endpoint_fails() {
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", Some(disconnected)).0)
+            .endpoint(MockEndpoint::new("b", Some(disconnected)).0)
+            .build();
+
+        assert!(matches!(
+            request(&client).await,
+            Err(RpcError::DisconnectedWillReconnect(_))
+        ));
+        assert!(matches!(
+            request(&MultiRpcClient::builder().build()).await,
+            Err(RpcError::ClientError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn unknown_client_errors_are_not_connection_errors() {
+        let (healthy, healthy_calls) = MockEndpoint::new("b", None);
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", Some(client_error)).0)
+            .endpoint(healthy)
+            .build();
+
+        assert!(matches!(
+            request(&client).await,
+            Err(RpcError::ClientError(_))
+        ));
+        assert_eq!(healthy_calls.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn chain_head_requests_go_to_the_following_endpoint() {
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", None).0)
+            .endpoint(MockEndpoint::new("b", None).0)
+            .build();
+
+        let follow = client
+            .subscribe_raw("chainHead_v1_follow", None, "chainHead_v1_unfollow")
+            .await
+            .unwrap();
+        assert_eq!(follow.id.as_deref(), Some("a"));
+
+        // Round robin would alternate, but every call refers to the subscription on "a".
+        for method in [
+            "chainHead_v1_call",
+            "chainHead_v1_body",
+            "chainHead_v1_unpin",
+        ] {
+            assert_eq!(request_with(&client, method, Some("a")).await.unwrap(), "a");
+        }
+
+        // Once the subscription is dropped, requests are routed as normal again.
+        drop(follow);
+        let mut routed = Vec::new();
+        for _ in 0..2 {
+            routed.push(
+                request_with(&client, "chainHead_v1_call", Some("a"))
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert!(routed.contains(&"b".to_string()));
+    }
+
+    #[tokio::test]
+    async fn transaction_stop_goes_to_the_broadcasting_endpoint() {
+        let client = MultiRpcClient::builder()
+            .endpoint(MockEndpoint::new("a", None).0)
+            .endpoint(MockEndpoint::new("b", None).0)
+            .build();
+
+        let operation_id = request_with(&client, "transaction_v1_broadcast", Some("0x00"))
+            .await
+            .unwrap();
+        assert_eq!(operation_id, "a");
+
+        // Round robin would pick "b" next.
+        let res = request_with(&client, "transaction_v1_stop", Some(&operation_id)).await;
+        assert_eq!(res.unwrap(), "a");
+    }
+}
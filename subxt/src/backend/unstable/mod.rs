@@ -30,7 +30,7 @@ use crate::error::{Error, RpcError};
 use crate::Config;
 use async_trait::async_trait;
 use follow_stream_driver::{FollowStreamDriver, FollowStreamDriverHandle};
-use futures::{Stream, StreamExt};
+use futures::{Stream, StreamExt, TryStreamExt};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::Poll;
@@ -176,21 +176,10 @@ impl<T: Config> UnstableBackend<T> {
 
         Ok(StreamOf(Box::pin(headers)))
     }
-}
-
-impl<Hash: BlockHash + 'static> BlockRefT for follow_stream_unpin::BlockRef<Hash> {}
-impl<Hash: BlockHash + 'static> From<follow_stream_unpin::BlockRef<Hash>> for BlockRef<Hash> {
-    fn from(b: follow_stream_unpin::BlockRef<Hash>) -> Self {
-        BlockRef::new(b.hash(), b)
-    }
-}
 
-impl<T: Config> super::sealed::Sealed for UnstableBackend<T> {}
-
-#[async_trait]
-impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
-    async fn storage_fetch_values(
+    async fn fetch_storage_values(
         &self,
+        child_key: Option<&[u8]>,
         keys: Vec<Vec<u8>>,
         at: T::Hash,
     ) -> Result<StreamOfResults<StorageResponse>, Error> {
@@ -199,9 +188,14 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
             query_type: StorageQueryType::Value,
         });
 
-        let storage_items =
-            StorageItems::from_methods(queries, at, &self.follow_handle, self.methods.clone())
-                .await?;
+        let storage_items = StorageItems::from_methods(
+            queries,
+            child_key,
+            at,
+            &self.follow_handle,
+            self.methods.clone(),
+        )
+        .await?;
 
         let storage_result_stream = storage_items.filter_map(|val| async move {
             let val = match val {
@@ -221,8 +215,9 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
         Ok(StreamOf(Box::pin(storage_result_stream)))
     }
 
-    async fn storage_fetch_descendant_keys(
+    async fn fetch_storage_descendant_keys(
         &self,
+        child_key: Option<&[u8]>,
         key: Vec<u8>,
         at: T::Hash,
     ) -> Result<StreamOfResults<Vec<u8>>, Error> {
@@ -234,6 +229,7 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
 
         let storage_items = StorageItems::from_methods(
             std::iter::once(query),
+            child_key,
             at,
             &self.follow_handle,
             self.methods.clone(),
@@ -244,8 +240,9 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
         Ok(StreamOf(Box::pin(storage_result_stream)))
     }
 
-    async fn storage_fetch_descendant_values(
+    async fn fetch_storage_descendant_values(
         &self,
+        child_key: Option<&[u8]>,
         key: Vec<u8>,
         at: T::Hash,
     ) -> Result<StreamOfResults<StorageResponse>, Error> {
@@ -256,6 +253,7 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
 
         let storage_items = StorageItems::from_methods(
             std::iter::once(query),
+            child_key,
             at,
             &self.follow_handle,
             self.methods.clone(),
@@ -279,6 +277,238 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
 
         Ok(StreamOf(Box::pin(storage_result_stream)))
     }
+}
+
+impl<Hash: BlockHash + 'static> BlockRefT for follow_stream_unpin::BlockRef<Hash> {}
+impl<Hash: BlockHash + 'static> From<follow_stream_unpin::BlockRef<Hash>> for BlockRef<Hash> {
+    fn from(b: follow_stream_unpin::BlockRef<Hash>) -> Self {
+        BlockRef::new(b.hash(), b)
+    }
+}
+
+impl<T: Config> super::sealed::Sealed for UnstableBackend<T> {}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_values(None, keys, at).await
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.fetch_storage_descendant_keys(None, key, at).await
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_descendant_values(None, key, at).await
+    }
+
+    async fn storage_fetch_descendant_hashes(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        let query = StorageQuery {
+            key: &*key,
+            query_type: StorageQueryType::DescendantsHashes,
+        };
+
+        let storage_items = StorageItems::from_methods(
+            std::iter::once(query),
+            None,
+            at,
+            &self.follow_handle,
+            self.methods.clone(),
+        )
+        .await?;
+
+        let storage_result_stream = storage_items.filter_map(|val| async move {
+            let val = match val {
+                Ok(val) => val,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let StorageResultType::Hash(result) = val.result else {
+                return None;
+            };
+            Some(Ok(StorageResponse {
+                key: val.key.0,
+                value: result.0,
+            }))
+        });
+
+        Ok(StreamOf(Box::pin(storage_result_stream)))
+    }
+
+    async fn storage_fetch_closest_descendant_merkle_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        let mut pending = keys;
+        let mut values = Vec::new();
+
+        // The node may not accept every query that we give it each time,
+        // so keep sending the rest until they have all been handled.
+        while !pending.is_empty() {
+            let queries = pending.iter().map(|key| StorageQuery {
+                key: &**key,
+                query_type: StorageQueryType::ClosestDescendantMerkleValue,
+            });
+            let storage_items = StorageItems::from_methods(
+                queries,
+                None,
+                at,
+                &self.follow_handle,
+                self.methods.clone(),
+            )
+            .await?;
+
+            let discarded_items = storage_items.discarded_items();
+            if discarded_items >= pending.len() {
+                return Err(RpcError::request_rejected("limit reached").into());
+            }
+            pending.drain(..pending.len() - discarded_items);
+
+            let items: Vec<_> = storage_items.try_collect().await?;
+            values.extend(items.into_iter().filter_map(|val| {
+                let StorageResultType::ClosestDescendantMerkleValue(result) = val.result else {
+                    return None;
+                };
+                Some(Ok(StorageResponse {
+                    key: val.key.0,
+                    value: result.0,
+                }))
+            }));
+        }
+
+        Ok(Some(StreamOf(Box::pin(futures::stream::iter(values)))))
+    }
+
+    async fn stream_storage_value(
+        &self,
+        key: Vec<u8>,
+    ) -> Result<StreamOfResults<(BlockRef<T::Hash>, Option<Vec<u8>>)>, Error> {
+        // Like `state_subscribeStorage`, we look at the value as of the current finalized
+        // block and then at each new best block.
+        let block_refs = self.follow_handle.subscribe().events().flat_map(|ev| {
+            let block_refs = match ev {
+                FollowEvent::Initialized(init) => init
+                    .finalized_block_hashes
+                    .last()
+                    .cloned()
+                    .into_iter()
+                    .collect(),
+                FollowEvent::BestBlockChanged(ev) => vec![ev.best_block_hash],
+                _ => vec![],
+            };
+            futures::stream::iter(block_refs)
+        });
+
+        // Fetch the hash of the value at each block, which is cheaper than fetching the value..
+        let key = Arc::new(key);
+        let value_hashes = block_refs.then({
+            let key = key.clone();
+            let methods = self.methods.clone();
+            let follow_handle = self.follow_handle.clone();
+            move |block_ref| {
+                let key = key.clone();
+                let methods = methods.clone();
+                let follow_handle = follow_handle.clone();
+                async move {
+                    let hash = fetch_storage_item(
+                        &key,
+                        StorageQueryType::Hash,
+                        block_ref.hash(),
+                        &follow_handle,
+                        methods,
+                    )
+                    .await?;
+                    Ok::<_, Error>((block_ref, hash))
+                }
+            }
+        });
+
+        // ..and then only fetch the value when this hash changes.
+        let mut last_hash = None;
+        let changed_blocks = value_hashes.filter_map(move |res| {
+            let changed = match res {
+                Err(e) => Some(Err(e)),
+                Ok((block_ref, hash)) if last_hash.as_ref() != Some(&hash) => {
+                    last_hash = Some(hash.clone());
+                    Some(Ok((block_ref, hash.is_some())))
+                }
+                Ok(_) => None,
+            };
+            std::future::ready(changed)
+        });
+
+        let methods = self.methods.clone();
+        let follow_handle = self.follow_handle.clone();
+        let values = changed_blocks.then(move |res| {
+            let key = key.clone();
+            let methods = methods.clone();
+            let follow_handle = follow_handle.clone();
+            async move {
+                let (block_ref, has_value) = res?;
+                let value = if has_value {
+                    fetch_storage_item(
+                        &key,
+                        StorageQueryType::Value,
+                        block_ref.hash(),
+                        &follow_handle,
+                        methods,
+                    )
+                    .await?
+                } else {
+                    None
+                };
+                Ok((block_ref.into(), value))
+            }
+        });
+
+        Ok(StreamOf(Box::pin(values)))
+    }
+
+    async fn storage_fetch_child_values(
+        &self,
+        child_key: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_values(Some(&child_key), keys, at).await
+    }
+
+    async fn storage_fetch_child_descendant_keys(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        self.fetch_storage_descendant_keys(Some(&child_key), key, at)
+            .await
+    }
+
+    async fn storage_fetch_child_descendant_values(
+        &self,
+        child_key: Vec<u8>,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        self.fetch_storage_descendant_values(Some(&child_key), key, at)
+            .await
+    }
 
     async fn genesis_hash(&self) -> Result<T::Hash, Error> {
         self.methods.chainspec_v1_genesis_hash().await
@@ -672,6 +902,30 @@ impl<T: Config + Send + Sync + 'static> Backend<T> for UnstableBackend<T> {
     }
 }
 
+/// A helper to fetch a single value or hash from storage.
+async fn fetch_storage_item<T: Config>(
+    key: &[u8],
+    query_type: StorageQueryType,
+    at: T::Hash,
+    follow_handle: &FollowStreamDriverHandle<T::Hash>,
+    methods: UnstableRpcMethods<T>,
+) -> Result<Option<Vec<u8>>, Error> {
+    let query = StorageQuery { key, query_type };
+    let mut items =
+        StorageItems::from_methods(std::iter::once(query), None, at, follow_handle, methods)
+            .await?;
+
+    while let Some(item) = items.next().await {
+        match item?.result {
+            StorageResultType::Value(bytes) | StorageResultType::Hash(bytes) => {
+                return Ok(Some(bytes.0))
+            }
+            StorageResultType::ClosestDescendantMerkleValue(_) => {}
+        }
+    }
+    Ok(None)
+}
+
 /// A helper to obtain a subscription ID.
 async fn get_subscription_id<Hash: BlockHash>(
     follow_handle: &FollowStreamDriverHandle<Hash>,
@@ -682,3 +936,360 @@ async fn get_subscription_id<Hash: BlockHash>(
 
     Ok(sub_id)
 }
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use super::*;
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClientT};
+    use crate::config::substrate::H256;
+    use crate::SubstrateConfig;
+    use futures::channel::mpsc;
+    use futures::FutureExt;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    type FollowItem = Result<Box<RawValue>, RpcError>;
+
+    /// Serves `chainHead_v1_*` requests from some storage per block, sending the results of
+    /// storage operations on the follow subscription as a node would. Storage requests for
+    /// blocks without any storage are rejected, as they would be for blocks the node has pruned.
+    pub struct MockChainHead {
+        storage: HashMap<H256, BTreeMap<Vec<u8>, Vec<u8>>>,
+        follow_tx: mpsc::UnboundedSender<FollowItem>,
+        follow_rx: Mutex<Option<mpsc::UnboundedReceiver<FollowItem>>>,
+        next_operation_id: AtomicUsize,
+        /// The block, type and key of each storage query made.
+        pub storage_queries: Mutex<Vec<(H256, String, Vec<u8>)>>,
+        /// The blocks that have been unpinned.
+        pub unpinned: Mutex<Vec<H256>>,
+    }
+
+    impl MockChainHead {
+        pub fn new(
+            storage: impl IntoIterator<Item = (u64, BTreeMap<Vec<u8>, Vec<u8>>)>,
+        ) -> Arc<Self> {
+            let (follow_tx, follow_rx) = mpsc::unbounded();
+            Arc::new(MockChainHead {
+                storage: storage.into_iter().map(|(n, s)| (hash(n), s)).collect(),
+                follow_tx,
+                follow_rx: Mutex::new(Some(follow_rx)),
+                next_operation_id: AtomicUsize::new(0),
+                storage_queries: Mutex::new(vec![]),
+                unpinned: Mutex::new(vec![]),
+            })
+        }
+
+        /// Send a follow event to the backend.
+        pub fn send(&self, ev: serde_json::Value) {
+            let ev = RawValue::from_string(ev.to_string()).unwrap();
+            self.follow_tx.unbounded_send(Ok(ev)).unwrap();
+        }
+
+        fn storage_items(&self, at: H256, items: &[serde_json::Value]) -> Vec<serde_json::Value> {
+            let storage = &self.storage[&at];
+            let mut results = vec![];
+            for item in items {
+                let key = from_hex(item["key"].as_str().unwrap());
+                let query_type = item["type"].as_str().unwrap();
+                self.storage_queries
+                    .lock()
+                    .unwrap()
+                    .push((at, query_type.to_owned(), key.clone()));
+
+                let entries: Vec<_> = match query_type {
+                    "descendantsValues" => storage
+                        .range(key.clone()..)
+                        .take_while(|(k, _)| k.starts_with(&key))
+                        .collect(),
+                    _ => storage.get_key_value(&key).into_iter().collect(),
+                };
+                for (k, v) in entries {
+                    let (field, v) = match query_type {
+                        "hash" => ("hash", sp_crypto_hashing::blake2_256(v).to_vec()),
+                        _ => ("value", v.clone()),
+                    };
+                    results.push(serde_json::json!({ "key": to_hex(k), field: to_hex(&v) }));
+                }
+            }
+            results
+        }
+    }
+
+    impl RpcClientT for MockChainHead {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            let params: Vec<serde_json::Value> =
+                serde_json::from_str(params.unwrap().get()).unwrap();
+
+            let res = match method {
+                "chainHead_v1_storage" => {
+                    let at: H256 = serde_json::from_value(params[1].clone()).unwrap();
+                    if !self.storage.contains_key(&at) {
+                        let err = RpcError::request_rejected("Invalid block hash");
+                        return async move { Err(err) }.boxed();
+                    }
+
+                    let operation_id = self
+                        .next_operation_id
+                        .fetch_add(1, Ordering::Relaxed)
+                        .to_string();
+                    let items = self.storage_items(at, params[2].as_array().unwrap());
+                    self.send(serde_json::json!({
+                        "event": "operationStorageItems",
+                        "operationId": operation_id,
+                        "items": items,
+                    }));
+                    self.send(serde_json::json!({
+                        "event": "operationStorageDone",
+                        "operationId": operation_id,
+                    }));
+                    serde_json::json!({ "result": "started", "operationId": operation_id })
+                }
+                "chainHead_v1_unpin" => {
+                    let at: H256 = serde_json::from_value(params[1].clone()).unwrap();
+                    self.unpinned.lock().unwrap().push(at);
+                    serde_json::Value::Null
+                }
+                _ => panic!("Unexpected method {method}"),
+            };
+
+            let res = RawValue::from_string(res.to_string()).unwrap();
+            async move { Ok(res) }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            assert_eq!(sub, "chainHead_v1_follow");
+            let stream = self
+                .follow_rx
+                .lock()
+                .unwrap()
+                .take()
+                .expect("only one follow subscription is expected");
+            let sub = RawRpcSubscription {
+                stream: Box::pin(stream),
+                id: Some("sub".to_owned()),
+            };
+            async move { Ok(sub) }.boxed()
+        }
+    }
+
+    pub fn hash(n: u64) -> H256 {
+        H256::from_low_u64_le(n)
+    }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        hex::decode(s.trim_start_matches("0x")).unwrap()
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    pub fn ev_initialized(n: u64) -> serde_json::Value {
+        serde_json::json!({ "event": "initialized", "finalizedBlockHashes": [hash(n)] })
+    }
+
+    pub fn ev_new_block(parent_n: u64, n: u64) -> serde_json::Value {
+        serde_json::json!({
+            "event": "newBlock",
+            "blockHash": hash(n),
+            "parentBlockHash": hash(parent_n),
+        })
+    }
+
+    pub fn ev_best_block(n: u64) -> serde_json::Value {
+        serde_json::json!({ "event": "bestBlockChanged", "bestBlockHash": hash(n) })
+    }
+
+    pub fn ev_finalized(finalized_n: u64, pruned_ns: &[u64]) -> serde_json::Value {
+        let pruned: Vec<_> = pruned_ns.iter().map(|n| hash(*n)).collect();
+        serde_json::json!({
+            "event": "finalized",
+            "finalizedBlockHashes": [hash(finalized_n)],
+            "prunedBlockHashes": pruned,
+        })
+    }
+
+    /// Build an [`UnstableBackend`] on top of the mock, driving it in the background.
+    pub fn test_backend(mock: Arc<MockChainHead>) -> UnstableBackend<SubstrateConfig> {
+        let (backend, mut driver) = UnstableBackend::builder().build(mock);
+        tokio::spawn(async move { while driver.next().await.is_some() {} });
+        backend
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_utils::*;
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    /// Storage where `b"foo"` has the given value.
+    fn foo(value: Option<&[u8]>) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        value
+            .map(|v| (b"foo".to_vec(), v.to_vec()))
+            .into_iter()
+            .collect()
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> S::Item {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for the next item")
+            .expect("stream ended unexpectedly")
+    }
+
+    async fn wait_for(f: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !f() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for condition")
+    }
+
+    #[tokio::test]
+    async fn streams_storage_value_changes_at_best_blocks() {
+        let mock = MockChainHead::new([
+            (0, foo(Some(b"a"))),
+            (1, foo(Some(b"a"))),
+            (2, foo(Some(b"b"))),
+            (3, foo(None)),
+            (4, foo(None)),
+            (5, foo(Some(b"a"))),
+        ]);
+        let backend = test_backend(mock.clone());
+        let mut values = backend
+            .stream_storage_value(b"foo".to_vec())
+            .await
+            .unwrap()
+            .map_ok(|(block_ref, value)| (block_ref.hash(), value));
+
+        mock.send(ev_initialized(0));
+        for n in 1..=5 {
+            mock.send(ev_new_block(n - 1, n));
+            mock.send(ev_best_block(n));
+        }
+
+        // Values are handed back only when they change from one best block to the next:
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(0), Some(b"a".to_vec()))
+        );
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(2), Some(b"b".to_vec()))
+        );
+        assert_eq!(next(&mut values).await.unwrap(), (hash(3), None));
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(5), Some(b"a".to_vec()))
+        );
+
+        // The hash of the value is checked at every block, but the value itself is
+        // only fetched when the hash changes to something other than nothing:
+        let queries = mock.storage_queries.lock().unwrap().clone();
+        let hash_queries: Vec<_> = queries.iter().filter(|(_, ty, _)| ty == "hash").collect();
+        let value_queries: Vec<_> = queries
+            .iter()
+            .filter(|(_, ty, _)| ty == "value")
+            .map(|(at, _, _)| *at)
+            .collect();
+        assert_eq!(hash_queries.len(), 6);
+        assert_eq!(value_queries, vec![hash(0), hash(2), hash(5)]);
+    }
+
+    #[tokio::test]
+    async fn storage_value_stream_reports_rejected_blocks_and_carries_on() {
+        // Storage at block 1 is unavailable (ie it's been pruned by the node).
+        let mock = MockChainHead::new([(0, foo(Some(b"a"))), (2, foo(Some(b"b")))]);
+        let backend = test_backend(mock.clone());
+        let mut values = backend
+            .stream_storage_value(b"foo".to_vec())
+            .await
+            .unwrap()
+            .map_ok(|(block_ref, value)| (block_ref.hash(), value));
+
+        mock.send(ev_initialized(0));
+        for n in 1..=2 {
+            mock.send(ev_new_block(n - 1, n));
+            mock.send(ev_best_block(n));
+        }
+
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(0), Some(b"a".to_vec()))
+        );
+        assert!(next(&mut values).await.is_err());
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(2), Some(b"b".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn storage_value_stream_does_not_keep_pruned_blocks_pinned() {
+        // Blocks 1 and 2 are forks from block 0; block 2 will be finalized and block 1 pruned.
+        let mock = MockChainHead::new([
+            (0, foo(Some(b"a"))),
+            (1, foo(Some(b"b"))),
+            (2, foo(Some(b"c"))),
+            (3, foo(Some(b"c"))),
+        ]);
+        let backend = test_backend(mock.clone());
+        let mut values = backend
+            .stream_storage_value(b"foo".to_vec())
+            .await
+            .unwrap()
+            .map_ok(|(block_ref, value)| (block_ref.hash(), value));
+
+        mock.send(ev_initialized(0));
+        mock.send(ev_new_block(0, 1));
+        mock.send(ev_best_block(1));
+        mock.send(ev_new_block(0, 2));
+        mock.send(ev_best_block(2));
+
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(0), Some(b"a".to_vec()))
+        );
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(1), Some(b"b".to_vec()))
+        );
+        assert_eq!(
+            next(&mut values).await.unwrap(),
+            (hash(2), Some(b"c".to_vec()))
+        );
+
+        // Block 1 is pruned. The stream holds onto the events it's given until it's
+        // polled, so keep polling it, and wait until it's moved on to block 3.
+        tokio::spawn(async move { while values.next().await.is_some() {} });
+        mock.send(ev_finalized(2, &[1]));
+        mock.send(ev_new_block(2, 3));
+        mock.send(ev_best_block(3));
+        wait_for(|| {
+            mock.storage_queries.lock().unwrap().contains(&(
+                hash(3),
+                "hash".to_owned(),
+                b"foo".to_vec(),
+            ))
+        })
+        .await;
+
+        // With nothing else holding onto it, block 1 is unpinned on the next finalized event.
+        mock.send(ev_finalized(3, &[]));
+        wait_for(|| mock.unpinned.lock().unwrap().contains(&hash(1))).await;
+    }
+}
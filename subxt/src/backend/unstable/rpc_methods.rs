@@ -259,7 +259,19 @@ impl<T: Config> UnstableRpcMethods<T> {
     /// Returns an array of strings indicating the names of all the JSON-RPC functions supported by
     /// the JSON-RPC server.
     pub async fn rpc_methods(&self) -> Result<Vec<String>, Error> {
-        self.client.request("rpc_methods", rpc_params![]).await
+        // Some servers return the array of methods directly, and others (including
+        // Substrate nodes) return an object containing it.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RpcMethods {
+            List(Vec<String>),
+            Object { methods: Vec<String> },
+        }
+
+        let methods = self.client.request("rpc_methods", rpc_params![]).await?;
+        Ok(match methods {
+            RpcMethods::List(methods) | RpcMethods::Object { methods } => methods,
+        })
     }
 
     /// Attempt to submit a transaction, returning events about its progress.
@@ -300,6 +312,129 @@ impl<T: Config> UnstableRpcMethods<T> {
             .request("transaction_v1_stop", rpc_params![operation_id])
             .await
     }
+
+    /// Return the height of the latest finalized block.
+    pub async fn archive_unstable_finalized_height(&self) -> Result<u64, Error> {
+        #[derive(Deserialize)]
+        struct Height(#[serde(with = "unsigned_number_as_string")] u64);
+
+        let Height(height) = self
+            .client
+            .request("archive_unstable_finalizedHeight", rpc_params![])
+            .await?;
+        Ok(height)
+    }
+
+    /// Return the hashes of all blocks at the given height. This can contain more than one
+    /// hash for heights that have not yet been finalized.
+    pub async fn archive_unstable_hash_by_height(
+        &self,
+        height: u64,
+    ) -> Result<Vec<T::Hash>, Error> {
+        self.client
+            .request("archive_unstable_hashByHeight", rpc_params![height])
+            .await
+    }
+
+    /// Get the header of any block known to the node, returning `None` if it's not known.
+    pub async fn archive_unstable_header(&self, hash: T::Hash) -> Result<Option<T::Header>, Error> {
+        // header returned as hex encoded SCALE encoded bytes.
+        let header: Option<Bytes> = self
+            .client
+            .request("archive_unstable_header", rpc_params![hash])
+            .await?;
+
+        let header = header
+            .map(|h| codec::Decode::decode(&mut &*h.0))
+            .transpose()?;
+        Ok(header)
+    }
+
+    /// Get the SCALE encoded extrinsics in any block known to the node, returning `None`
+    /// if it's not known.
+    pub async fn archive_unstable_body(
+        &self,
+        hash: T::Hash,
+    ) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        let body: Option<Vec<Bytes>> = self
+            .client
+            .request("archive_unstable_body", rpc_params![hash])
+            .await?;
+
+        Ok(body.map(|exts| exts.into_iter().map(|e| e.0).collect()))
+    }
+
+    /// Query the storage of any block known to the node.
+    ///
+    /// The node may not process every query that it is given; the number of queries at the
+    /// end of the list which were ignored is given by [`ArchiveStorageResult::discarded_items`],
+    /// and these should be sent again in another request. Queries for descendants may also be
+    /// cut short, in which case they can be resumed by setting
+    /// [`ArchiveStorageQuery::pagination_start_key`] to the last key that was returned.
+    pub async fn archive_unstable_storage(
+        &self,
+        hash: T::Hash,
+        items: impl IntoIterator<Item = ArchiveStorageQuery<&[u8]>>,
+        child_key: Option<&[u8]>,
+    ) -> Result<ArchiveStorageResult, Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Response {
+            Ok(ArchiveStorageResult),
+            Err { error: String },
+        }
+
+        let items: Vec<ArchiveStorageQuery<String>> = items
+            .into_iter()
+            .map(|item| ArchiveStorageQuery {
+                key: to_hex(item.key),
+                query_type: item.query_type,
+                pagination_start_key: item.pagination_start_key.map(to_hex),
+            })
+            .collect();
+
+        let response = self
+            .client
+            .request(
+                "archive_unstable_storage",
+                rpc_params![hash, items, child_key.map(to_hex)],
+            )
+            .await?;
+
+        match response {
+            Response::Ok(res) => Ok(res),
+            Response::Err { error } => Err(Error::Other(error)),
+        }
+    }
+
+    /// Call a runtime API function at any block known to the node, returning the SCALE
+    /// encoded output.
+    pub async fn archive_unstable_call(
+        &self,
+        hash: T::Hash,
+        function: &str,
+        call_parameters: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Response {
+            Success { value: Bytes },
+            Error { error: String },
+        }
+
+        let response = self
+            .client
+            .request(
+                "archive_unstable_call",
+                rpc_params![hash, function, to_hex(call_parameters)],
+            )
+            .await?;
+
+        match response {
+            Response::Success { value } => Ok(value.0),
+            Response::Error { error } => Err(Error::Other(error)),
+        }
+    }
 }
 
 /// This represents events generated by the `follow` method.
@@ -625,6 +760,31 @@ pub enum StorageQueryType {
     DescendantsHashes,
 }
 
+/// A storage query given to `archive_unstable_storage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageQuery<Key> {
+    /// The provided key.
+    pub key: Key,
+    /// The type of the storage query.
+    #[serde(rename = "type")]
+    pub query_type: StorageQueryType,
+    /// For descendant queries, only return keys which come after this one.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pagination_start_key: Option<Key>,
+}
+
+/// The response of `archive_unstable_storage`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageResult {
+    /// The results of the queries that were processed.
+    #[serde(rename = "result")]
+    pub items: Vec<StorageResult>,
+    /// The number of queries from the back of the list that were not processed.
+    pub discarded_items: usize,
+}
+
 /// A subscription which returns follow events, and ends when a Stop event occurs.
 pub struct FollowSubscription<Hash> {
     sub: RpcSubscription<FollowEvent<Hash>>,
@@ -892,6 +1052,35 @@ pub(crate) mod hashmap_as_tuple_list {
 mod test {
     use super::*;
 
+    #[test]
+    fn can_deserialize_archive_storage_result() {
+        let response = serde_json::json!({
+            "result": [
+                { "key": "0x0102", "value": "0x03" },
+                { "key": "0x0104", "hash": "0x05" }
+            ],
+            "discardedItems": 1
+        });
+        let result: ArchiveStorageResult = serde_json::from_value(response).unwrap();
+
+        assert_eq!(
+            result,
+            ArchiveStorageResult {
+                items: vec![
+                    StorageResult {
+                        key: Bytes(vec![1, 2]),
+                        result: StorageResultType::Value(Bytes(vec![3])),
+                    },
+                    StorageResult {
+                        key: Bytes(vec![1, 4]),
+                        result: StorageResultType::Hash(Bytes(vec![5])),
+                    },
+                ],
+                discarded_items: 1,
+            }
+        );
+    }
+
     #[test]
     fn can_deserialize_apis_from_tuple_or_object() {
         let old_response = serde_json::json!({
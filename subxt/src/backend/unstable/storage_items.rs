@@ -21,6 +21,7 @@ use std::task::{Context, Poll};
 pub struct StorageItems<T: Config> {
     done: bool,
     operation_id: Arc<str>,
+    discarded_items: usize,
     buffered_responses: VecDeque<StorageResult>,
     continue_call: ContinueFutGetter,
     continue_fut: Option<ContinueFut>,
@@ -29,10 +30,11 @@ pub struct StorageItems<T: Config> {
 
 impl<T: Config> StorageItems<T> {
     // Subscribe to follow events, and return a stream of storage results
-    // given some storage queries. The stream will automatically resume as
-    // needed, and stop when done.
+    // given some storage queries, optionally against a child trie. The stream
+    // will automatically resume as needed, and stop when done.
     pub async fn from_methods(
         queries: impl Iterator<Item = StorageQuery<&[u8]>>,
+        child_key: Option<&[u8]>,
         at: T::Hash,
         follow_handle: &FollowStreamDriverHandle<T::Hash>,
         methods: UnstableRpcMethods<T>,
@@ -42,13 +44,13 @@ impl<T: Config> StorageItems<T> {
         // Subscribe to events and make the initial request to get an operation ID.
         let follow_events = follow_handle.subscribe().events();
         let status = methods
-            .chainhead_v1_storage(&sub_id, at, queries, None)
+            .chainhead_v1_storage(&sub_id, at, queries, child_key)
             .await?;
-        let operation_id: Arc<str> = match status {
+        let (operation_id, discarded_items): (Arc<str>, _) = match status {
             MethodResponse::LimitReached => {
                 return Err(RpcError::request_rejected("limit reached").into())
             }
-            MethodResponse::Started(s) => s.operation_id.into(),
+            MethodResponse::Started(s) => (s.operation_id.into(), s.discarded_items),
         };
 
         // A function which returns the call to continue the subscription:
@@ -65,13 +67,21 @@ impl<T: Config> StorageItems<T> {
 
         Ok(StorageItems::new(
             operation_id,
+            discarded_items.unwrap_or(0),
             continue_call,
             Box::pin(follow_events),
         ))
     }
 
+    /// The number of queries from the end of those given which the node
+    /// did not accept, and which will need to be sent again.
+    pub fn discarded_items(&self) -> usize {
+        self.discarded_items
+    }
+
     fn new(
         operation_id: Arc<str>,
+        discarded_items: usize,
         continue_call: ContinueFutGetter,
         follow_event_stream: FollowEventStream<T::Hash>,
     ) -> Self {
@@ -79,6 +89,7 @@ impl<T: Config> StorageItems<T> {
             done: false,
             buffered_responses: VecDeque::new(),
             operation_id,
+            discarded_items,
             continue_call,
             continue_fut: None,
             follow_event_stream,
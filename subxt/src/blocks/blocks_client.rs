@@ -6,7 +6,7 @@ use super::Block;
 use crate::{
     backend::{BlockRef, StreamOfResults},
     client::OnlineClientT,
-    config::Config,
+    config::{Config, Header},
     error::{BlockError, Error},
     utils::PhantomDataSendSync,
 };
@@ -45,7 +45,8 @@ where
     ///
     /// This call only supports blocks produced since the most recent
     /// runtime upgrade. You can attempt to retrieve older blocks,
-    /// but may run into errors attempting to work with them.
+    /// but may run into errors attempting to work with them. Use
+    /// [`BlocksClient::at_historic()`] to work with older blocks.
     pub fn at(
         &self,
         block_ref: impl Into<BlockRef<T::Hash>>,
@@ -60,6 +61,35 @@ where
         self.at_or_latest(None)
     }
 
+    /// Obtain block details given the provided block hash. Unlike [`BlocksClient::at()`], the
+    /// returned block works with the runtime version and metadata that the block was produced
+    /// with, rather than those of the client, so its extrinsics, events and storage can be decoded
+    /// across runtime upgrades. The client is obtained via [`OnlineClientT::at_runtime_of()`];
+    /// an [`crate::OnlineClient`] fetches metadata once per runtime version and then caches it.
+    pub fn at_historic(
+        &self,
+        block_ref: impl Into<BlockRef<T::Hash>>,
+    ) -> impl Future<Output = Result<Block<T, Client>, Error>> + Send + 'static {
+        let client = self.client.clone();
+        let block_ref = block_ref.into();
+        async move {
+            let block_header = match client.backend().block_header(block_ref.hash()).await? {
+                Some(header) => header,
+                None => return Err(BlockError::not_found(block_ref.hash()).into()),
+            };
+
+            // A block is executed by the runtime in the state of its parent block. The
+            // genesis block has no parent, and so we use the runtime it contains.
+            let runtime_block_hash = match block_header.parent_hash() {
+                Some(parent_hash) if block_header.number().into() > 0 => parent_hash,
+                _ => block_ref.hash(),
+            };
+            let client = client.at_runtime_of(runtime_block_hash).await?;
+
+            Ok(Block::new(block_header, block_ref, client))
+        }
+    }
+
     /// Obtain block details given the provided block hash, or the latest block if `None` is
     /// provided.
     fn at_or_latest(
@@ -156,3 +186,225 @@ where
     });
     BlockStreamRes::Ok(StreamOfResults::new(Box::pin(sub)))
 }
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::{BlakeTwo256, SubstrateHeader, H256};
+    use crate::config::SubstrateExtrinsicParams;
+    use crate::utils::{AccountId32, MultiAddress, MultiSignature};
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::{Decode, Encode};
+    use std::sync::Arc;
+
+    /// Like [`SubstrateConfig`], but headers don't say what their parent hash is.
+    enum NoParentConfig {}
+
+    impl Config for NoParentConfig {
+        type Hash = H256;
+        type AccountId = AccountId32;
+        type Address = MultiAddress<Self::AccountId, u32>;
+        type Signature = MultiSignature;
+        type Hasher = BlakeTwo256;
+        type Header = NoParentHeader;
+        type ExtrinsicParams = SubstrateExtrinsicParams<Self>;
+        type AssetId = u32;
+    }
+
+    #[derive(Debug, Encode, Decode, serde::Deserialize)]
+    #[serde(transparent)]
+    struct NoParentHeader(SubstrateHeader<u32, BlakeTwo256>);
+
+    impl Header for NoParentHeader {
+        type Number = u32;
+        type Hasher = BlakeTwo256;
+
+        fn number(&self) -> u32 {
+            self.0.number
+        }
+    }
+
+    fn core_version(spec_version: u32) -> Vec<u8> {
+        let apis: Vec<([u8; 8], u32)> = Vec::new();
+        ("test", "test", 1u32, spec_version, 1u32, apis, 1u32).encode()
+    }
+
+    /// Four blocks, where the runtime is upgraded in block 2. Block 2 is still produced by
+    /// the old runtime (that of its parent), and block 3 is the first produced by the new one.
+    /// The old runtime has the tiny metadata, and the new one the small metadata.
+    async fn client_and_hashes<T: Config<Hash = H256>>(
+        to_header: impl Fn(SubstrateHeader<u32, BlakeTwo256>) -> T::Header,
+    ) -> (OnlineClient<T>, Vec<H256>) {
+        let mut headers = vec![test_header(0, H256::zero())];
+        for n in 1..4 {
+            let parent_hash = headers.last().unwrap().hash();
+            headers.push(test_header(n, parent_hash));
+        }
+        let hashes: Vec<H256> = headers.iter().map(|h| h.hash()).collect();
+
+        let tiny = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let small = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let mut builder = MockBackend::<T>::builder()
+            .metadata(small)
+            .metadata_at(hashes[0], tiny.clone())
+            .metadata_at(hashes[1], tiny);
+        for (n, (header, hash)) in headers.into_iter().zip(&hashes).enumerate() {
+            let spec_version = if n < 2 { 1 } else { 2 };
+            builder = builder
+                .block(to_header(header), vec![])
+                .runtime_api_call_at(*hash, "Core_version", core_version(spec_version));
+        }
+
+        let client = OnlineClient::from_backend(Arc::new(builder.build()))
+            .await
+            .unwrap();
+        (client, hashes)
+    }
+
+    // Only the small metadata knows about the `Timestamp` pallet. This works with any
+    // client, rather than just an `OnlineClient`.
+    async fn historic_blocks_have_new_metadata<T: Config, C: OnlineClientT<T>>(
+        client: &C,
+        hashes: &[T::Hash],
+    ) -> Vec<bool> {
+        let address = crate::storage::dynamic("Timestamp", "Now", ());
+        let blocks = BlocksClient::new(client.clone());
+        let mut has_new_metadata = Vec::new();
+        for hash in hashes {
+            let block = blocks.at_historic(*hash).await.unwrap();
+            has_new_metadata.push(block.storage().fetch(&address).await.is_ok());
+        }
+        has_new_metadata
+    }
+
+    #[tokio::test]
+    async fn at_runtime_of_follows_runtime_upgrades() {
+        let (client, hashes) = client_and_hashes::<SubstrateConfig>(|h| h).await;
+
+        let old = client.at_runtime_of(hashes[1]).await.unwrap();
+        let new = client.at_runtime_of(hashes[2]).await.unwrap();
+        assert_eq!(old.runtime_version().spec_version, 1);
+        assert_eq!(new.runtime_version().spec_version, 2);
+        assert!(old.metadata().pallet_by_name("Timestamp").is_none());
+        assert!(new.metadata().pallet_by_name("Timestamp").is_some());
+
+        // Metadata is cached by spec version, and shared with the clients handed back.
+        let cached = new.at_runtime_of(hashes[0]).await.unwrap();
+        assert_eq!(cached.runtime_version().spec_version, 1);
+        assert!(std::ptr::eq(
+            cached.metadata().types(),
+            old.metadata().types()
+        ));
+    }
+
+    #[tokio::test]
+    async fn at_historic_uses_runtime_of_parent_block() {
+        let (client, hashes) = client_and_hashes::<SubstrateConfig>(|h| h).await;
+
+        // The genesis block has no parent, and so uses its own runtime.
+        let has_new_metadata =
+            historic_blocks_have_new_metadata(&client, &[hashes[0], hashes[2], hashes[3]]).await;
+        assert_eq!(has_new_metadata, vec![false, false, true]);
+    }
+
+    #[tokio::test]
+    async fn at_historic_uses_own_runtime_without_parent_hash() {
+        let (client, hashes) = client_and_hashes::<NoParentConfig>(NoParentHeader).await;
+
+        // Without a parent hash, the runtime in the block's own state is used instead.
+        let has_new_metadata = historic_blocks_have_new_metadata(&client, &hashes[1..3]).await;
+        assert_eq!(has_new_metadata, vec![false, true]);
+    }
+
+    #[cfg(feature = "legacy")]
+    #[tokio::test]
+    async fn at_runtime_of_converts_legacy_metadata() {
+        use crate::metadata::types::{LegacyTypeDef, LegacyTypes};
+        use frame_metadata::decode_different::DecodeDifferent::Decoded;
+        use frame_metadata::{v13, RuntimeMetadata, RuntimeMetadataPrefixed};
+
+        let module = v13::ModuleMetadata {
+            name: Decoded("Legacy".to_string()),
+            storage: Some(Decoded(v13::StorageMetadata {
+                prefix: Decoded("Legacy".to_string()),
+                entries: Decoded(vec![v13::StorageEntryMetadata {
+                    name: Decoded("Now".to_string()),
+                    modifier: v13::StorageEntryModifier::Default,
+                    ty: v13::StorageEntryType::Plain(Decoded("T::Moment".to_string())),
+                    default: Decoded(vec![0; 8]),
+                    documentation: Decoded(vec![]),
+                }]),
+            })),
+            calls: None,
+            event: None,
+            constants: Decoded(vec![]),
+            errors: Decoded(vec![]),
+            index: 0,
+        };
+        let legacy_metadata = RuntimeMetadataPrefixed(
+            frame_metadata::META_RESERVED,
+            RuntimeMetadata::V13(v13::RuntimeMetadataV13 {
+                modules: Decoded(vec![module]),
+                extrinsic: v13::ExtrinsicMetadata {
+                    version: 4,
+                    signed_extensions: vec![],
+                },
+            }),
+        );
+        let mut types = LegacyTypes::new();
+        types
+            .insert("Moment", LegacyTypeDef::Alias("u64".to_string()))
+            .insert("Address", LegacyTypeDef::Alias("[u8; 32]".to_string()))
+            .insert(
+                "ExtrinsicSignature",
+                LegacyTypeDef::Alias("[u8; 64]".to_string()),
+            );
+
+        // The second runtime provides version 2 of the `Metadata` API, and so its metadata
+        // is never treated as legacy metadata, even if fetching it fails.
+        let metadata_api = (sp_crypto_hashing::blake2_64(b"Metadata"), 2u32);
+        let versioned_core_version = |spec_version: u32| {
+            (
+                "test",
+                "test",
+                1u32,
+                spec_version,
+                1u32,
+                vec![metadata_api],
+                1u32,
+            )
+                .encode()
+        };
+
+        let genesis = test_header(0, H256::zero());
+        let block1 = test_header(1, genesis.hash());
+        let block2 = test_header(2, block1.hash());
+        let (old_hash, broken_hash, new_hash) = (genesis.hash(), block1.hash(), block2.hash());
+        let small = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(small)
+            .metadata_at(old_hash, legacy_metadata.encode())
+            .metadata_at(broken_hash, legacy_metadata.encode())
+            .block(genesis, vec![])
+            .block(block1, vec![])
+            .block(block2, vec![])
+            .runtime_api_call_at(old_hash, "Core_version", core_version(1))
+            .runtime_api_call_at(new_hash, "Core_version", versioned_core_version(2))
+            .runtime_api_call_at(broken_hash, "Core_version", versioned_core_version(3))
+            .build();
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .legacy_types(types)
+            .build(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let old = client.at_runtime_of(old_hash).await.unwrap();
+        assert!(old.metadata().pallet_by_name("Legacy").is_some());
+        assert!(old.metadata().pallet_by_name("Timestamp").is_none());
+
+        let new = client.at_runtime_of(new_hash).await.unwrap();
+        assert!(new.metadata().pallet_by_name("Timestamp").is_some());
+        assert!(client.at_runtime_of(broken_hash).await.is_err());
+    }
+}
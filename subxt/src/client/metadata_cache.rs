@@ -0,0 +1,187 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::Metadata;
+use codec::{Decode, Encode};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use subxt_core::client::RuntimeVersion;
+
+/// Distinguishes the temporary files written by each call to [`MetadataCache::store()`].
+static NEXT_TMP_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Stores encoded metadata in a directory on disk, keyed by the genesis hash of the chain,
+/// the runtime version that the metadata belongs to and the version of the metadata that was
+/// asked for, so that it doesn't need to be downloaded from a node again. See [`crate::client::OnlineClientBuilder::metadata_cache()`].
+///
+/// Failing to read from or write to the cache is never an error; metadata is instead
+/// fetched from the node as it would be without a cache.
+#[derive(Debug, Clone)]
+pub(crate) struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl MetadataCache {
+    /// Cache metadata in the given directory, which is created if it doesn't exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Load metadata from the cache, if it's present.
+    pub fn load<Hash: Encode>(
+        &self,
+        genesis_hash: &Hash,
+        runtime_version: &RuntimeVersion,
+        metadata_version: Option<u32>,
+    ) -> Option<Metadata> {
+        let path = self.path(genesis_hash, runtime_version, metadata_version);
+        let bytes = std::fs::read(&path).ok()?;
+        match Metadata::decode(&mut &*bytes) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                tracing::warn!("Ignoring cached metadata at {path:?} which can't be decoded: {e}");
+                None
+            }
+        }
+    }
+
+    /// Store metadata in the cache, replacing anything stored for the same runtime version.
+    pub fn store<Hash: Encode>(
+        &self,
+        genesis_hash: &Hash,
+        runtime_version: &RuntimeVersion,
+        metadata_version: Option<u32>,
+        metadata: &Metadata,
+    ) {
+        let path = self.path(genesis_hash, runtime_version, metadata_version);
+
+        // Write to a temporary file and then move it into place, so that other processes
+        // and threads sharing the cache never see a partially written file.
+        let tmp_path = path.with_extension(format!(
+            "scale.{}.{}.tmp",
+            std::process::id(),
+            NEXT_TMP_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let res = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&tmp_path, (**metadata).encode()))
+            .and_then(|_| std::fs::rename(&tmp_path, &path));
+
+        if let Err(e) = res {
+            tracing::warn!("Failed to cache metadata at {path:?}: {e}");
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+    }
+
+    /// Metadata fetched without asking for a specific version is the latest that we support,
+    /// which is keyed as "latest".
+    fn path<Hash: Encode>(
+        &self,
+        genesis_hash: &Hash,
+        runtime_version: &RuntimeVersion,
+        metadata_version: Option<u32>,
+    ) -> PathBuf {
+        let metadata_version = match metadata_version {
+            Some(version) => format!("v{version}"),
+            None => "latest".to_owned(),
+        };
+        self.dir.join(format!(
+            "{}-{}-{}-{metadata_version}.scale",
+            hex::encode(genesis_hash.encode()),
+            runtime_version.spec_version,
+            runtime_version.transaction_version
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::MetadataCache;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::client::RuntimeVersion;
+    use crate::config::substrate::H256;
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Decode;
+    use std::sync::Arc;
+
+    fn backend(with_metadata: bool) -> MockBackend<SubstrateConfig> {
+        let builder = MockBackend::builder()
+            .block(test_header(0, H256::zero()), vec![])
+            .runtime_version(RuntimeVersion {
+                spec_version: 1,
+                transaction_version: 2,
+            });
+
+        if with_metadata {
+            let metadata = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+            builder.metadata(metadata).build()
+        } else {
+            builder.build()
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_cached_metadata() {
+        let dir =
+            std::env::temp_dir().join(format!("subxt-metadata-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // Without a cache, we need the node to hand back metadata.
+        assert!(OnlineClient::from_backend(Arc::new(backend(false)))
+            .await
+            .is_err());
+
+        // The first client fetches metadata and stores it..
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_cache(&dir)
+            .build(Arc::new(backend(true)))
+            .await
+            .unwrap();
+
+        // ..and the next client can be built without fetching it.
+        let cached_client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_cache(&dir)
+            .build(Arc::new(backend(false)))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            client.metadata().hasher().hash(),
+            cached_client.metadata().hasher().hash()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_stores_dont_clash() {
+        let dir = std::env::temp_dir().join(format!(
+            "subxt-metadata-cache-concurrent-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = crate::Metadata::decode(&mut &*bytes).unwrap();
+        let cache = MetadataCache::new(&dir);
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 2,
+        };
+
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| cache.store(&H256::zero(), &runtime_version, None, &metadata));
+            }
+        });
+
+        // Every write succeeded and was moved into place, leaving just the one file.
+        assert!(cache.load(&H256::zero(), &runtime_version, None).is_some());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Metadata is also keyed by the version of it that was asked for.
+        assert!(cache
+            .load(&H256::zero(), &runtime_version, Some(14))
+            .is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,11 +8,15 @@
 //! require network access. The [`OnlineClient`] requires network
 //! access.
 
+mod metadata_cache;
 mod offline_client;
 mod online_client;
+mod online_client_builder;
 
 pub use offline_client::{OfflineClient, OfflineClientT};
 pub use online_client::{
-    ClientRuntimeUpdater, OnlineClient, OnlineClientT, RuntimeUpdaterStream, Update, UpgradeError,
+    AtRuntimeOfFut, ClientRuntimeUpdater, OnlineClient, OnlineClientT, RuntimeUpdaterStream,
+    Update, UpgradeError,
 };
+pub use online_client_builder::{BackendKind, OnlineClientBuilder, Spawner};
 pub use subxt_core::client::{ClientState, RuntimeVersion};
@@ -2,7 +2,8 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::{OfflineClient, OfflineClientT};
+use super::metadata_cache::MetadataCache;
+use super::{OfflineClient, OfflineClientT, OnlineClientBuilder};
 use crate::custom_values::CustomValuesClient;
 use crate::{
     backend::{legacy::LegacyBackend, rpc::RpcClient, Backend, BackendExt, StreamOfResults},
@@ -15,8 +16,12 @@ use crate::{
     tx::TxClient,
     Config, Metadata,
 };
+use codec::Decode;
 use derive_where::derive_where;
 use futures::future;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::{Arc, RwLock};
 use subxt_core::client::{ClientState, RuntimeVersion};
 
@@ -25,14 +30,46 @@ use subxt_core::client::{ClientState, RuntimeVersion};
 pub trait OnlineClientT<T: Config>: OfflineClientT<T> {
     /// Return a backend that can be used to communicate with a node.
     fn backend(&self) -> &dyn Backend<T>;
+
+    /// Return a client which uses the runtime version and metadata that were in use in the
+    /// state of the given block. This is used by [`BlocksClient::at_historic()`] to work with
+    /// blocks produced by runtimes other than the current one.
+    ///
+    /// By default, this client is handed back unchanged. [`OnlineClient`] fetches (and caches)
+    /// the runtime version and metadata at the given block; see [`OnlineClient::at_runtime_of()`].
+    fn at_runtime_of(&self, block_hash: T::Hash) -> AtRuntimeOfFut<Self> {
+        let _ = block_hash;
+        let client = self.clone();
+        Box::pin(async move { Ok(client) })
+    }
 }
 
+/// The future returned from [`OnlineClientT::at_runtime_of()`].
+pub type AtRuntimeOfFut<Client> =
+    Pin<Box<dyn Future<Output = Result<Client, Error>> + Send + 'static>>;
+
 /// A client that can be used to perform API calls (that is, either those
 /// requiring an [`OfflineClientT`] or those requiring an [`OnlineClientT`]).
 #[derive_where(Clone)]
 pub struct OnlineClient<T: Config> {
     inner: Arc<RwLock<Inner<T>>>,
     backend: Arc<dyn Backend<T>>,
+    // Metadata for past runtimes, keyed by spec version. See `OnlineClient::at_runtime_of()`.
+    historic_metadata: Arc<RwLock<HashMap<u32, Metadata>>>,
+    // How to obtain metadata for new runtime versions.
+    metadata_options: MetadataOptions,
+}
+
+/// How an [`OnlineClient`] obtains metadata for new runtime versions. See [`OnlineClientBuilder`].
+#[derive(Debug, Clone, Default)]
+pub(super) struct MetadataOptions {
+    /// Store metadata on disk, and look for it there before fetching it from the node.
+    pub cache: Option<MetadataCache>,
+    /// Fetch this version of the metadata rather than the latest one that we support.
+    pub version: Option<u32>,
+    /// Describe the types used in pre-V14 metadata, so that it can be converted.
+    #[cfg(feature = "legacy")]
+    pub legacy_types: Option<Arc<subxt_metadata::LegacyTypes>>,
 }
 
 #[derive_where(Debug)]
@@ -79,6 +116,12 @@ impl<T: Config> OnlineClient<T> {
 }
 
 impl<T: Config> OnlineClient<T> {
+    /// Configure and construct an [`OnlineClient`] via an [`OnlineClientBuilder`], for
+    /// instance to cache metadata on disk between runs.
+    pub fn builder() -> OnlineClientBuilder<T> {
+        OnlineClientBuilder::new()
+    }
+
     /// Construct a new [`OnlineClient`] by providing an [`RpcClient`] to drive the connection.
     /// This will use the current default [`Backend`], which may change in future releases.
     pub async fn from_rpc_client(
@@ -116,16 +159,59 @@ impl<T: Config> OnlineClient<T> {
     /// Construct a new [`OnlineClient`] by providing an underlying [`Backend`]
     /// implementation to power it. Other details will be obtained from the chain.
     pub async fn from_backend<B: Backend<T>>(backend: Arc<B>) -> Result<OnlineClient<T>, Error> {
+        OnlineClient::from_backend_with_options(backend, MetadataOptions::default(), None).await
+    }
+
+    /// Construct a new [`OnlineClient`] from a [`Backend`], obtaining metadata as configured
+    /// and using the given runtime version if one is provided.
+    pub(super) async fn from_backend_with_options<B: Backend<T>>(
+        backend: Arc<B>,
+        metadata_options: MetadataOptions,
+        runtime_version: Option<RuntimeVersion>,
+    ) -> Result<OnlineClient<T>, Error> {
         let latest_block = backend.latest_finalized_block_ref().await?;
+        // A provided runtime version may not be the one that the metadata belongs to, and so
+        // we don't want to cache the metadata under it (or load metadata cached under it).
+        let use_cache = metadata_options.cache.is_some() && runtime_version.is_none();
+        let runtime_version = async {
+            match runtime_version {
+                Some(runtime_version) => Ok(runtime_version),
+                None => backend.current_runtime_version().await,
+            }
+        };
 
-        let (genesis_hash, runtime_version, metadata) = future::join3(
-            backend.genesis_hash(),
-            backend.current_runtime_version(),
-            OnlineClient::fetch_metadata(&*backend, latest_block.hash()),
-        )
-        .await;
+        let (genesis_hash, runtime_version, metadata) = if !use_cache {
+            let (genesis_hash, runtime_version, metadata) = future::join3(
+                backend.genesis_hash(),
+                runtime_version,
+                OnlineClient::fetch_metadata_uncached(
+                    &*backend,
+                    &metadata_options,
+                    latest_block.hash(),
+                ),
+            )
+            .await;
+            (genesis_hash?, runtime_version?, metadata?)
+        } else {
+            // We need to know the runtime version before we can look in the cache.
+            let (genesis_hash, runtime_version) =
+                future::join(backend.genesis_hash(), runtime_version).await;
+            let (genesis_hash, runtime_version) = (genesis_hash?, runtime_version?);
+            let metadata = OnlineClient::fetch_metadata_with_options(
+                &*backend,
+                &metadata_options,
+                &genesis_hash,
+                &runtime_version,
+                latest_block.hash(),
+            )
+            .await?;
+            (genesis_hash, runtime_version, metadata)
+        };
 
-        OnlineClient::from_backend_with(genesis_hash?, runtime_version?, metadata?, backend)
+        let mut client =
+            OnlineClient::from_backend_with(genesis_hash, runtime_version, metadata, backend)?;
+        client.metadata_options = metadata_options;
+        Ok(client)
     }
 
     /// Construct a new [`OnlineClient`] by providing all of the underlying details needed
@@ -153,9 +239,182 @@ impl<T: Config> OnlineClient<T> {
                 metadata: metadata.into(),
             })),
             backend,
+            historic_metadata: Default::default(),
+            metadata_options: MetadataOptions::default(),
         })
     }
 
+    /// Return a client which uses the runtime version and metadata that were in use in the
+    /// state of the given block, and shares the same backend as this client. This allows
+    /// working with blocks produced by runtimes other than the current one.
+    ///
+    /// The metadata for each runtime version is only fetched once; it's cached (by spec
+    /// version) and shared between this client, its clones and any clients returned from here.
+    /// If this client was configured with [`OnlineClientBuilder::metadata_cache()`], the
+    /// metadata is also cached on disk.
+    ///
+    /// Runtimes which predate V14 metadata can be used if the client was configured with
+    /// `OnlineClientBuilder::legacy_types()`, which requires the `legacy` feature.
+    pub async fn at_runtime_of(&self, block_hash: T::Hash) -> Result<OnlineClient<T>, Error> {
+        let runtime_version =
+            OnlineClient::fetch_runtime_version(&*self.backend, block_hash).await?;
+
+        let cached_metadata = self
+            .historic_metadata
+            .read()
+            .expect("shouldn't be poisoned")
+            .get(&runtime_version.spec_version)
+            .cloned();
+        let metadata = match cached_metadata {
+            Some(metadata) => metadata,
+            None => {
+                let metadata = OnlineClient::fetch_metadata_with_options(
+                    &*self.backend,
+                    &self.metadata_options,
+                    &self.genesis_hash(),
+                    &runtime_version,
+                    block_hash,
+                )
+                .await?;
+                self.historic_metadata
+                    .write()
+                    .expect("shouldn't be poisoned")
+                    .insert(runtime_version.spec_version, metadata.clone());
+                metadata
+            }
+        };
+
+        Ok(OnlineClient {
+            inner: Arc::new(RwLock::new(Inner {
+                genesis_hash: self.genesis_hash(),
+                runtime_version,
+                metadata,
+            })),
+            backend: self.backend.clone(),
+            historic_metadata: self.historic_metadata.clone(),
+            metadata_options: self.metadata_options.clone(),
+        })
+    }
+
+    /// Fetch the runtime version in the state of some block using the runtime API.
+    async fn fetch_runtime_version(
+        backend: &dyn Backend<T>,
+        block_hash: T::Hash,
+    ) -> Result<RuntimeVersion, Error> {
+        let (runtime_version, _) = OnlineClient::fetch_core_version(backend, block_hash).await?;
+        Ok(runtime_version)
+    }
+
+    /// Fetch the runtime version in the state of some block, along with the runtime APIs
+    /// (and their versions) that the runtime provides.
+    async fn fetch_core_version(
+        backend: &dyn Backend<T>,
+        block_hash: T::Hash,
+    ) -> Result<(RuntimeVersion, Vec<([u8; 8], u32)>), Error> {
+        let bytes = backend.call("Core_version", None, block_hash).await?;
+
+        // destructuring sp_version::RuntimeVersion; the fields we don't need are:
+        // spec_name: String, impl_name: String, authoring_version: u32, (spec_version)
+        // impl_version: u32, (apis), (transaction_version), ...
+        let cursor = &mut &*bytes;
+        let (_, _, _, spec_version, _, apis) =
+            <(String, String, u32, u32, u32, Vec<([u8; 8], u32)>)>::decode(cursor)?;
+        // Very old runtimes did not have a transaction version.
+        let transaction_version = if cursor.is_empty() {
+            0
+        } else {
+            u32::decode(cursor)?
+        };
+
+        let runtime_version = RuntimeVersion {
+            spec_version,
+            transaction_version,
+        };
+        Ok((runtime_version, apis))
+    }
+
+    /// Fetch the metadata for the given runtime version as configured in the [`MetadataOptions`].
+    /// If a cache is configured, the metadata is loaded from it if present, and otherwise fetched
+    /// at the given block and stored in it.
+    async fn fetch_metadata_with_options(
+        backend: &dyn Backend<T>,
+        metadata_options: &MetadataOptions,
+        genesis_hash: &T::Hash,
+        runtime_version: &RuntimeVersion,
+        block_hash: T::Hash,
+    ) -> Result<Metadata, Error> {
+        let Some(cache) = &metadata_options.cache else {
+            return OnlineClient::fetch_metadata_uncached(backend, metadata_options, block_hash)
+                .await;
+        };
+        if let Some(metadata) = cache.load(genesis_hash, runtime_version, metadata_options.version)
+        {
+            return Ok(metadata);
+        }
+
+        let metadata =
+            OnlineClient::fetch_metadata_uncached(backend, metadata_options, block_hash).await?;
+
+        // Metadata is cached in the V15 format, which can't describe pallets whose calls or
+        // events are encoded with a different index to the pallet, as in pre-V12 metadata.
+        let cacheable = metadata
+            .pallets()
+            .all(|p| p.call_index() == p.index() && p.event_index() == p.index());
+        if cacheable {
+            cache.store(
+                genesis_hash,
+                runtime_version,
+                metadata_options.version,
+                &metadata,
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Fetch the configured version of the metadata, or the latest version we support if none
+    /// is configured. If legacy types are configured and the runtime predates the versioned
+    /// metadata API, whatever metadata the runtime hands back is converted via
+    /// [`Metadata::from_legacy()`] instead.
+    ///
+    /// [`Metadata::from_legacy()`]: subxt_metadata::Metadata::from_legacy
+    async fn fetch_metadata_uncached(
+        backend: &dyn Backend<T>,
+        metadata_options: &MetadataOptions,
+        block_hash: T::Hash,
+    ) -> Result<Metadata, Error> {
+        #[cfg(feature = "legacy")]
+        if let Some(types) = &metadata_options.legacy_types {
+            // `Metadata_metadata_at_version` and `Metadata_metadata_versions` were added in
+            // version 2 of the `Metadata` runtime API. Runtimes which predate them may hand
+            // back pre-V14 metadata from `Metadata_metadata`.
+            let (_, apis) = OnlineClient::fetch_core_version(backend, block_hash).await?;
+            let metadata_api_id = sp_crypto_hashing::blake2_64(b"Metadata");
+            let has_versioned_metadata = apis
+                .iter()
+                .any(|(id, version)| *id == metadata_api_id && *version >= 2);
+
+            if !has_versioned_metadata {
+                let opaque: frame_metadata::OpaqueMetadata = backend
+                    .call_decoding("Metadata_metadata", None, block_hash)
+                    .await?;
+                let metadata = frame_metadata::RuntimeMetadataPrefixed::decode(&mut &opaque.0[..])?;
+                if metadata.1.version() < 14 {
+                    return Ok(subxt_metadata::Metadata::from_legacy(metadata, types)?.into());
+                }
+            }
+        }
+
+        match metadata_options.version {
+            None => OnlineClient::fetch_metadata(backend, block_hash).await,
+            Some(version) => match backend.metadata_at_version(version, block_hash).await {
+                Ok(metadata) => Ok(metadata),
+                // Nodes which predate the versioned metadata API can still hand back V14 metadata.
+                Err(_) if version == 14 => backend.legacy_metadata(block_hash).await,
+                Err(e) => Err(e),
+            },
+        }
+    }
+
     /// Fetch the metadata from substrate using the runtime API.
     async fn fetch_metadata(
         backend: &dyn Backend<T>,
@@ -376,6 +635,11 @@ impl<T: Config> OnlineClientT<T> for OnlineClient<T> {
     fn backend(&self) -> &dyn Backend<T> {
         &*self.backend
     }
+
+    fn at_runtime_of(&self, block_hash: T::Hash) -> AtRuntimeOfFut<Self> {
+        let client = self.clone();
+        Box::pin(async move { OnlineClient::at_runtime_of(&client, block_hash).await })
+    }
 }
 
 /// Client wrapper for performing runtime updates. See [`OnlineClient::updater()`]
@@ -460,7 +724,15 @@ impl<T: Config> RuntimeUpdaterStream<T> {
                 Err(err) => return Some(Err(err)),
             };
 
-        let metadata = match OnlineClient::fetch_metadata(self.client.backend(), at.hash()).await {
+        let metadata = match OnlineClient::fetch_metadata_with_options(
+            self.client.backend(),
+            &self.client.metadata_options,
+            &self.client.genesis_hash(),
+            &runtime_version,
+            at.hash(),
+        )
+        .await
+        {
             Ok(metadata) => metadata,
             Err(err) => return Some(Err(err)),
         };
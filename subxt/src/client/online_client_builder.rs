@@ -0,0 +1,424 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::metadata_cache::MetadataCache;
+use super::online_client::MetadataOptions;
+use super::{OnlineClient, RuntimeVersion};
+use crate::{
+    backend::{
+        legacy::LegacyBackend,
+        rpc::RpcClient,
+        unstable::{UnstableBackend, UnstableRpcMethods},
+        Backend,
+    },
+    error::Error,
+    Config,
+};
+use futures::StreamExt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Which [`Backend`] an [`OnlineClientBuilder`] uses to talk to a node.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Ask the node which RPC methods it supports, and use the [`UnstableBackend`] if it
+    /// supports the `chainHead_v1` methods, falling back to the [`LegacyBackend`] otherwise.
+    ///
+    /// The [`UnstableBackend`] is only chosen if there is a [`Spawner`] to drive it in the
+    /// background; see [`OnlineClientBuilder::spawner()`].
+    #[default]
+    Auto,
+    /// Always use the [`LegacyBackend`].
+    Legacy,
+    /// Always use the [`UnstableBackend`], driving it in the background. Building the client
+    /// fails if there is no [`Spawner`] to do so; see [`OnlineClientBuilder::spawner()`].
+    Unstable,
+}
+
+/// Runs the given future to completion in the background. See [`OnlineClientBuilder::spawner()`].
+pub type Spawner = Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync>;
+
+/// Configure and construct an [`OnlineClient`]. Construct one of these via
+/// [`OnlineClient::builder()`].
+pub struct OnlineClientBuilder<T: Config> {
+    backend_kind: BackendKind,
+    spawner: Option<Spawner>,
+    metadata_options: MetadataOptions,
+    runtime_version: Option<RuntimeVersion>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config> Default for OnlineClientBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> OnlineClientBuilder<T> {
+    /// Create a new [`OnlineClientBuilder`].
+    pub fn new() -> Self {
+        Self {
+            backend_kind: BackendKind::default(),
+            spawner: default_spawner(),
+            metadata_options: MetadataOptions::default(),
+            runtime_version: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Which [`Backend`] to use when building a client from a URL or an [`RpcClient`].
+    /// Defaults to [`BackendKind::Auto`]. This has no effect when a [`Backend`] is
+    /// provided via [`OnlineClientBuilder::build()`].
+    pub fn backend_kind(mut self, backend_kind: BackendKind) -> Self {
+        self.backend_kind = backend_kind;
+        self
+    }
+
+    /// Use the given function to drive the [`UnstableBackend`] in the background, if it's used.
+    /// For instance, when using tokio, pass `|fut| { tokio::spawn(fut); }`.
+    ///
+    /// With the `web` feature, futures are spawned via `wasm_bindgen_futures::spawn_local` by
+    /// default. Otherwise there is no default, and without a spawner the [`LegacyBackend`] is
+    /// used; see [`BackendKind`].
+    pub fn spawner(
+        mut self,
+        spawner: impl Fn(Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync + 'static,
+    ) -> Self {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    /// Cache metadata in the given directory. Metadata is stored by the genesis hash of the
+    /// chain, the spec and transaction versions of the runtime it belongs to, and the
+    /// [`OnlineClientBuilder::metadata_version()`] asked for. When the client is constructed,
+    /// or the runtime is updated via [`OnlineClient::updater()`], any metadata in the cache for
+    /// the current runtime version is used rather than downloading it again from the node.
+    /// The cache isn't used to construct the client if a
+    /// [`OnlineClientBuilder::runtime_version()`] is provided.
+    ///
+    /// The directory can be shared between many clients, including those in other processes.
+    /// Problems reading from or writing to it are logged and otherwise ignored.
+    pub fn metadata_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.metadata_options.cache = Some(MetadataCache::new(dir));
+        self
+    }
+
+    /// Fetch this version of the metadata from the node, rather than the latest version that
+    /// Subxt supports. This is also used when fetching metadata after a runtime update.
+    pub fn metadata_version(mut self, version: u32) -> Self {
+        self.metadata_options.version = Some(version);
+        self
+    }
+
+    /// Describe the types used by runtimes which predate V14 metadata. When metadata can't
+    /// otherwise be obtained from the node, for instance in [`OnlineClient::at_runtime_of()`],
+    /// any pre-V14 metadata that the node hands back is converted using these types.
+    #[cfg(feature = "legacy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "legacy")))]
+    pub fn legacy_types(mut self, types: crate::metadata::types::LegacyTypes) -> Self {
+        self.metadata_options.legacy_types = Some(Arc::new(types));
+        self
+    }
+
+    /// Use this runtime version when constructing the client, rather than fetching the
+    /// current runtime version from the node.
+    ///
+    /// # Warning
+    ///
+    /// Providing a runtime version which doesn't match the node may leave Subxt unable
+    /// to submit valid transactions.
+    pub fn runtime_version(mut self, runtime_version: RuntimeVersion) -> Self {
+        self.runtime_version = Some(runtime_version);
+        self
+    }
+
+    /// Construct an [`OnlineClient`], providing a URL to connect to.
+    #[cfg(feature = "jsonrpsee")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jsonrpsee")))]
+    pub async fn build_from_url(self, url: impl AsRef<str>) -> Result<OnlineClient<T>, Error> {
+        crate::utils::validate_url_is_secure(url.as_ref())?;
+        self.build_from_insecure_url(url).await
+    }
+
+    /// Construct an [`OnlineClient`], providing a URL to connect to.
+    ///
+    /// Allows insecure URLs without SSL encryption, e.g. (http:// and ws:// URLs).
+    #[cfg(feature = "jsonrpsee")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jsonrpsee")))]
+    pub async fn build_from_insecure_url(
+        self,
+        url: impl AsRef<str>,
+    ) -> Result<OnlineClient<T>, Error> {
+        let client = RpcClient::from_insecure_url(url).await?;
+        self.build_from_rpc_client(client).await
+    }
+
+    /// Construct an [`OnlineClient`] by providing an [`RpcClient`] to drive the connection.
+    /// The [`Backend`] used is chosen according to [`OnlineClientBuilder::backend_kind()`].
+    pub async fn build_from_rpc_client(
+        self,
+        rpc_client: impl Into<RpcClient>,
+    ) -> Result<OnlineClient<T>, Error> {
+        let rpc_client = rpc_client.into();
+        let spawner = self.spawner.clone();
+        if let Some(spawner) =
+            use_unstable_backend::<T>(self.backend_kind, spawner, &rpc_client).await?
+        {
+            let (backend, mut driver) = UnstableBackend::builder().build(rpc_client);
+            spawner(Box::pin(async move {
+                while let Some(res) = driver.next().await {
+                    if let Err(e) = res {
+                        tracing::debug!("Error driving UnstableBackend: {e}");
+                    }
+                }
+            }));
+            self.build(Arc::new(backend)).await
+        } else {
+            let backend = LegacyBackend::builder().build(rpc_client);
+            self.build(Arc::new(backend)).await
+        }
+    }
+
+    /// Construct an [`OnlineClient`] by providing an underlying [`Backend`]
+    /// implementation to power it. Other details will be obtained from the chain.
+    pub async fn build<B: Backend<T>>(self, backend: Arc<B>) -> Result<OnlineClient<T>, Error> {
+        OnlineClient::from_backend_with_options(
+            backend,
+            self.metadata_options,
+            self.runtime_version,
+        )
+        .await
+    }
+}
+
+/// Should the [`UnstableBackend`] be used to talk to the node, given the [`BackendKind`] asked for?
+/// If so, this hands back the [`Spawner`] to drive it with.
+async fn use_unstable_backend<T: Config>(
+    backend_kind: BackendKind,
+    spawner: Option<Spawner>,
+    rpc_client: &RpcClient,
+) -> Result<Option<Spawner>, Error> {
+    match (backend_kind, spawner) {
+        (BackendKind::Legacy, _) | (BackendKind::Auto, None) => Ok(None),
+        (BackendKind::Unstable, None) => Err(Error::Other(
+            "The UnstableBackend cannot be driven in the background; provide a spawner via \
+            OnlineClientBuilder::spawner()"
+                .into(),
+        )),
+        (BackendKind::Unstable, Some(spawner)) => Ok(Some(spawner)),
+        (BackendKind::Auto, Some(spawner)) => {
+            let supported = supports_chain_head::<T>(rpc_client).await;
+            Ok(supported.then_some(spawner))
+        }
+    }
+}
+
+/// Does the node support the `chainHead_v1` RPC methods? If we can't tell, assume not.
+async fn supports_chain_head<T: Config>(rpc_client: &RpcClient) -> bool {
+    match UnstableRpcMethods::<T>::new(rpc_client.clone())
+        .rpc_methods()
+        .await
+    {
+        Ok(methods) => methods.iter().any(|m| m == "chainHead_v1_follow"),
+        Err(e) => {
+            tracing::debug!("Failed to obtain the RPC methods supported by the node: {e}");
+            false
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+fn default_spawner() -> Option<Spawner> {
+    Some(Arc::new(|fut| wasm_bindgen_futures::spawn_local(fut)))
+}
+
+#[cfg(not(feature = "web"))]
+fn default_spawner() -> Option<Spawner> {
+    None
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::{use_unstable_backend, BackendKind, Spawner};
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::backend::rpc::{RawRpcFuture, RawRpcSubscription, RawValue, RpcClient, RpcClientT};
+    use crate::client::RuntimeVersion;
+    use crate::config::substrate::H256;
+    use crate::error::{Error, RpcError};
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Encode;
+    use futures::FutureExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Answers `rpc_methods` with the given methods, or an error if there are none,
+    /// and counts how many times it was asked.
+    struct MockRpcMethods {
+        methods: Option<Vec<&'static str>>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl MockRpcMethods {
+        fn client(methods: Option<Vec<&'static str>>) -> (RpcClient, Arc<AtomicUsize>) {
+            let requests = Arc::new(AtomicUsize::new(0));
+            let client = RpcClient::new(MockRpcMethods {
+                methods,
+                requests: requests.clone(),
+            });
+            (client, requests)
+        }
+    }
+
+    impl RpcClientT for MockRpcMethods {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RawRpcFuture<'a, Box<RawValue>> {
+            assert_eq!(method, "rpc_methods");
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let res = match &self.methods {
+                Some(methods) => {
+                    let res = serde_json::json!({ "methods": methods });
+                    Ok(RawValue::from_string(res.to_string()).unwrap())
+                }
+                None => Err(RpcError::RequestRejected("rpc_methods".into())),
+            };
+            async move { res }.boxed()
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RawRpcFuture<'a, RawRpcSubscription> {
+            unimplemented!("Not used in this test")
+        }
+    }
+
+    /// A spawner which never runs anything; these tests only check which backend is chosen.
+    fn spawner() -> Option<Spawner> {
+        Some(Arc::new(|_fut| {}))
+    }
+
+    async fn use_unstable(backend_kind: BackendKind, methods: Option<Vec<&'static str>>) -> bool {
+        let (client, _) = MockRpcMethods::client(methods);
+        use_unstable_backend::<SubstrateConfig>(backend_kind, spawner(), &client)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn auto_picks_backend_from_supported_methods() {
+        let chain_head = vec!["chain_getHeader", "chainHead_v1_follow"];
+        let legacy = vec!["chain_getHeader", "state_call"];
+
+        assert!(use_unstable(BackendKind::Auto, Some(chain_head)).await);
+        assert!(!use_unstable(BackendKind::Auto, Some(legacy)).await);
+        // If we can't tell what's supported, the legacy backend is used.
+        assert!(!use_unstable(BackendKind::Auto, None).await);
+    }
+
+    #[tokio::test]
+    async fn explicit_backend_kinds_dont_ask_the_node() {
+        for (backend_kind, expected) in
+            [(BackendKind::Unstable, true), (BackendKind::Legacy, false)]
+        {
+            let (client, requests) = MockRpcMethods::client(Some(vec!["chainHead_v1_follow"]));
+            let res =
+                use_unstable_backend::<SubstrateConfig>(backend_kind, spawner(), &client).await;
+            assert_eq!(res.unwrap().is_some(), expected);
+            assert_eq!(requests.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    #[test]
+    fn unstable_backend_needs_a_spawner() {
+        let choose = |backend_kind, spawner| {
+            let (client, requests) = MockRpcMethods::client(Some(vec!["chainHead_v1_follow"]));
+            let res = futures::executor::block_on(use_unstable_backend::<SubstrateConfig>(
+                backend_kind,
+                spawner,
+                &client,
+            ));
+            (res, requests.load(Ordering::SeqCst))
+        };
+
+        // Without a spawner, the UnstableBackend can't be driven in the background.
+        let (auto, requests) = choose(BackendKind::Auto, None);
+        assert!(auto.unwrap().is_none());
+        assert_eq!(requests, 0);
+        let (unstable, _) = choose(BackendKind::Unstable, None);
+        assert!(matches!(unstable, Err(Error::Other(_))));
+
+        // With one, the choice doesn't depend on whether we're running in some async runtime.
+        let (auto, requests) = choose(BackendKind::Auto, spawner());
+        assert!(auto.unwrap().is_some());
+        assert_eq!(requests, 1);
+    }
+
+    /// A backend which only hands back V15 metadata, when asked for that version explicitly.
+    fn backend_with_v15_metadata() -> MockBackend<SubstrateConfig> {
+        let metadata = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Some(frame_metadata::OpaqueMetadata(metadata)).encode();
+        MockBackend::builder()
+            .block(test_header(0, H256::zero()), vec![])
+            .runtime_api_call_with_params("Metadata_metadata_at_version", 15u32.encode(), metadata)
+            .runtime_version(RuntimeVersion {
+                spec_version: 1,
+                transaction_version: 1,
+            })
+            .build()
+    }
+
+    #[tokio::test]
+    async fn uses_provided_versions() {
+        let runtime_version = RuntimeVersion {
+            spec_version: 100,
+            transaction_version: 200,
+        };
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_version(15)
+            .runtime_version(runtime_version)
+            .build(Arc::new(backend_with_v15_metadata()))
+            .await
+            .unwrap();
+        assert_eq!(client.runtime_version(), runtime_version);
+
+        // Any other version of the metadata can't be found.
+        let res = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_version(14)
+            .build(Arc::new(backend_with_v15_metadata()))
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn provided_runtime_versions_are_not_cached() {
+        let dir = std::env::temp_dir().join(format!(
+            "subxt-provided-runtime-version-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        OnlineClient::<SubstrateConfig>::builder()
+            .metadata_cache(&dir)
+            .metadata_version(15)
+            .runtime_version(RuntimeVersion {
+                spec_version: 100,
+                transaction_version: 200,
+            })
+            .build(Arc::new(backend_with_v15_metadata()))
+            .await
+            .unwrap();
+
+        // The provided version may not match the metadata, so nothing was cached under it.
+        assert!(!dir.exists() || std::fs::read_dir(&dir).unwrap().next().is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -21,7 +21,9 @@ pub use dispatch_error::{
 pub use crate::metadata::Metadata;
 pub use scale_decode::Error as DecodeError;
 pub use scale_encode::Error as EncodeError;
-pub use subxt_core::error::{ExtrinsicParamsError, MetadataError, StorageAddressError};
+pub use subxt_core::error::{
+    ExtrinsicParamsError, MetadataError, StorageAddressError, StorageProofError,
+};
 pub use subxt_metadata::TryFromError as MetadataTryFromError;
 
 /// The underlying error enum, generic over the type held by the `Runtime`
@@ -69,6 +71,9 @@ pub enum Error {
     /// An error encoding a storage address.
     #[error("Error encoding storage address: {0}")]
     StorageAddress(#[from] StorageAddressError),
+    /// An error verifying a storage proof.
+    #[error("Error verifying storage proof: {0}")]
+    StorageProof(#[from] StorageProofError),
     /// The bytes representing an error that we were unable to decode.
     #[error("An error occurred but it could not be decoded: {0:?}")]
     Unknown(Vec<u8>),
@@ -92,6 +97,7 @@ impl From<CoreError> for Error {
             CoreError::Encode(e) => Error::Encode(e),
             CoreError::ExtrinsicParams(e) => Error::ExtrinsicParams(e),
             CoreError::Block(e) => Error::Block(e.into()),
+            CoreError::StorageProof(e) => Error::StorageProof(e),
         }
     }
 }
@@ -213,4 +219,10 @@ pub enum TransactionError {
     /// The transaction was dropped.
     #[error("The transaction was dropped: {0}")]
     Dropped(String),
+    /// The transaction was submitted the maximum number of times allowed without being finalized.
+    #[error("The transaction was not finalized after {0} submission attempts")]
+    MaxAttemptsReached(u32),
+    /// The transaction was not finalized within the maximum number of blocks allowed.
+    #[error("The transaction was not finalized within {0} blocks")]
+    BlockLimitReached(u64),
 }
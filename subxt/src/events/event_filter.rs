@@ -0,0 +1,93 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    events::{EventDetails, Phase, StaticEvent},
+    Config,
+};
+use derive_where::derive_where;
+
+/// A filter over events, for use with [`super::EventsClient::subscribe()`].
+///
+/// Each kind of criteria (event names, topics and phases) is optional, and an event
+/// must satisfy every kind that has been given in order to match. Where several criteria
+/// of the same kind are given, an event must match at least one of them. An empty filter
+/// matches every event.
+///
+/// Only the pallet and variant indexes, phase and topics of each event are inspected, so
+/// the fields of events which don't match are never decoded.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use subxt::events::{EventFilter, Phase};
+/// use subxt::PolkadotConfig;
+///
+/// // Any `Balances` event, or `System.ExtrinsicFailed`, emitted by the first extrinsic in a block:
+/// let filter = EventFilter::<PolkadotConfig>::new()
+///     .pallet("Balances")
+///     .event("System", "ExtrinsicFailed")
+///     .phase(Phase::ApplyExtrinsic(0));
+/// ```
+#[derive_where(Clone, Debug, Default)]
+pub struct EventFilter<T: Config> {
+    // Pallet names, each with an optional event name.
+    events: Vec<(String, Option<String>)>,
+    topics: Vec<T::Hash>,
+    phases: Vec<Phase>,
+}
+
+impl<T: Config> EventFilter<T> {
+    /// Create a new [`EventFilter`] which matches every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match any event emitted by the given pallet.
+    pub fn pallet(mut self, pallet_name: impl Into<String>) -> Self {
+        self.events.push((pallet_name.into(), None));
+        self
+    }
+
+    /// Match the event with the given pallet and variant name.
+    pub fn event(mut self, pallet_name: impl Into<String>, event_name: impl Into<String>) -> Self {
+        self.events
+            .push((pallet_name.into(), Some(event_name.into())));
+        self
+    }
+
+    /// Match the given statically generated event.
+    pub fn static_event<E: StaticEvent>(self) -> Self {
+        self.event(E::PALLET, E::EVENT)
+    }
+
+    /// Match events which have been given the topic provided.
+    pub fn topic(mut self, topic: T::Hash) -> Self {
+        self.topics.push(topic);
+        self
+    }
+
+    /// Match events which were emitted in the phase provided, for instance
+    /// [`Phase::ApplyExtrinsic`] with the index of some extrinsic in the block.
+    pub fn phase(mut self, phase: Phase) -> Self {
+        self.phases.push(phase);
+        self
+    }
+
+    /// Does the given event satisfy this filter?
+    pub fn matches(&self, event: &EventDetails<T>) -> bool {
+        let event_matches = self.events.is_empty()
+            || self.events.iter().any(|(pallet_name, event_name)| {
+                pallet_name == event.pallet_name()
+                    && event_name
+                        .as_ref()
+                        .map_or(true, |name| name == event.variant_name())
+            });
+        let topic_matches =
+            self.topics.is_empty() || event.topics().iter().any(|t| self.topics.contains(t));
+        let phase_matches = self.phases.is_empty() || self.phases.contains(&event.phase());
+
+        event_matches && topic_matches && phase_matches
+    }
+}
@@ -2,10 +2,17 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use crate::backend::{Backend, BackendExt, BlockRef};
+use crate::backend::{Backend, BackendExt, BlockRef, StreamOfResults};
+use crate::events::{EventDetails, EventFilter};
 use crate::{client::OnlineClientT, error::Error, events::Events, Config};
 use derive_where::derive_where;
+use futures::{stream, StreamExt};
 use std::future::Future;
+use std::sync::Arc;
+
+/// A stream of the events matching some [`EventFilter`], along with the block that each was
+/// found in. Obtain one of these via [`EventsClient::subscribe()`].
+pub type FilteredEventStream<T> = StreamOfResults<(BlockRef<<T as Config>::Hash>, EventDetails<T>)>;
 
 /// A client for working with events.
 #[derive_where(Clone; Client)]
@@ -48,6 +55,72 @@ where
         self.at_or_latest(None)
     }
 
+    /// Subscribe to the events in each new finalized block which match the given filter.
+    /// Events are handed back in the order that they occur, across block boundaries. Events
+    /// which don't match the filter are skipped over without their fields being decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use futures::StreamExt;
+    /// use subxt::events::EventFilter;
+    /// use subxt::{OnlineClient, PolkadotConfig};
+    ///
+    /// let api = OnlineClient::<PolkadotConfig>::new().await?;
+    ///
+    /// let filter = EventFilter::new().event("Balances", "Transfer");
+    /// let mut events = api.events().subscribe(filter).await?;
+    /// while let Some(ev) = events.next().await {
+    ///     let (block_ref, ev) = ev?;
+    ///     println!("{:?}: {:?}", block_ref.hash(), ev.field_values()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subscribe(
+        &self,
+        filter: EventFilter<T>,
+    ) -> impl Future<Output = Result<FilteredEventStream<T>, Error>> + Send + 'static
+    where
+        Client: Send + Sync + 'static,
+    {
+        let client = self.client.clone();
+        async move {
+            let headers = client.backend().stream_finalized_block_headers().await?;
+            let filter = Arc::new(filter);
+
+            let events = headers
+                .then(move |header_and_ref| {
+                    let client = client.clone();
+                    async move {
+                        let (_, block_ref) = header_and_ref?;
+                        let event_bytes =
+                            get_event_bytes(client.backend(), block_ref.hash()).await?;
+                        let events = Events::<T>::decode_from(event_bytes, client.metadata());
+                        Ok::<_, Error>((block_ref, events))
+                    }
+                })
+                .flat_map(move |block_events| {
+                    let matching: Vec<_> = match block_events {
+                        Err(e) => vec![Err(e)],
+                        Ok((block_ref, events)) => events
+                            .iter()
+                            .filter_map(|ev| match ev {
+                                Ok(ev) if filter.matches(&ev) => Some(Ok((block_ref.clone(), ev))),
+                                Ok(_) => None,
+                                Err(e) => Some(Err(e.into())),
+                            })
+                            .collect(),
+                    };
+                    stream::iter(matching)
+                });
+
+            Ok(StreamOfResults::new(Box::pin(events)))
+        }
+    }
+
     /// Obtain events at some block hash.
     fn at_or_latest(
         &self,
@@ -89,3 +162,141 @@ pub(crate) async fn get_event_bytes<T: Config>(
         .await?
         .unwrap_or_default())
 }
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::H256;
+    use crate::events::{Phase, StaticEvent};
+    use crate::metadata::Metadata;
+    use crate::utils::AccountId32;
+    use crate::{config::Header, OnlineClient, SubstrateConfig};
+    use codec::{Compact, Decode, Encode};
+
+    #[derive(scale_decode::DecodeAsType)]
+    struct NewAccount {
+        #[allow(dead_code)]
+        account: AccountId32,
+    }
+    impl StaticEvent for NewAccount {
+        const PALLET: &'static str = "System";
+        const EVENT: &'static str = "NewAccount";
+    }
+
+    // Encode some `System` events, each of which has a single `AccountId32` field.
+    fn system_events(metadata: &Metadata, events: &[(Phase, &str, Vec<H256>)]) -> Vec<u8> {
+        let system = metadata.pallet_by_name("System").unwrap();
+        let mut bytes = Compact(events.len() as u32).encode();
+        for (phase, name, topics) in events {
+            let variant = system
+                .event_variants()
+                .unwrap()
+                .iter()
+                .find(|v| &v.name == name)
+                .unwrap();
+            phase.encode_to(&mut bytes);
+            (system.index(), variant.index, [1u8; 32], topics).encode_to(&mut bytes);
+        }
+        bytes
+    }
+
+    #[tokio::test]
+    async fn subscribes_to_matching_events() {
+        let metadata_bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*metadata_bytes).unwrap();
+        let hash0 = test_header(0, H256::zero()).hash();
+        let block1 = test_header(1, hash0);
+        let hash1 = block1.hash();
+        let topic = H256::repeat_byte(7);
+
+        let events_key = system_events_key().to_vec();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .block(block1, vec![])
+            .storage_entry(
+                hash0,
+                events_key.clone(),
+                system_events(
+                    &metadata,
+                    &[
+                        (Phase::ApplyExtrinsic(0), "NewAccount", vec![]),
+                        (Phase::ApplyExtrinsic(1), "KilledAccount", vec![topic]),
+                    ],
+                ),
+            )
+            .storage_entry(
+                hash1,
+                events_key,
+                system_events(
+                    &metadata,
+                    &[
+                        (Phase::ApplyExtrinsic(1), "NewAccount", vec![topic]),
+                        (Phase::Finalization, "KilledAccount", vec![]),
+                    ],
+                ),
+            )
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let matches = |filter: EventFilter<SubstrateConfig>| {
+            let api = api.clone();
+            async move {
+                let events: Vec<_> = api
+                    .events()
+                    .subscribe(filter)
+                    .await
+                    .unwrap()
+                    .collect()
+                    .await;
+                events
+                    .into_iter()
+                    .map(|ev| {
+                        let (block_ref, ev) = ev.unwrap();
+                        (block_ref.hash(), ev.variant_name().to_owned())
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+        let new_account = |hash| (hash, "NewAccount".to_owned());
+        let killed_account = |hash| (hash, "KilledAccount".to_owned());
+
+        assert_eq!(matches(EventFilter::new()).await.len(), 4);
+        assert_eq!(
+            matches(EventFilter::new().static_event::<NewAccount>()).await,
+            vec![new_account(hash0), new_account(hash1)]
+        );
+        assert_eq!(
+            matches(EventFilter::new().topic(topic)).await,
+            vec![killed_account(hash0), new_account(hash1)]
+        );
+        assert_eq!(
+            matches(
+                EventFilter::new()
+                    .pallet("System")
+                    .phase(Phase::ApplyExtrinsic(1))
+                    .phase(Phase::Finalization)
+            )
+            .await,
+            vec![
+                killed_account(hash0),
+                new_account(hash1),
+                killed_account(hash1)
+            ]
+        );
+        assert_eq!(
+            matches(
+                EventFilter::new()
+                    .event("System", "KilledAccount")
+                    .topic(topic)
+            )
+            .await,
+            vec![killed_account(hash0)]
+        );
+        assert!(matches(EventFilter::new().pallet("Balances"))
+            .await
+            .is_empty());
+    }
+}
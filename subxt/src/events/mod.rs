@@ -9,8 +9,11 @@ use crate::client::OnlineClientT;
 use crate::Error;
 use subxt_core::{Config, Metadata};
 
+mod event_filter;
 mod events_client;
-pub use events_client::EventsClient;
+
+pub use event_filter::EventFilter;
+pub use events_client::{EventsClient, FilteredEventStream};
 pub use subxt_core::events::{EventDetails, Events, Phase, StaticEvent};
 
 /// Creates a new [`Events`] instance by fetching the corresponding bytes at `block_hash` from the client.
@@ -24,6 +24,12 @@ macro_rules! cfg_unstable_light_client {
 	};
 }
 
+macro_rules! cfg_mock_backend {
+	($($item:item)*) => {
+		crate::macros::cfg_feature!("mock-backend", $($item)*);
+	};
+}
+
 macro_rules! cfg_jsonrpsee {
 	($($item:item)*) => {
 		crate::macros::cfg_feature!("jsonrpsee", $($item)*);
@@ -64,8 +70,8 @@ macro_rules! cfg_reconnecting_rpc_client {
 }
 
 pub(crate) use {
-    cfg_feature, cfg_jsonrpsee, cfg_reconnecting_rpc_client, cfg_substrate_compat,
-    cfg_unstable_light_client,
+    cfg_feature, cfg_jsonrpsee, cfg_mock_backend, cfg_reconnecting_rpc_client,
+    cfg_substrate_compat, cfg_unstable_light_client,
 };
 
 // Only used by light-client.
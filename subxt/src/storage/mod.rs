@@ -4,10 +4,16 @@
 
 //! Types associated with accessing and working with storage items.
 
+mod parallel_iter;
 mod storage_client;
+mod storage_diff;
+mod storage_snapshot;
 mod storage_type;
 
+pub use parallel_iter::{ParallelStorageIter, StorageIterCursor, StorageIterProgress};
 pub use storage_client::StorageClient;
+pub use storage_diff::StorageDiff;
+pub use storage_snapshot::StorageSnapshot;
 pub use storage_type::{Storage, StorageKeyValuePair};
 pub use subxt_core::storage::address::{
     dynamic, Address, DefaultAddress, DynamicAddress, StaticAddress, StaticStorageKey, StorageKey,
@@ -0,0 +1,453 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::storage_type::{decode_storage_key_value_pair, StorageKeyValuePair};
+use crate::{
+    backend::StorageResponse, client::OnlineClientT, error::Error, metadata::Metadata, Config,
+};
+use codec::{Decode, Encode};
+use futures::stream::{self, BoxStream, SelectAll};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use subxt_core::storage::address::{Address, StorageHashers};
+
+/// The key space under an address is split into this many ranges, one for each possible
+/// value of the first nibble after the address bytes, which are then iterated concurrently.
+const RANGE_COUNT: usize = 16;
+
+/// How many of the possible values of the first byte after the address bytes each range covers.
+const BYTES_PER_RANGE: usize = 256 / RANGE_COUNT;
+
+/// Records how far a [`ParallelStorageIter`] has got, so that iteration can be resumed later
+/// from the same point via [`super::Storage::iter_parallel()`]. Obtain one of these from
+/// [`ParallelStorageIter::cursor()`]. It can be stored via its SCALE or serde encoding.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct StorageIterCursor {
+    prefix: Vec<u8>,
+    ranges: Vec<RangeCursor>,
+    keys_scanned: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+struct RangeCursor {
+    last_key: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl StorageIterCursor {
+    fn new(prefix: Vec<u8>) -> Self {
+        let range = RangeCursor {
+            last_key: None,
+            done: false,
+        };
+        Self {
+            prefix,
+            ranges: vec![range; RANGE_COUNT],
+            keys_scanned: 0,
+        }
+    }
+
+    /// Has every key been iterated over?
+    pub fn is_done(&self) -> bool {
+        self.ranges.iter().all(|range| range.done)
+    }
+
+    /// The progress that this cursor represents.
+    pub fn progress(&self) -> StorageIterProgress {
+        StorageIterProgress {
+            keys_scanned: self.keys_scanned,
+            ranges_done: self.ranges.iter().filter(|range| range.done).count(),
+            ranges_total: self.ranges.len(),
+        }
+    }
+}
+
+/// The progress of a [`ParallelStorageIter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StorageIterProgress {
+    /// How many keys have been handed back so far, including any handed back
+    /// before iteration was resumed.
+    pub keys_scanned: u64,
+    /// How many of the ranges that the key space is split into have been fully iterated over.
+    pub ranges_done: usize,
+    /// How many ranges the key space is split into.
+    pub ranges_total: usize,
+}
+
+/// An item handed back by one of the ranges being iterated over: either a value, or `None`
+/// once the range is exhausted.
+type RangeItem = (usize, Option<Result<StorageResponse, Error>>);
+
+/// A stream of the key value pairs under some storage address, fetched concurrently from
+/// a number of ranges of the key space. Obtain one of these via
+/// [`super::Storage::iter_parallel()`].
+///
+/// Entries are not handed back in key order. At any point, [`ParallelStorageIter::cursor()`]
+/// can be called to obtain a [`StorageIterCursor`] which accounts for every entry handed back
+/// so far, and which can be used to resume iteration later.
+pub struct ParallelStorageIter<Addr: Address> {
+    ranges: SelectAll<BoxStream<'static, RangeItem>>,
+    cursor: StorageIterCursor,
+    // Ranges which have handed back an error, and so can't be trusted to be complete.
+    failed_ranges: [bool; RANGE_COUNT],
+    return_type_id: u32,
+    hashers: StorageHashers,
+    metadata: Metadata,
+    _marker: PhantomData<fn() -> Addr>,
+}
+
+impl<Addr: Address> ParallelStorageIter<Addr> {
+    pub(crate) async fn new<T: Config, Client: OnlineClientT<T>>(
+        client: Client,
+        prefix: Vec<u8>,
+        cursor: Option<StorageIterCursor>,
+        at: T::Hash,
+        return_type_id: u32,
+        hashers: StorageHashers,
+        metadata: Metadata,
+    ) -> Result<Self, Error> {
+        let cursor = match cursor {
+            Some(cursor) if cursor.prefix != prefix || cursor.ranges.len() != RANGE_COUNT => {
+                return Err(Error::Other(
+                    "The storage iteration cursor given does not belong to this address".into(),
+                ))
+            }
+            Some(cursor) => cursor,
+            None => StorageIterCursor::new(prefix.clone()),
+        };
+
+        // Each range covers keys after the start of this range up to and including the start
+        // of the next one. The first range begins at the prefix itself, and the last is only
+        // bounded by the prefix.
+        let range_start = |idx: usize| [&*prefix, &[(idx as u8) << 4]].concat();
+        let pending: Vec<_> = cursor
+            .ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| !range.done)
+            .map(|(idx, range)| {
+                let end = (idx + 1 < RANGE_COUNT).then(|| range_start(idx + 1));
+                let start = match (&range.last_key, idx) {
+                    (Some(last_key), _) => Some(last_key.clone()),
+                    (None, 0) => None,
+                    (None, _) => Some(range_start(idx)),
+                };
+                (idx, start, end)
+            })
+            .collect();
+
+        // Ranges can only be fetched from a start key if the backend supports it.
+        let backend = client.backend();
+        let mut streams = Vec::new();
+        let mut can_start_from_key = true;
+        for (idx, start, end) in &pending {
+            let Some(start) = start else { continue };
+            let values = backend
+                .storage_fetch_descendant_values_after(prefix.clone(), start.clone(), at)
+                .await?;
+            match values {
+                Some(values) => streams.push((*idx, values, end.clone())),
+                None => {
+                    can_start_from_key = false;
+                    break;
+                }
+            }
+        }
+
+        let mut ranges = SelectAll::new();
+        if !can_start_from_key {
+            // Split each range up by the first byte after the prefix instead.
+            for (idx, _, _) in &pending {
+                let last_key = cursor.ranges[*idx].last_key.clone();
+                ranges.push(byte_prefix_range(
+                    client.clone(),
+                    prefix.clone(),
+                    *idx,
+                    last_key,
+                    at,
+                ));
+            }
+        } else {
+            for (idx, start, end) in &pending {
+                if start.is_none() {
+                    let values = backend
+                        .storage_fetch_descendant_values(prefix.clone(), at)
+                        .await?;
+                    streams.push((*idx, values, end.clone()));
+                }
+            }
+            for (idx, values, end) in streams {
+                let values = values
+                    .take_while(move |res| {
+                        let in_range = match (res, &end) {
+                            (Ok(res), Some(end)) => res.key <= *end,
+                            _ => true,
+                        };
+                        futures::future::ready(in_range)
+                    })
+                    .map(move |res| (idx, Some(res)))
+                    .chain(stream::once(async move { (idx, None) }));
+                ranges.push(values.boxed());
+            }
+        }
+
+        Ok(Self {
+            ranges,
+            cursor,
+            failed_ranges: [false; RANGE_COUNT],
+            return_type_id,
+            hashers,
+            metadata,
+            _marker: PhantomData,
+        })
+    }
+
+    /// A cursor which accounts for every entry handed back so far. Pass this to
+    /// [`super::Storage::iter_parallel()`] to resume iteration from this point.
+    pub fn cursor(&self) -> StorageIterCursor {
+        self.cursor.clone()
+    }
+
+    /// How far through iterating we are.
+    pub fn progress(&self) -> StorageIterProgress {
+        self.cursor.progress()
+    }
+}
+
+/// Hand back the entries in a range for backends which can't begin fetching from a start key.
+/// Such a range covers every key whose first byte after the prefix begins with the nibble of
+/// the range, and these are fetched one byte prefix at a time, skipping over any which have
+/// already been handed back (up to and including `last_key`).
+///
+/// This means that a key exactly one byte longer than the prefix can belong to a different
+/// range here than when the backend can begin from a start key, and so a cursor should be
+/// resumed using the same backend.
+fn byte_prefix_range<T: Config, Client: OnlineClientT<T>>(
+    client: Client,
+    prefix: Vec<u8>,
+    idx: usize,
+    last_key: Option<Vec<u8>>,
+    at: T::Hash,
+) -> BoxStream<'static, RangeItem> {
+    let first_byte = idx * BYTES_PER_RANGE;
+    let resume_byte = last_key
+        .as_ref()
+        .and_then(|k| k.get(prefix.len()))
+        .map_or(first_byte, |&b| b as usize);
+    let byte_prefixes = (resume_byte.max(first_byte)..first_byte + BYTES_PER_RANGE)
+        .map(move |byte| [&*prefix, &[byte as u8]].concat());
+
+    stream::iter(byte_prefixes)
+        .then(move |key| {
+            let client = client.clone();
+            async move {
+                client
+                    .backend()
+                    .storage_fetch_descendant_values(key, at)
+                    .await
+            }
+        })
+        .flat_map(|values| match values {
+            Ok(values) => values.left_stream(),
+            Err(e) => stream::once(async { Err(e) }).right_stream(),
+        })
+        .filter(move |res| {
+            let seen = match (res, &last_key) {
+                (Ok(res), Some(last_key)) => res.key <= *last_key,
+                _ => false,
+            };
+            futures::future::ready(!seen)
+        })
+        // Anything after an error might leave a gap in the range, so stop there.
+        .scan(false, |failed, res| {
+            if *failed {
+                return futures::future::ready(None);
+            }
+            *failed = res.is_err();
+            futures::future::ready(Some(res))
+        })
+        .map(move |res| (idx, Some(res)))
+        .chain(stream::once(async move { (idx, None) }))
+        .boxed()
+}
+
+impl<Addr> Stream for ParallelStorageIter<Addr>
+where
+    Addr: Address,
+    Addr::Keys: 'static + Sized,
+{
+    type Item = Result<StorageKeyValuePair<Addr>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let Some((idx, item)) = futures::ready!(this.ranges.poll_next_unpin(cx)) else {
+                return Poll::Ready(None);
+            };
+
+            let kv = match item {
+                None => {
+                    this.cursor.ranges[idx].done = !this.failed_ranges[idx];
+                    continue;
+                }
+                Some(Err(e)) => {
+                    this.failed_ranges[idx] = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Some(Ok(kv)) => kv,
+            };
+
+            let res = decode_storage_key_value_pair::<Addr>(
+                kv.key.clone(),
+                &kv.value,
+                this.return_type_id,
+                &this.hashers,
+                &this.metadata,
+            );
+            this.cursor.ranges[idx].last_key = Some(kv.key);
+            this.cursor.keys_scanned += 1;
+            return Poll::Ready(Some(res));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::backend::unstable::test_utils::{ev_initialized, hash, test_backend, MockChainHead};
+    use crate::client::RuntimeVersion;
+    use crate::config::substrate::H256;
+    use crate::storage::Storage;
+    use crate::{config::Header, OnlineClient, SubstrateConfig};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::sync::Arc;
+
+    fn account_prefix() -> Vec<u8> {
+        let mut prefix = sp_crypto_hashing::twox_128(b"System").to_vec();
+        prefix.extend(sp_crypto_hashing::twox_128(b"Account"));
+        prefix
+    }
+
+    // Keys are spread across the key space by the Blake2_128Concat hasher.
+    fn account_entries(count: u8) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..count)
+            .map(|n| {
+                let account_id = [n; 32];
+                let mut key = account_prefix();
+                key.extend(sp_crypto_hashing::blake2_128(&account_id));
+                key.extend(account_id);
+                let value = (0u32, 0u32, 0u32, 0u32, (n as u128, 0u128, 0u128, 0u128));
+                (key, value.encode())
+            })
+            .collect()
+    }
+
+    async fn client_with_accounts(count: u8) -> (OnlineClient<SubstrateConfig>, BTreeSet<Vec<u8>>) {
+        let genesis_hash = test_header(0, H256::zero()).hash();
+        let entries = account_entries(count);
+        let keys = entries.iter().map(|(k, _)| k.clone()).collect();
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .storage_entries(genesis_hash, entries)
+            // Something outside of the address being iterated over:
+            .storage_entry(genesis_hash, vec![0xff; 64], vec![1])
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+        (client, keys)
+    }
+
+    // Stop iterating over the 50 accounts part way through, and then carry on from the cursor.
+    async fn iterate_and_resume<Client: OnlineClientT<SubstrateConfig>>(
+        storage: &Storage<SubstrateConfig, Client>,
+    ) -> Vec<Vec<u8>> {
+        let address = || crate::storage::dynamic("System", "Account", ());
+
+        let mut iter = storage.iter_parallel(address(), None).await.unwrap();
+        let mut keys = Vec::new();
+        for _ in 0..20 {
+            keys.push(iter.next().await.unwrap().unwrap().key_bytes);
+        }
+        let cursor = iter.cursor();
+        assert_eq!(cursor.progress().keys_scanned, 20);
+        assert!(!cursor.is_done());
+        drop(iter);
+
+        let mut iter = storage
+            .iter_parallel(address(), Some(cursor))
+            .await
+            .unwrap();
+        while let Some(kv) = iter.next().await {
+            keys.push(kv.unwrap().key_bytes);
+        }
+
+        let progress = iter.progress();
+        assert_eq!(progress.keys_scanned, 50);
+        assert_eq!(progress.ranges_done, progress.ranges_total);
+        assert!(iter.cursor().is_done());
+        keys
+    }
+
+    #[tokio::test]
+    async fn iterates_and_resumes() {
+        let (client, expected_keys) = client_with_accounts(50).await;
+        let storage = client.storage().at_latest().await.unwrap();
+
+        // Every key is seen exactly once.
+        let keys = iterate_and_resume(&storage).await;
+        assert_eq!(keys.len(), 50);
+        assert_eq!(keys.into_iter().collect::<BTreeSet<_>>(), expected_keys);
+    }
+
+    #[tokio::test]
+    async fn iterates_and_resumes_by_byte_prefix() {
+        // The unstable backend can't begin fetching from a start key.
+        let mut entries: BTreeMap<_, _> = account_entries(50).into_iter().collect();
+        let expected_keys: BTreeSet<_> = entries.keys().cloned().collect();
+        entries.insert(vec![0xff; 64], vec![1]);
+        let mock = MockChainHead::new([(0, entries)]);
+        let backend = test_backend(mock.clone());
+        mock.send(ev_initialized(0));
+
+        let metadata = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let metadata = crate::Metadata::decode(&mut &*metadata).unwrap();
+        let runtime_version = RuntimeVersion {
+            spec_version: 0,
+            transaction_version: 0,
+        };
+        let client =
+            OnlineClient::from_backend_with(hash(0), runtime_version, metadata, Arc::new(backend))
+                .unwrap();
+        let storage = client.storage().at(hash(0));
+
+        let keys = iterate_and_resume(&storage).await;
+        assert_eq!(keys.len(), 50);
+        assert_eq!(keys.into_iter().collect::<BTreeSet<_>>(), expected_keys);
+
+        // Each query asked for the values under one byte prefix of the address.
+        let queries = mock.storage_queries.lock().unwrap();
+        assert!(queries.iter().all(|(_, query_type, key)| {
+            query_type == "descendantsValues" && key.len() == account_prefix().len() + 1
+        }));
+    }
+
+    #[tokio::test]
+    async fn rejects_cursor_for_other_address() {
+        let (client, _) = client_with_accounts(1).await;
+        let storage = client.storage().at_latest().await.unwrap();
+        let cursor = StorageIterCursor::new(vec![1, 2, 3]);
+
+        let res = storage
+            .iter_parallel(
+                crate::storage::dynamic("System", "Account", ()),
+                Some(cursor),
+            )
+            .await;
+        assert!(res.is_err());
+    }
+}
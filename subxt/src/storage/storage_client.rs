@@ -4,14 +4,16 @@
 
 use super::storage_type::Storage;
 use crate::{
-    backend::BlockRef,
+    backend::{BlockRef, StreamOfResults},
     client::{OfflineClientT, OnlineClientT},
     error::Error,
     Config,
 };
 use derive_where::derive_where;
+use futures::StreamExt;
 use std::{future::Future, marker::PhantomData};
 use subxt_core::storage::address::Address;
+use subxt_core::utils::Yes;
 
 /// Query the runtime storage.
 #[derive_where(Clone; Client)]
@@ -83,4 +85,140 @@ where
             Ok(Storage::new(client, block_ref))
         }
     }
+
+    /// Watch the value at some storage address. This hands back the value when the stream
+    /// starts, and then each time that it changes, alongside a reference to the block in
+    /// which the new value was seen. `None` is handed back when there is no value at the
+    /// address.
+    ///
+    /// New values are looked for in each new best block, and so a block in which a value is
+    /// seen may not go on to be finalized.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use subxt::{ PolkadotConfig, OnlineClient };
+    ///
+    /// #[subxt::subxt(runtime_metadata_path = "../artifacts/polkadot_metadata_full.scale")]
+    /// pub mod polkadot {}
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+    ///
+    /// // Address to a storage entry we'd like to watch.
+    /// let address = polkadot::storage().timestamp().now();
+    ///
+    /// let mut values = api.storage().watch(address).await.unwrap();
+    /// while let Some(Ok((block_ref, value))) = values.next().await {
+    ///     println!("Value in block {:?}: {:?}", block_ref.hash(), value);
+    /// }
+    /// # }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn watch<Addr>(
+        &self,
+        address: Addr,
+    ) -> impl Future<
+        Output = Result<StreamOfResults<(BlockRef<T::Hash>, Option<Addr::Target>)>, Error>,
+    > + 'static
+    where
+        Addr: Address<IsFetchable = Yes> + Send + 'static,
+    {
+        let client = self.client.clone();
+        async move {
+            let metadata = client.metadata();
+
+            // Metadata validation checks whether the static address given
+            // is likely to actually correspond to a real storage entry or not.
+            // if not, it means static codegen doesn't line up with runtime
+            // metadata.
+            subxt_core::storage::validate(&address, &metadata)?;
+
+            let key = subxt_core::storage::get_address_bytes(&address, &metadata)?;
+            let values = client
+                .backend()
+                .stream_storage_value(key)
+                .await?
+                .map(move |res| {
+                    let (block_ref, value) = res?;
+                    // Use the latest metadata in case the runtime has been updated.
+                    let metadata = client.metadata();
+                    let value = value
+                        .map(|v| subxt_core::storage::decode_value(&mut &*v, &address, &metadata))
+                        .transpose()?;
+                    Ok((block_ref, value))
+                });
+
+            Ok(StreamOfResults::new(Box::pin(values)))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::H256;
+    use crate::{config::Header, OnlineClient, SubstrateConfig};
+    use codec::Encode;
+    use futures::StreamExt;
+    use std::sync::Arc;
+    use subxt_core::storage::address::StaticAddress;
+    use subxt_core::utils::Yes;
+
+    // An address like the codegen would give for `Timestamp::Now`.
+    fn now_address() -> StaticAddress<(), u64, Yes, Yes, ()> {
+        StaticAddress::new_static("Timestamp", "Now", (), [0; 32]).unvalidated()
+    }
+
+    fn now_key() -> Vec<u8> {
+        let mut key = sp_crypto_hashing::twox_128(b"Timestamp").to_vec();
+        key.extend(sp_crypto_hashing::twox_128(b"Now"));
+        key
+    }
+
+    #[tokio::test]
+    async fn watch_decodes_values() {
+        let genesis = test_header(0, H256::zero());
+        let block_1 = test_header(1, genesis.hash());
+        let block_2 = test_header(2, block_1.hash());
+        let block_3 = test_header(3, block_2.hash());
+        let hashes = [block_1.hash(), block_2.hash(), block_3.hash()];
+
+        // The value is set, changed, and then is something that isn't a valid u64.
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .block(block_1, vec![])
+            .block(block_2, vec![])
+            .block(block_3, vec![])
+            .storage_entry(hashes[0], now_key(), 1234u64.encode())
+            .storage_entry(hashes[1], now_key(), 5678u64.encode())
+            .storage_entry(hashes[2], now_key(), vec![1, 2, 3])
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+
+        let mut values = client.storage().watch(now_address()).await.unwrap();
+
+        let decoded: Vec<_> = values
+            .by_ref()
+            .take(3)
+            .map(|res| {
+                let (block_ref, value) = res.unwrap();
+                (block_ref.hash(), value)
+            })
+            .collect()
+            .await;
+        assert_eq!(
+            decoded,
+            vec![
+                (genesis.hash(), None),
+                (hashes[0], Some(1234)),
+                (hashes[1], Some(5678))
+            ]
+        );
+
+        // A value that can't be decoded into the storage type is an error.
+        assert!(values.next().await.unwrap().is_err());
+        assert!(values.next().await.is_none());
+    }
 }
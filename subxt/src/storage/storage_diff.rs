@@ -0,0 +1,334 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::StorageKeyValuePair;
+use crate::{
+    backend::{Backend, StorageResponse, StreamOfResults},
+    client::OnlineClientT,
+    error::Error,
+    Config,
+};
+use derive_where::derive_where;
+use futures::{future, stream, Stream, StreamExt, TryStreamExt};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use subxt_core::storage::address::Address;
+
+/// How many bytes below the address being diffed we'll look for identical subtrees to skip.
+const MAX_PRUNE_DEPTH: usize = 2;
+/// The most closest descendant merkle values that we'll ask for at once when pruning.
+const MAX_PRUNE_QUERIES: usize = 4096;
+
+/// A difference between the storage at two blocks. See [`super::Storage::diff()`].
+#[derive_where(Clone, Debug, Eq, PartialEq; StorageKeyValuePair<T>)]
+pub enum StorageDiff<T: Address> {
+    /// An entry which exists in the other block but not in this one.
+    Added(StorageKeyValuePair<T>),
+    /// An entry which exists in this block but not in the other one.
+    Removed(StorageKeyValuePair<T>),
+    /// An entry which exists in both blocks, but with a different value.
+    Modified {
+        /// The entry in this block.
+        old: StorageKeyValuePair<T>,
+        /// The entry in the other block.
+        new: StorageKeyValuePair<T>,
+    },
+}
+
+/// Find the keys underneath `prefix` whose values differ between blocks `a` and `b`,
+/// handing them back in key order as they're found.
+///
+/// If the backend can provide closest descendant merkle values, these are compared at and
+/// just below the prefix in order to avoid looking at any subtrees which are identical in
+/// both blocks. The hashes of every value in each remaining subtree are then streamed from
+/// both blocks and merged, one subtree at a time.
+pub(crate) async fn changed_keys<T: Config, Client: OnlineClientT<T>>(
+    client: Client,
+    prefix: Vec<u8>,
+    a: T::Hash,
+    b: T::Hash,
+) -> Result<impl Stream<Item = Result<Vec<u8>, Error>> + Send + 'static, Error> {
+    let backend = client.backend();
+
+    // Subtrees which differ between the blocks, in key order:
+    let mut frontier = vec![prefix];
+    // Keys that we've descended beyond, whose own values need comparing:
+    let mut exact_keys = Vec::new();
+
+    for depth in 0..=MAX_PRUNE_DEPTH {
+        let (merkle_a, merkle_b) = future::try_join(
+            merkle_values(backend, frontier.clone(), a),
+            merkle_values(backend, frontier.clone(), b),
+        )
+        .await?;
+        let (Some(merkle_a), Some(merkle_b)) = (merkle_a, merkle_b) else {
+            break;
+        };
+
+        frontier.retain(|key| merkle_a.get(key) != merkle_b.get(key));
+        if depth == MAX_PRUNE_DEPTH || frontier.len() * 256 > MAX_PRUNE_QUERIES {
+            break;
+        }
+
+        exact_keys.extend(frontier.iter().cloned());
+        frontier = frontier
+            .into_iter()
+            .flat_map(|key| {
+                (0..=u8::MAX).map(move |byte| {
+                    let mut child = key.clone();
+                    child.push(byte);
+                    child
+                })
+            })
+            .collect();
+    }
+
+    // There are only a few exact keys, and so we compare their values up front.
+    let mut exact_changes = Vec::new();
+    if !exact_keys.is_empty() {
+        let (values_a, values_b) = future::try_join(
+            fetch_values(backend, exact_keys.clone(), a),
+            fetch_values(backend, exact_keys.clone(), b),
+        )
+        .await?;
+        exact_changes.extend(
+            exact_keys
+                .into_iter()
+                .filter(|key| values_a.get(key) != values_b.get(key)),
+        );
+        exact_changes.sort();
+    }
+
+    // The subtrees don't overlap, and so comparing each in turn hands back keys in order.
+    let subtree_changes = stream::iter(frontier)
+        .then(move |key| {
+            let client = client.clone();
+            async move {
+                let (hashes_a, hashes_b) = future::try_join(
+                    client
+                        .backend()
+                        .storage_fetch_descendant_hashes(key.clone(), a),
+                    client.backend().storage_fetch_descendant_hashes(key, b),
+                )
+                .await?;
+                Ok::<_, Error>(merge_changed(hashes_a, hashes_b))
+            }
+        })
+        .try_flatten();
+
+    // Exact keys are ancestors of the subtrees, so slot them in before the first key after them.
+    let mut exact_changes = exact_changes.into_iter().peekable();
+    let changes = subtree_changes
+        .map(Some)
+        .chain(stream::once(future::ready(None)))
+        .flat_map(move |next| {
+            let mut changes = Vec::new();
+            match &next {
+                Some(Ok(key)) => {
+                    while let Some(exact) = exact_changes.next_if(|exact| exact < key) {
+                        changes.push(Ok(exact));
+                    }
+                }
+                Some(Err(_)) => {}
+                None => changes.extend(exact_changes.by_ref().map(Ok)),
+            }
+            changes.extend(next);
+            stream::iter(changes)
+        });
+
+    Ok(changes)
+}
+
+/// Merge two streams of key and hash pairs, each in key order, handing back (in key order)
+/// the keys which are only in one of them or whose hashes differ.
+fn merge_changed(
+    a: StreamOfResults<StorageResponse>,
+    b: StreamOfResults<StorageResponse>,
+) -> impl Stream<Item = Result<Vec<u8>, Error>> + Send + 'static {
+    let state = (in_key_order(a), in_key_order(b), None, None);
+    stream::try_unfold(state, |(mut a, mut b, mut next_a, mut next_b)| async move {
+        loop {
+            if next_a.is_none() {
+                next_a = a.try_next().await?;
+            }
+            if next_b.is_none() {
+                next_b = b.try_next().await?;
+            }
+
+            let order = match (&next_a, &next_b) {
+                (None, None) => return Ok(None),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(a), Some(b)) => a.key.cmp(&b.key),
+            };
+            let changed = match order {
+                Ordering::Less => next_a.take().map(|res| res.key),
+                Ordering::Greater => next_b.take().map(|res| res.key),
+                Ordering::Equal => {
+                    let (res_a, res_b) = (next_a.take(), next_b.take());
+                    res_a.zip(res_b).and_then(|(res_a, res_b)| {
+                        (res_a.value != res_b.value).then_some(res_a.key)
+                    })
+                }
+            };
+
+            if let Some(key) = changed {
+                return Ok(Some((key, (a, b, next_a, next_b))));
+            }
+        }
+    })
+}
+
+/// Hand back an error rather than a wrong diff if a backend doesn't hand keys back in order.
+fn in_key_order(
+    responses: StreamOfResults<StorageResponse>,
+) -> impl Stream<Item = Result<StorageResponse, Error>> + Send + Unpin + 'static {
+    let mut last_key: Option<Vec<u8>> = None;
+    responses.fuse().map(move |res| {
+        let res = res?;
+        if last_key
+            .as_ref()
+            .is_some_and(|last_key| *last_key >= res.key)
+        {
+            return Err(Error::Other(
+                "Storage hashes were not handed back in key order".into(),
+            ));
+        }
+        last_key = Some(res.key.clone());
+        Ok(res)
+    })
+}
+
+/// Fetch the values at the given keys, omitting any which don't exist.
+pub(crate) async fn fetch_values<T: Config>(
+    backend: &dyn Backend<T>,
+    keys: Vec<Vec<u8>>,
+    at: T::Hash,
+) -> Result<HashMap<Vec<u8>, Vec<u8>>, Error> {
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+    backend
+        .storage_fetch_values(keys, at)
+        .await?
+        .map_ok(|res| (res.key, res.value))
+        .try_collect()
+        .await
+}
+
+async fn merkle_values<T: Config>(
+    backend: &dyn Backend<T>,
+    keys: Vec<Vec<u8>>,
+    at: T::Hash,
+) -> Result<Option<HashMap<Vec<u8>, Vec<u8>>>, Error> {
+    let Some(values) = backend
+        .storage_fetch_closest_descendant_merkle_values(keys, at)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let values = values
+        .map_ok(|res| (res.key, res.value))
+        .try_collect()
+        .await?;
+    Ok(Some(values))
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::client::{OnlineClient, RuntimeVersion};
+    use crate::config::substrate::H256;
+    use crate::{Metadata, SubstrateConfig};
+    use codec::Decode;
+    use futures::stream::BoxStream;
+    use std::sync::Arc;
+
+    fn responses(items: &[(&[u8], u8)]) -> StreamOfResults<StorageResponse> {
+        let items: Vec<_> = items
+            .iter()
+            .map(|(key, value)| {
+                Ok(StorageResponse {
+                    key: key.to_vec(),
+                    value: vec![*value],
+                })
+            })
+            .collect();
+        let stream: BoxStream<'static, _> = stream::iter(items).boxed();
+        StreamOfResults::new(stream)
+    }
+
+    #[tokio::test]
+    async fn merges_hashes_in_key_order() {
+        let a = responses(&[(b"a", 1), (b"b", 1), (b"d", 1)]);
+        let b = responses(&[(b"b", 2), (b"c", 1), (b"d", 1)]);
+        let changed: Vec<_> = merge_changed(a, b).try_collect().await.unwrap();
+        assert_eq!(changed, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+        // Keys out of order would produce a wrong diff, and so are an error.
+        let a = responses(&[(b"b", 1), (b"a", 1)]);
+        let res: Result<Vec<_>, _> = merge_changed(a, responses(&[])).try_collect().await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn finds_changed_keys() {
+        let a = H256::repeat_byte(1);
+        let b = H256::repeat_byte(2);
+        let unchanged = (0..100u8).map(|n| (vec![b'p', b'x', n, n], vec![n]));
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .storage_entries(a, unchanged.clone())
+            .storage_entries(
+                a,
+                [
+                    (b"p".to_vec(), b"root".to_vec()),
+                    (b"pa".to_vec(), b"removed".to_vec()),
+                    (b"pbcd".to_vec(), b"old".to_vec()),
+                    (b"q".to_vec(), b"outside".to_vec()),
+                ],
+            )
+            .storage_entries(b, unchanged)
+            .storage_entries(
+                b,
+                [
+                    (b"p".to_vec(), b"root".to_vec()),
+                    (b"pbcd".to_vec(), b"new".to_vec()),
+                    (b"pbce".to_vec(), b"added".to_vec()),
+                    (b"q".to_vec(), b"outside changed".to_vec()),
+                ],
+            )
+            .build();
+
+        let metadata = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let client = OnlineClient::from_backend_with(
+            H256::zero(),
+            RuntimeVersion {
+                spec_version: 1,
+                transaction_version: 1,
+            },
+            Metadata::decode(&mut &*metadata).unwrap(),
+            Arc::new(backend),
+        )
+        .unwrap();
+        let changed_keys = |prefix: &[u8]| {
+            let client = client.clone();
+            let prefix = prefix.to_vec();
+            async move {
+                let changed = changed_keys(client, prefix, a, b).await?;
+                changed.try_collect::<Vec<_>>().await
+            }
+        };
+
+        let changed = changed_keys(b"p").await.unwrap();
+        assert_eq!(
+            changed,
+            vec![b"pa".to_vec(), b"pbcd".to_vec(), b"pbce".to_vec()]
+        );
+
+        let unchanged = changed_keys(b"px").await.unwrap();
+        assert!(unchanged.is_empty());
+    }
+}
@@ -0,0 +1,445 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::Storage;
+use crate::{
+    backend::{
+        in_memory::{clone_header, headers_stream, InMemoryStorage},
+        sealed::Sealed,
+        Backend, BlockRef, StorageResponse, StreamOfResults, TransactionStatus,
+    },
+    client::{OnlineClient, RuntimeVersion},
+    config::Header,
+    error::{BlockError, Error},
+    metadata::Metadata,
+    Config,
+};
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use derive_where::derive_where;
+use futures::stream;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The bytes that every encoded snapshot begins with.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"subxtsnp";
+/// The version of the snapshot format, which follows the magic bytes.
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// The raw storage entries under some set of pallets at a single block, alongside the block
+/// header and the metadata needed to make sense of them. Create one of these with
+/// [`Storage::snapshot()`], and then save it with [`StorageSnapshot::write_to_file()`] and load it
+/// again later with [`StorageSnapshot::read_from_file()`].
+///
+/// [`StorageSnapshot::storage()`] gives back a [`Storage`] view over the snapshot, so that
+/// entries can be fetched, decoded and iterated over just as they would be from a node, but
+/// without needing to talk to one.
+#[derive_where(Clone)]
+pub struct StorageSnapshot<T: Config> {
+    inner: Arc<SnapshotInner<T>>,
+}
+
+struct SnapshotInner<T: Config> {
+    genesis_hash: T::Hash,
+    runtime_version: RuntimeVersion,
+    header: T::Header,
+    metadata: Metadata,
+    entries: InMemoryStorage,
+}
+
+/// How a snapshot is encoded, after the magic bytes and version.
+#[derive(Encode, Decode)]
+struct EncodedSnapshot<Hash, Header> {
+    genesis_hash: Hash,
+    spec_version: u32,
+    transaction_version: u32,
+    header: Header,
+    metadata: Vec<u8>,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<T: Config> std::fmt::Debug for StorageSnapshot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageSnapshot")
+            .field("block_hash", &self.block_hash())
+            .field("entries", &self.inner.entries.len())
+            .finish()
+    }
+}
+
+impl<T: Config> StorageSnapshot<T> {
+    pub(crate) fn new(
+        genesis_hash: T::Hash,
+        runtime_version: RuntimeVersion,
+        header: T::Header,
+        metadata: Metadata,
+        entries: InMemoryStorage,
+    ) -> Self {
+        Self {
+            inner: Arc::new(SnapshotInner {
+                genesis_hash,
+                runtime_version,
+                header,
+                metadata,
+                entries,
+            }),
+        }
+    }
+
+    /// The hash of the block that this snapshot was taken at.
+    pub fn block_hash(&self) -> T::Hash {
+        self.inner.header.hash()
+    }
+
+    /// The header of the block that this snapshot was taken at.
+    pub fn header(&self) -> &T::Header {
+        &self.inner.header
+    }
+
+    /// The genesis hash of the chain that this snapshot was taken from.
+    pub fn genesis_hash(&self) -> T::Hash {
+        self.inner.genesis_hash
+    }
+
+    /// The runtime version in use when this snapshot was taken.
+    pub fn runtime_version(&self) -> RuntimeVersion {
+        self.inner.runtime_version
+    }
+
+    /// The metadata used to make sense of the entries in this snapshot.
+    pub fn metadata(&self) -> Metadata {
+        self.inner.metadata.clone()
+    }
+
+    /// The raw keys and values in this snapshot, in key order.
+    pub fn entries(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.inner
+            .entries
+            .iter()
+            .map(|(key, value)| (&**key, &**value))
+    }
+
+    /// A [`Storage`] view over the entries in this snapshot, at the block that it was taken at.
+    /// Anything which isn't in the snapshot will appear to be absent from storage.
+    pub fn storage(&self) -> Result<Storage<T, OnlineClient<T>>, Error> {
+        let client = OnlineClient::from_backend_with(
+            self.inner.genesis_hash,
+            self.inner.runtime_version,
+            self.inner.metadata.clone(),
+            Arc::new(SnapshotBackend {
+                inner: self.inner.clone(),
+            }),
+        )?;
+        Ok(Storage::new(client, BlockRef::from_hash(self.block_hash())))
+    }
+
+    /// Encode this snapshot to bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let snapshot = EncodedSnapshot {
+            genesis_hash: self.inner.genesis_hash,
+            spec_version: self.inner.runtime_version.spec_version,
+            transaction_version: self.inner.runtime_version.transaction_version,
+            header: &self.inner.header,
+            metadata: (*self.inner.metadata).encode(),
+            entries: self
+                .entries()
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .collect(),
+        };
+
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.push(SNAPSHOT_VERSION);
+        snapshot.encode_to(&mut bytes);
+        bytes
+    }
+
+    /// Decode a snapshot from bytes produced by [`StorageSnapshot::to_bytes()`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let Some(bytes) = bytes.strip_prefix(SNAPSHOT_MAGIC) else {
+            return Err(Error::Other("Not a storage snapshot".into()));
+        };
+        let Some((&version, mut bytes)) = bytes.split_first() else {
+            return Err(Error::Other("Not a storage snapshot".into()));
+        };
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::Other(format!(
+                "Unsupported storage snapshot version {version}; expected version {SNAPSHOT_VERSION}"
+            )));
+        }
+
+        let snapshot = EncodedSnapshot::<T::Hash, T::Header>::decode(&mut bytes)?;
+        let metadata = Metadata::decode(&mut &*snapshot.metadata)?;
+
+        Ok(Self::new(
+            snapshot.genesis_hash,
+            RuntimeVersion {
+                spec_version: snapshot.spec_version,
+                transaction_version: snapshot.transaction_version,
+            },
+            snapshot.header,
+            metadata,
+            snapshot.entries.into_iter().collect(),
+        ))
+    }
+
+    /// Write this snapshot to a file, replacing the file if it already exists.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Read a snapshot from a file written by [`StorageSnapshot::write_to_file()`].
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Serves storage from a snapshot, and little else.
+struct SnapshotBackend<T: Config> {
+    inner: Arc<SnapshotInner<T>>,
+}
+
+impl<T: Config> SnapshotBackend<T> {
+    fn block_hash(&self) -> T::Hash {
+        self.inner.header.hash()
+    }
+
+    fn entries_at(&self, at: T::Hash) -> Result<&InMemoryStorage, Error> {
+        if at != self.block_hash() {
+            return Err(BlockError::not_found(at).into());
+        }
+        Ok(&self.inner.entries)
+    }
+
+    fn headers_stream(&self) -> StreamOfResults<(T::Header, BlockRef<T::Hash>)> {
+        headers_stream::<T>([&self.inner.header])
+    }
+}
+
+fn not_available(what: &str) -> Error {
+    Error::Other(format!("Cannot {what} using a storage snapshot"))
+}
+
+impl<T: Config> Sealed for SnapshotBackend<T> {}
+
+#[async_trait]
+impl<T: Config + Send + Sync + 'static> Backend<T> for SnapshotBackend<T> {
+    async fn storage_fetch_values(
+        &self,
+        keys: Vec<Vec<u8>>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.entries_at(at)?.values(keys))
+    }
+
+    async fn storage_fetch_descendant_keys(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<Vec<u8>>, Error> {
+        Ok(self.entries_at(at)?.descendant_keys(key))
+    }
+
+    async fn storage_fetch_descendant_values(
+        &self,
+        key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<StreamOfResults<StorageResponse>, Error> {
+        Ok(self.entries_at(at)?.descendant_values(key))
+    }
+
+    async fn storage_fetch_descendant_values_after(
+        &self,
+        key: Vec<u8>,
+        start_key: Vec<u8>,
+        at: T::Hash,
+    ) -> Result<Option<StreamOfResults<StorageResponse>>, Error> {
+        let values = self.entries_at(at)?.descendant_values_after(key, start_key);
+        Ok(Some(values))
+    }
+
+    async fn genesis_hash(&self) -> Result<T::Hash, Error> {
+        Ok(self.inner.genesis_hash)
+    }
+
+    async fn block_header(&self, at: T::Hash) -> Result<Option<T::Header>, Error> {
+        Ok((at == self.block_hash()).then(|| clone_header::<T>(&self.inner.header)))
+    }
+
+    async fn block_body(&self, _at: T::Hash) -> Result<Option<Vec<Vec<u8>>>, Error> {
+        Err(not_available("fetch block bodies"))
+    }
+
+    async fn latest_finalized_block_ref(&self) -> Result<BlockRef<T::Hash>, Error> {
+        Ok(BlockRef::from_hash(self.block_hash()))
+    }
+
+    async fn current_runtime_version(&self) -> Result<RuntimeVersion, Error> {
+        Ok(self.inner.runtime_version)
+    }
+
+    async fn stream_runtime_version(&self) -> Result<StreamOfResults<RuntimeVersion>, Error> {
+        Ok(StreamOfResults::new(Box::pin(stream::iter([Ok(self
+            .inner
+            .runtime_version)]))))
+    }
+
+    async fn stream_all_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn stream_best_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn stream_finalized_block_headers(
+        &self,
+    ) -> Result<StreamOfResults<(T::Header, BlockRef<T::Hash>)>, Error> {
+        Ok(self.headers_stream())
+    }
+
+    async fn submit_transaction(
+        &self,
+        _bytes: &[u8],
+    ) -> Result<StreamOfResults<TransactionStatus<T::Hash>>, Error> {
+        Err(not_available("submit transactions"))
+    }
+
+    async fn call(
+        &self,
+        _method: &str,
+        _call_parameters: Option<&[u8]>,
+        _at: T::Hash,
+    ) -> Result<Vec<u8>, Error> {
+        Err(not_available("call runtime APIs"))
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::H256;
+    use crate::SubstrateConfig;
+    use futures::TryStreamExt;
+
+    fn key(pallet: &str, entry: &str) -> Vec<u8> {
+        let mut key = sp_crypto_hashing::twox_128(pallet.as_bytes()).to_vec();
+        key.extend(sp_crypto_hashing::twox_128(entry.as_bytes()));
+        key
+    }
+
+    fn core_version(spec_version: u32) -> Vec<u8> {
+        let apis: Vec<([u8; 8], u32)> = Vec::new();
+        ("test", "test", 1u32, spec_version, 1u32, apis, 0u32).encode()
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trip() {
+        let genesis_hash = test_header(0, H256::zero()).hash();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .storage_entries(
+                genesis_hash,
+                [
+                    (key("Timestamp", "Now"), 1234u64.encode()),
+                    (key("Timestamp", "DidUpdate"), true.encode()),
+                    (key("System", "Number"), 0u32.encode()),
+                ],
+            )
+            .runtime_api_call("Core_version", core_version(0))
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+
+        let snapshot = client
+            .storage()
+            .at(genesis_hash)
+            .snapshot(["Timestamp"])
+            .await
+            .unwrap();
+        let snapshot =
+            StorageSnapshot::<SubstrateConfig>::from_bytes(&snapshot.to_bytes()).unwrap();
+
+        assert_eq!(snapshot.block_hash(), genesis_hash);
+        assert_eq!(snapshot.genesis_hash(), client.genesis_hash());
+        assert_eq!(snapshot.runtime_version(), client.runtime_version());
+
+        // Only entries under the pallets asked for are in the snapshot:
+        let storage = snapshot.storage().unwrap();
+        let keys: Vec<_> = storage
+            .fetch_raw_keys(Vec::new())
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(storage
+            .fetch_raw(key("System", "Number"))
+            .await
+            .unwrap()
+            .is_none());
+
+        // And they can be decoded as usual:
+        let now = storage
+            .fetch(&crate::storage::dynamic("Timestamp", "Now", ()))
+            .await
+            .unwrap()
+            .unwrap()
+            .to_value()
+            .unwrap();
+        assert_eq!(now.as_u128(), Some(1234));
+    }
+
+    #[tokio::test]
+    async fn snapshot_uses_the_runtime_of_its_block() {
+        // The runtime was upgraded in block 1, and only the old metadata knows about `Timestamp`.
+        let genesis = test_header(0, H256::zero());
+        let block_1 = test_header(1, genesis.hash());
+        let (genesis_hash, block_1_hash) = (genesis.hash(), block_1.hash());
+
+        let tiny = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let small = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(tiny)
+            .metadata_at(genesis_hash, small)
+            .runtime_version(RuntimeVersion {
+                spec_version: 2,
+                transaction_version: 0,
+            })
+            .block(genesis, vec![])
+            .block(block_1, vec![])
+            .runtime_api_call_at(genesis_hash, "Core_version", core_version(1))
+            .runtime_api_call_at(block_1_hash, "Core_version", core_version(2))
+            .storage_entries(genesis_hash, [(key("Timestamp", "Now"), 1234u64.encode())])
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+        assert!(client.metadata().pallet_by_name("Timestamp").is_none());
+
+        let snapshot = client
+            .storage()
+            .at(genesis_hash)
+            .snapshot(["Timestamp"])
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.runtime_version().spec_version, 1);
+        assert!(snapshot.metadata().pallet_by_name("Timestamp").is_some());
+        let now = snapshot
+            .storage()
+            .unwrap()
+            .fetch(&crate::storage::dynamic("Timestamp", "Now", ()))
+            .await
+            .unwrap()
+            .unwrap()
+            .as_type::<u64>()
+            .unwrap();
+        assert_eq!(now, 1234);
+    }
+}
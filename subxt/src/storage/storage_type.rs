@@ -2,20 +2,31 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
+use super::parallel_iter::{ParallelStorageIter, StorageIterCursor};
+use super::storage_diff::{self, StorageDiff};
+use super::storage_snapshot::StorageSnapshot;
 use crate::{
-    backend::{BackendExt, BlockRef},
+    backend::{in_memory::InMemoryStorage, BackendExt, BlockRef, StorageResponse},
     client::OnlineClientT,
-    error::{Error, MetadataError, StorageAddressError},
-    metadata::DecodeWithMetadata,
+    config::Header,
+    error::{BlockError, Error, MetadataError, StorageAddressError, StorageProofError},
+    metadata::{DecodeWithMetadata, Metadata},
     Config,
 };
 use codec::Decode;
 use derive_where::derive_where;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use std::{future::Future, marker::PhantomData};
 use subxt_core::storage::address::{Address, StorageHashers, StorageKey};
+use subxt_core::storage::StorageProof;
 use subxt_core::utils::Yes;
 
+/// How many keys to ask for a storage proof of at once when iterating over verified values.
+const VERIFIED_ITER_BATCH_SIZE: usize = 64;
+
+/// How many changed values to fetch at once when diffing storage between blocks.
+const DIFF_BATCH_SIZE: usize = 64;
+
 /// This is returned from a couple of storage functions.
 pub use crate::backend::StreamOfResults;
 
@@ -62,6 +73,31 @@ where
         }
     }
 
+    /// Fetch the raw encoded value at the key given, verifying it against the state root of
+    /// this block using a storage proof obtained from the backend. Use this when the backend
+    /// is not trusted to return correct values.
+    pub fn fetch_raw_verified(
+        &self,
+        key: impl Into<Vec<u8>>,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Error>> + 'static {
+        let client = self.client.clone();
+        let key = key.into();
+        // Keep this alive until the call is complete:
+        let block_ref = self.block_ref.clone();
+        // Manual future so lifetime not tied to api.storage().
+        async move {
+            let block_hash = block_ref.hash();
+            let state_root = verified_state_root(&client, block_hash).await?;
+            let proof = client
+                .backend()
+                .storage_fetch_read_proof(vec![key.clone()], block_hash)
+                .await?;
+            let proof = StorageProof::new::<T::Hasher>(state_root, proof);
+            let value = proof.verify_value(&key)?;
+            Ok(value.map(|v| v.to_vec()))
+        }
+    }
+
     /// Stream all of the raw keys underneath the key given
     pub fn fetch_raw_keys(
         &self,
@@ -80,6 +116,95 @@ where
         }
     }
 
+    /// Fetch the raw encoded value at the key given in a child trie. `child_key` is the key
+    /// of a child trie in the default namespace, without the `:child_storage:default:` prefix.
+    pub fn fetch_child_raw(
+        &self,
+        child_key: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Error>> + 'static {
+        let client = self.client.clone();
+        let child_key = child_key.into();
+        let key = key.into();
+        // Keep this alive until the call is complete:
+        let block_ref = self.block_ref.clone();
+        // Manual future so lifetime not tied to api.storage().
+        async move {
+            let data = client
+                .backend()
+                .storage_fetch_child_value(child_key, key, block_ref.hash())
+                .await?;
+            Ok(data)
+        }
+    }
+
+    /// Stream all of the raw keys underneath the key given in a child trie. See
+    /// [`Storage::fetch_child_raw()`].
+    pub fn fetch_child_raw_keys(
+        &self,
+        child_key: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> impl Future<Output = Result<StreamOfResults<Vec<u8>>, Error>> + 'static {
+        let client = self.client.clone();
+        let block_hash = self.block_ref.hash();
+        let child_key = child_key.into();
+        let key = key.into();
+        // Manual future so lifetime not tied to api.storage().
+        async move {
+            let keys = client
+                .backend()
+                .storage_fetch_child_descendant_keys(child_key, key, block_hash)
+                .await?;
+            Ok(keys)
+        }
+    }
+
+    /// Stream all of the raw keys and values underneath the key given in a child trie.
+    /// Pass an empty key to iterate over the entire child trie. See [`Storage::fetch_child_raw()`].
+    ///
+    /// ```no_run
+    /// use subxt::{ PolkadotConfig, OnlineClient };
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+    ///
+    /// // The (unprefixed) key of some child trie that we'd like to iterate over.
+    /// let child_key = hex::decode("6372...").unwrap();
+    ///
+    /// let mut iter = api
+    ///     .storage()
+    ///     .at_latest()
+    ///     .await
+    ///     .unwrap()
+    ///     .iter_child_raw(child_key, Vec::new())
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(Ok(kv)) = iter.next().await {
+    ///     println!("0x{}: 0x{}", hex::encode(&kv.key), hex::encode(&kv.value));
+    /// }
+    /// # }
+    /// ```
+    pub fn iter_child_raw(
+        &self,
+        child_key: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> impl Future<Output = Result<StreamOfResults<StorageResponse>, Error>> + 'static {
+        let client = self.client.clone();
+        let block_hash = self.block_ref.hash();
+        let child_key = child_key.into();
+        let key = key.into();
+        // Manual future so lifetime not tied to api.storage().
+        async move {
+            let values = client
+                .backend()
+                .storage_fetch_child_descendant_values(child_key, key, block_hash)
+                .await?;
+            Ok(values)
+        }
+    }
+
     /// Fetch a decoded value from storage at a given address.
     ///
     /// # Example
@@ -138,6 +263,40 @@ where
         }
     }
 
+    /// Fetch a decoded value from storage at a given address, verifying it against the state
+    /// root of this block. This works like [`Storage::fetch()`], but the value is obtained from a
+    /// storage proof rather than trusted as is from the backend.
+    ///
+    /// Only backends which support fetching storage proofs (currently the
+    /// [`crate::backend::legacy::LegacyBackend`]) can be used to fetch verified values.
+    pub fn fetch_verified<'address, Addr>(
+        &self,
+        address: &'address Addr,
+    ) -> impl Future<Output = Result<Option<Addr::Target>, Error>> + 'address
+    where
+        Addr: Address<IsFetchable = Yes> + 'address,
+    {
+        let client = self.clone();
+        async move {
+            let metadata = client.client.metadata();
+
+            // Metadata validation checks whether the static address given
+            // is likely to actually correspond to a real storage entry or not.
+            // if not, it means static codegen doesn't line up with runtime
+            // metadata.
+            subxt_core::storage::validate(address, &metadata)?;
+
+            // Look up the return type ID to enable DecodeWithMetadata:
+            let lookup_bytes = subxt_core::storage::get_address_bytes(address, &metadata)?;
+            if let Some(data) = client.fetch_raw_verified(lookup_bytes).await? {
+                let val = subxt_core::storage::decode_value(&mut &*data, address, &metadata)?;
+                Ok(Some(val))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
     /// Fetch a StorageKey that has a default value with an optional block hash.
     pub fn fetch_or_default<'address, Addr>(
         &self,
@@ -233,27 +392,339 @@ where
                         Ok(kv) => kv,
                         Err(e) => return Err(e),
                     };
-                    let value = Addr::Target::decode_with_metadata(
-                        &mut &*kv.value,
+                    decode_storage_key_value_pair::<Addr>(
+                        kv.key,
+                        &kv.value,
+                        return_type_id,
+                        &hashers,
+                        &metadata,
+                    )
+                });
+
+            let s = StreamOfResults::new(Box::pin(s));
+            Ok(s)
+        }
+    }
+
+    /// Returns an iterator of key value pairs, where each value is verified against the state
+    /// root of this block using storage proofs obtained from the backend. This works like
+    /// [`Storage::iter()`], but values are obtained from storage proofs rather than trusted as is
+    /// from the backend.
+    ///
+    /// Note that while every value handed back is proven to exist in the state of this block,
+    /// the proofs do not guarantee that the backend has handed back every key under the address.
+    pub fn iter_verified<Addr>(
+        &self,
+        address: Addr,
+    ) -> impl Future<Output = Result<StreamOfResults<StorageKeyValuePair<Addr>>, Error>> + 'static
+    where
+        Addr: Address<IsIterable = Yes> + 'static,
+        Addr::Keys: 'static + Sized,
+    {
+        let client = self.client.clone();
+        let block_ref = self.block_ref.clone();
+        async move {
+            let metadata = client.metadata();
+            let (_pallet, entry) = subxt_core::storage::lookup_storage_entry_details(
+                address.pallet_name(),
+                address.entry_name(),
+                &metadata,
+            )?;
+
+            // Metadata validation checks whether the static address given
+            // is likely to actually correspond to a real storage entry or not.
+            // if not, it means static codegen doesn't line up with runtime
+            // metadata.
+            subxt_core::storage::validate(&address, &metadata)?;
+
+            let entry = entry.entry_type();
+            let return_type_id = entry.value_ty();
+            let hashers = StorageHashers::new(entry, metadata.types())?;
+
+            let block_hash = block_ref.hash();
+            let state_root = verified_state_root(&client, block_hash).await?;
+
+            // The address bytes of this entry:
+            let address_bytes = subxt_core::storage::get_address_bytes(&address, &metadata)?;
+            let s = client
+                .backend()
+                .storage_fetch_descendant_keys(address_bytes, block_hash)
+                .await?
+                .chunks(VERIFIED_ITER_BATCH_SIZE)
+                .then(move |keys| {
+                    let client = client.clone();
+                    // Keep this alive until the proofs have been fetched:
+                    let block_ref = block_ref.clone();
+                    async move {
+                        let keys = keys.into_iter().collect::<Result<Vec<_>, Error>>()?;
+                        let proof = client
+                            .backend()
+                            .storage_fetch_read_proof(keys.clone(), block_ref.hash())
+                            .await?;
+                        let proof = StorageProof::new::<T::Hasher>(state_root, proof);
+
+                        // Keys that the proof shows to have no value are skipped over.
+                        let mut pairs = Vec::with_capacity(keys.len());
+                        for key in keys {
+                            if let Some(value) = proof.verify_value(&key)? {
+                                let value = value.to_vec();
+                                pairs.push(Ok((key, value)));
+                            }
+                        }
+                        Ok::<_, Error>(pairs)
+                    }
+                })
+                .flat_map(|pairs| match pairs {
+                    Ok(pairs) => futures::stream::iter(pairs).left_stream(),
+                    Err(e) => futures::stream::once(async { Err(e) }).right_stream(),
+                })
+                .map(move |kv: Result<(Vec<u8>, Vec<u8>), Error>| {
+                    let (key, value) = kv?;
+                    decode_storage_key_value_pair::<Addr>(
+                        key,
+                        &value,
                         return_type_id,
+                        &hashers,
                         &metadata,
-                    )?;
-
-                    let key_bytes = kv.key;
-                    let cursor = &mut &key_bytes[..];
-                    strip_storage_address_root_bytes(cursor)?;
-
-                    let keys = <Addr::Keys as StorageKey>::decode_storage_key(
-                        cursor,
-                        &mut hashers.iter(),
-                        metadata.types(),
-                    )?;
-
-                    Ok(StorageKeyValuePair::<Addr> {
-                        keys,
-                        key_bytes,
-                        value,
-                    })
+                    )
+                });
+
+            let s = StreamOfResults::new(Box::pin(s));
+            Ok(s)
+        }
+    }
+
+    /// Returns a [`ParallelStorageIter`] of the key value pairs under some address. Unlike
+    /// [`Storage::iter()`], the key space is split into a number of ranges which are fetched
+    /// concurrently, and so entries are not handed back in key order.
+    ///
+    /// The [`StorageIterCursor`] obtained from [`ParallelStorageIter::cursor()`] can be saved
+    /// and then given here to resume iterating over the same address from where it left off,
+    /// for instance if the connection to the node drops. [`ParallelStorageIter::progress()`]
+    /// reports how many keys have been iterated over so far.
+    ///
+    /// Iteration should be resumed at the same block, otherwise entries which have changed in
+    /// the meantime may be missed or handed back twice.
+    ///
+    /// Where the backend can't begin fetching from a start key (see
+    /// [`crate::backend::Backend::storage_fetch_descendant_values_after()`]), such as
+    /// [`crate::backend::unstable::UnstableBackend`], each range is instead fetched one byte
+    /// prefix at a time.
+    ///
+    /// ```no_run
+    /// use subxt::{ PolkadotConfig, OnlineClient };
+    ///
+    /// #[subxt::subxt(runtime_metadata_path = "../artifacts/polkadot_metadata_full.scale")]
+    /// pub mod polkadot {}
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+    /// let storage = api.storage().at_latest().await.unwrap();
+    ///
+    /// let mut cursor = None;
+    /// loop {
+    ///     let address = polkadot::storage().system().account_iter();
+    ///     let mut iter = storage.iter_parallel(address, cursor.clone()).await.unwrap();
+    ///
+    ///     while let Some(res) = iter.next().await {
+    ///         match res {
+    ///             Ok(kv) => println!("{:?}: {:?}", kv.keys, kv.value),
+    ///             // Something went wrong; carry on from where we left off.
+    ///             Err(_) => break,
+    ///         }
+    ///         if iter.progress().keys_scanned % 10_000 == 0 {
+    ///             println!("{:?}", iter.progress());
+    ///         }
+    ///     }
+    ///
+    ///     let next_cursor = iter.cursor();
+    ///     if next_cursor.is_done() {
+    ///         break;
+    ///     }
+    ///     cursor = Some(next_cursor);
+    /// }
+    /// # }
+    /// ```
+    pub fn iter_parallel<Addr>(
+        &self,
+        address: Addr,
+        cursor: Option<StorageIterCursor>,
+    ) -> impl Future<Output = Result<ParallelStorageIter<Addr>, Error>> + 'static
+    where
+        Addr: Address<IsIterable = Yes> + 'static,
+        Addr::Keys: 'static + Sized,
+    {
+        let client = self.client.clone();
+        let block_ref = self.block_ref.clone();
+        async move {
+            let metadata = client.metadata();
+            let (_pallet, entry) = subxt_core::storage::lookup_storage_entry_details(
+                address.pallet_name(),
+                address.entry_name(),
+                &metadata,
+            )?;
+
+            // Metadata validation checks whether the static address given
+            // is likely to actually correspond to a real storage entry or not.
+            // if not, it means static codegen doesn't line up with runtime
+            // metadata.
+            subxt_core::storage::validate(&address, &metadata)?;
+
+            let entry = entry.entry_type();
+            let return_type_id = entry.value_ty();
+            let hashers = StorageHashers::new(entry, metadata.types())?;
+
+            // The address bytes of this entry:
+            let address_bytes = subxt_core::storage::get_address_bytes(&address, &metadata)?;
+            ParallelStorageIter::new(
+                client,
+                address_bytes,
+                cursor,
+                block_ref.hash(),
+                return_type_id,
+                hashers,
+                metadata,
+            )
+            .await
+        }
+    }
+
+    /// Compare the entries at some address in this block with those in another block, returning
+    /// a [`StorageDiff`] for each entry that was added, removed or modified between this block
+    /// and the other one. Diffs are handed back in key order.
+    ///
+    /// Where the backend supports it (see [`crate::backend::Backend::storage_fetch_closest_descendant_merkle_values()`]),
+    /// parts of the storage trie which are identical in both blocks are skipped over. The hashes
+    /// of the values in the remaining parts are then compared, and only the values which differ
+    /// are fetched in full. Backends which can't hash values remotely (see
+    /// [`crate::backend::Backend::storage_fetch_descendant_hashes()`]), such as
+    /// [`crate::backend::legacy::LegacyBackend`], will download every value under the address
+    /// in both blocks in order to hash them locally.
+    ///
+    /// ```no_run
+    /// use subxt::{ PolkadotConfig, OnlineClient };
+    /// use subxt::storage::StorageDiff;
+    ///
+    /// #[subxt::subxt(runtime_metadata_path = "../artifacts/polkadot_metadata_full.scale")]
+    /// pub mod polkadot {}
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+    ///
+    /// let block = api.blocks().at_latest().await.unwrap();
+    /// let parent_hash = block.header().parent_hash;
+    ///
+    /// // Which accounts changed in the latest block?
+    /// let address = polkadot::storage().system().account_iter();
+    /// let mut diffs = api
+    ///     .storage()
+    ///     .at(parent_hash)
+    ///     .diff(address, block.reference())
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(Ok(diff)) = diffs.next().await {
+    ///     match diff {
+    ///         StorageDiff::Added(kv) => println!("Added: {:?}", kv.keys),
+    ///         StorageDiff::Removed(kv) => println!("Removed: {:?}", kv.keys),
+    ///         StorageDiff::Modified { old, new } => {
+    ///             println!("Modified: {:?}: {:?} -> {:?}", new.keys, old.value, new.value)
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn diff<Addr>(
+        &self,
+        address: Addr,
+        other_block: impl Into<BlockRef<T::Hash>>,
+    ) -> impl Future<Output = Result<StreamOfResults<StorageDiff<Addr>>, Error>> + 'static
+    where
+        Addr: Address<IsIterable = Yes> + 'static,
+        Addr::Keys: 'static + Sized,
+    {
+        let client = self.client.clone();
+        let block_ref = self.block_ref.clone();
+        let other_block_ref = other_block.into();
+        async move {
+            let metadata = client.metadata();
+            let (_pallet, entry) = subxt_core::storage::lookup_storage_entry_details(
+                address.pallet_name(),
+                address.entry_name(),
+                &metadata,
+            )?;
+
+            // Metadata validation checks whether the static address given
+            // is likely to actually correspond to a real storage entry or not.
+            // if not, it means static codegen doesn't line up with runtime
+            // metadata.
+            subxt_core::storage::validate(&address, &metadata)?;
+
+            let entry = entry.entry_type();
+            let return_type_id = entry.value_ty();
+            let hashers = StorageHashers::new(entry, metadata.types())?;
+
+            let old_hash = block_ref.hash();
+            let new_hash = other_block_ref.hash();
+
+            // The address bytes of this entry:
+            let address_bytes = subxt_core::storage::get_address_bytes(&address, &metadata)?;
+            let changed_keys =
+                storage_diff::changed_keys(client.clone(), address_bytes, old_hash, new_hash)
+                    .await?;
+
+            let s = changed_keys
+                .try_chunks(DIFF_BATCH_SIZE)
+                .map_err(|e| e.1)
+                .and_then(move |keys| {
+                    let client = client.clone();
+                    // Keep the blocks pinned until the values have been fetched:
+                    let block_refs = (block_ref.clone(), other_block_ref.clone());
+                    async move {
+                        let (mut old_values, mut new_values) = futures::future::try_join(
+                            storage_diff::fetch_values(client.backend(), keys.clone(), old_hash),
+                            storage_diff::fetch_values(client.backend(), keys.clone(), new_hash),
+                        )
+                        .await?;
+                        drop(block_refs);
+
+                        let values = keys
+                            .into_iter()
+                            .map(|key| {
+                                let old = old_values.remove(&key);
+                                let new = new_values.remove(&key);
+                                Ok::<_, Error>((key, old, new))
+                            })
+                            .collect::<Vec<_>>();
+                        Ok::<_, Error>(futures::stream::iter(values))
+                    }
+                })
+                .try_flatten()
+                .map(move |values: Result<_, Error>| {
+                    let (key, old, new) = values?;
+                    let decode = |value: Vec<u8>| {
+                        decode_storage_key_value_pair::<Addr>(
+                            key.clone(),
+                            &value,
+                            return_type_id,
+                            &hashers,
+                            &metadata,
+                        )
+                    };
+                    match (old, new) {
+                        (None, Some(new)) => Ok(StorageDiff::Added(decode(new)?)),
+                        (Some(old), None) => Ok(StorageDiff::Removed(decode(old)?)),
+                        (Some(old), Some(new)) => Ok(StorageDiff::Modified {
+                            old: decode(old)?,
+                            new: decode(new)?,
+                        }),
+                        (None, None) => Err(Error::Other(format!(
+                            "Storage value at key 0x{} could not be found in either block",
+                            hex::encode(&key)
+                        ))),
+                    }
                 });
 
             let s = StreamOfResults::new(Box::pin(s));
@@ -261,6 +732,87 @@ where
         }
     }
 
+    /// Take a [`StorageSnapshot`] of every storage entry under the given pallets at this block,
+    /// alongside the block header and the metadata needed to decode them. The metadata and
+    /// runtime version recorded are those in use at this block (see
+    /// [`OnlineClientT::at_runtime_of()`]).
+    ///
+    /// ```no_run
+    /// use subxt::{ PolkadotConfig, OnlineClient };
+    /// use subxt::storage::StorageSnapshot;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+    ///
+    /// // Save the storage of a couple of pallets to a file..
+    /// let snapshot = api
+    ///     .storage()
+    ///     .at_latest()
+    ///     .await
+    ///     .unwrap()
+    ///     .snapshot(["Balances", "Staking"])
+    ///     .await
+    ///     .unwrap();
+    /// snapshot.write_to_file("snapshot.scale").unwrap();
+    ///
+    /// // ..and later, load it back to look at it without a node.
+    /// let snapshot = StorageSnapshot::<PolkadotConfig>::read_from_file("snapshot.scale").unwrap();
+    /// let storage = snapshot.storage().unwrap();
+    /// # }
+    /// ```
+    pub fn snapshot<'a>(
+        &self,
+        pallet_names: impl IntoIterator<Item = &'a str>,
+    ) -> impl Future<Output = Result<StorageSnapshot<T>, Error>> + 'static {
+        let client = self.client.clone();
+        let block_ref = self.block_ref.clone();
+        let pallet_names: Vec<String> = pallet_names.into_iter().map(Into::into).collect();
+        async move {
+            // Record the runtime that was in use at the block, which may not be the latest.
+            let block_hash = block_ref.hash();
+            let client = client.at_runtime_of(block_hash).await?;
+            let metadata = client.metadata();
+
+            let header = client
+                .backend()
+                .block_header(block_hash)
+                .await?
+                .ok_or_else(|| BlockError::not_found(block_hash))?;
+
+            let mut entries = InMemoryStorage::default();
+            for pallet_name in pallet_names {
+                let pallet = metadata
+                    .pallet_by_name(&pallet_name)
+                    .ok_or_else(|| MetadataError::PalletNameNotFound(pallet_name.clone()))?;
+
+                // Pallets without any storage have nothing to snapshot.
+                let Some(storage) = pallet.storage() else {
+                    continue;
+                };
+
+                // Every storage entry in a pallet begins with the hash of its storage prefix.
+                let prefix = sp_crypto_hashing::twox_128(storage.prefix().as_bytes()).to_vec();
+                let mut values = client
+                    .backend()
+                    .storage_fetch_descendant_values(prefix, block_hash)
+                    .await?;
+                while let Some(kv) = values.next().await {
+                    let kv = kv?;
+                    entries.insert(kv.key, kv.value);
+                }
+            }
+
+            Ok(StorageSnapshot::new(
+                client.genesis_hash(),
+                client.runtime_version(),
+                header,
+                metadata,
+                entries,
+            ))
+        }
+    }
+
     /// The storage version of a pallet.
     /// The storage version refers to the `frame_support::traits::Metadata::StorageVersion` type.
     pub async fn storage_version(&self, pallet_name: impl AsRef<str>) -> Result<u16, Error> {
@@ -300,6 +852,57 @@ where
     }
 }
 
+/// Fetch the header of the given block and return the state root from it, checking that the
+/// header we were given back does in fact belong to the block hash we asked for.
+async fn verified_state_root<T: Config, Client: OnlineClientT<T>>(
+    client: &Client,
+    block_hash: T::Hash,
+) -> Result<T::Hash, Error> {
+    let header = client
+        .backend()
+        .block_header(block_hash)
+        .await?
+        .ok_or_else(|| BlockError::not_found(block_hash))?;
+
+    if header.hash() != block_hash {
+        return Err(StorageProofError::HeaderHashMismatch.into());
+    }
+
+    let state_root = header
+        .state_root()
+        .ok_or(StorageProofError::StateRootNotAvailable)?;
+    Ok(state_root)
+}
+
+/// Decode the keys and value of some storage entry that we've iterated over.
+pub(super) fn decode_storage_key_value_pair<Addr: Address>(
+    key_bytes: Vec<u8>,
+    value_bytes: &[u8],
+    return_type_id: u32,
+    hashers: &StorageHashers,
+    metadata: &Metadata,
+) -> Result<StorageKeyValuePair<Addr>, Error>
+where
+    Addr::Keys: 'static,
+{
+    let value = Addr::Target::decode_with_metadata(&mut &*value_bytes, return_type_id, metadata)?;
+
+    let cursor = &mut &key_bytes[..];
+    strip_storage_address_root_bytes(cursor)?;
+
+    let keys = <Addr::Keys as StorageKey>::decode_storage_key(
+        cursor,
+        &mut hashers.iter(),
+        metadata.types(),
+    )?;
+
+    Ok(StorageKeyValuePair {
+        keys,
+        key_bytes,
+        value,
+    })
+}
+
 /// Strips the first 32 bytes (16 for the pallet hash, 16 for the entry hash) off some storage address bytes.
 fn strip_storage_address_root_bytes(address_bytes: &mut &[u8]) -> Result<(), StorageAddressError> {
     if address_bytes.len() >= 32 {
@@ -321,3 +924,190 @@ pub struct StorageKeyValuePair<T: Address> {
     /// The value of the storage entry.
     pub value: T::Target,
 }
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::StorageDiff;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::{SubstrateHeader, H256};
+    use crate::error::StorageProofError;
+    use crate::{config::Header, Error, OnlineClient, SubstrateConfig};
+    use codec::Encode;
+    use futures::TryStreamExt;
+    use sp_trie::{LayoutV1, MemoryDB, TrieDBMutBuilder, TrieMut};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn key(pallet: &str, entry: &str) -> Vec<u8> {
+        let mut key = sp_crypto_hashing::twox_128(pallet.as_bytes()).to_vec();
+        key.extend(sp_crypto_hashing::twox_128(entry.as_bytes()));
+        key
+    }
+
+    fn entries(balance: u128) -> Entries {
+        let mut entries = vec![(key("Timestamp", "Now"), 1234u64.encode())];
+        for n in 0..3u8 {
+            let account_id = [n; 32];
+            let mut key = key("System", "Account");
+            key.extend(sp_crypto_hashing::blake2_128(&account_id));
+            key.extend(account_id);
+            let value = (0u32, 0u32, 0u32, 0u32, (balance, 0u128, 0u128, 0u128));
+            entries.push((key, value.encode()));
+        }
+        entries
+    }
+
+    // Build a trie from the given entries and return the root plus every node in the
+    // trie; a superset of the nodes is a valid proof for any key.
+    fn trie(entries: &Entries) -> (H256, Vec<Vec<u8>>) {
+        let mut db = MemoryDB::<sp_core::Blake2Hasher>::default();
+        let mut root = Default::default();
+        {
+            let mut trie =
+                TrieDBMutBuilder::<LayoutV1<sp_core::Blake2Hasher>>::new(&mut db, &mut root)
+                    .build();
+            for (k, v) in entries {
+                trie.insert(k, v).unwrap();
+            }
+        }
+        let nodes = db.drain().into_values().map(|(node, _rc)| node).collect();
+        (H256(root.0), nodes)
+    }
+
+    // Serve the given entries at block 1, whose state root is that of `entries`, but hand
+    // back a proof built from `proof_entries`.
+    async fn client(
+        entries: Entries,
+        proof_entries: Entries,
+    ) -> (OnlineClient<SubstrateConfig>, H256) {
+        let (state_root, _) = trie(&entries);
+        let (_, proof) = trie(&proof_entries);
+        let block1 = SubstrateHeader {
+            state_root,
+            ..test_header(1, test_header(0, H256::zero()).hash())
+        };
+        let hash = block1.hash();
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .block(block1, vec![])
+            .storage_entries(hash, entries)
+            .read_proof(hash, proof)
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+        (client, hash)
+    }
+
+    #[tokio::test]
+    async fn diffs_entries_between_blocks() {
+        let (old_hash, new_hash) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let old_entries = entries(100);
+        // Account 0 is unchanged, 1 is modified, 2 is removed and 3 is added:
+        let mut new_entries = entries(200);
+        new_entries[1] = old_entries[1].clone();
+        let mut added_key = key("System", "Account");
+        added_key.extend(sp_crypto_hashing::blake2_128(&[3u8; 32]));
+        added_key.extend([3u8; 32]);
+        new_entries[3] = (added_key, new_entries[3].1.clone());
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .storage_entries(old_hash, old_entries)
+            .storage_entries(new_hash, new_entries)
+            .build();
+        let client = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+
+        let diffs: Vec<_> = client
+            .storage()
+            .at(old_hash)
+            .diff(crate::storage::dynamic("System", "Account", ()), new_hash)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let account_of = |kv: &super::StorageKeyValuePair<_>| kv.key_bytes.last().copied();
+        let diffs: Vec<_> = diffs
+            .iter()
+            .map(|diff| match diff {
+                StorageDiff::Added(kv) => ("added", account_of(kv)),
+                StorageDiff::Removed(kv) => ("removed", account_of(kv)),
+                StorageDiff::Modified { old, new } => {
+                    assert_eq!(old.key_bytes, new.key_bytes);
+                    assert_ne!(old.value.encoded(), new.value.encoded());
+                    ("modified", account_of(new))
+                }
+            })
+            .collect();
+        assert_eq!(diffs.len(), 3);
+        for diff in [
+            ("modified", Some(1)),
+            ("removed", Some(2)),
+            ("added", Some(3)),
+        ] {
+            assert!(diffs.contains(&diff), "{diff:?} missing from {diffs:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_and_iterates_verified_values() {
+        let (client, hash) = client(entries(100), entries(100)).await;
+        let storage = client.storage().at(hash);
+
+        let now = storage
+            .fetch_verified(&crate::storage::dynamic("Timestamp", "Now", ()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(now.encoded(), 1234u64.encode());
+
+        let missing = storage
+            .fetch_verified(&crate::storage::dynamic("Timestamp", "DidUpdate", ()))
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        let accounts: Vec<_> = storage
+            .iter_verified(crate::storage::dynamic("System", "Account", ()))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let accounts: BTreeMap<_, _> = accounts
+            .into_iter()
+            .map(|kv| (kv.key_bytes, kv.value.encoded().to_vec()))
+            .collect();
+        let expected: BTreeMap<_, _> = entries(100).into_iter().skip(1).collect();
+        assert_eq!(accounts, expected);
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_proofs() {
+        // The backend hands back values and a proof which don't match the state root.
+        let (client, hash) = client(entries(100), entries(1_000_000)).await;
+        let storage = client.storage().at(hash);
+
+        let res = storage
+            .fetch_verified(&crate::storage::dynamic("Timestamp", "Now", ()))
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::StorageProof(StorageProofError::IncompleteProof))
+        ));
+
+        let res: Result<Vec<_>, _> = storage
+            .iter_verified(crate::storage::dynamic("System", "Account", ()))
+            .await
+            .unwrap()
+            .try_collect()
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::StorageProof(StorageProofError::IncompleteProof))
+        ));
+    }
+}
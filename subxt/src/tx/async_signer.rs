@@ -0,0 +1,231 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{error::Error, metadata::Metadata, tx::Signer as SignerT, Config};
+use async_trait::async_trait;
+
+/// Like [`crate::tx::Signer`], but signing is asynchronous and can fail. This suits signers
+/// whose keys live elsewhere, such as on a hardware device, in a key management service or
+/// in a browser extension, which need to be asked for each signature.
+///
+/// Every [`crate::tx::Signer`] is also an [`AsyncSigner`], so any signer can be used with the
+/// `_async` methods on [`super::TxClient`], with [`super::TxClient::create_partial_signed_for()`]
+/// and with [`super::PartialExtrinsic::sign_async()`].
+///
+/// A [`crate::tx::ProxySigner`] can only wrap a [`crate::tx::Signer`]. To dispatch calls on
+/// behalf of another account from an [`AsyncSigner`], implement [`AsyncSigner::wrap_call_data()`]
+/// to wrap each call in `Proxy::proxy`.
+#[async_trait]
+pub trait AsyncSigner<T: Config>: Send + Sync {
+    /// Return the "from" account ID.
+    fn account_id(&self) -> T::AccountId;
+
+    /// Return the "from" address.
+    fn address(&self) -> T::Address;
+
+    /// Takes a signer payload for an extrinsic, and returns a signature based on it.
+    ///
+    /// This may fail, for instance because the remote signer could not be reached, or
+    /// because it refused to sign the payload.
+    async fn sign(&self, signer_payload: &[u8]) -> Result<T::Signature, Error>;
+
+    /// Takes the SCALE encoded call data of a transaction that is about to be signed, and
+    /// returns the call data that should be signed and submitted in its place. See
+    /// [`crate::tx::Signer::wrap_call_data()`]. By default, the call data is returned unchanged.
+    fn wrap_call_data(&self, call_data: Vec<u8>, _metadata: &Metadata) -> Result<Vec<u8>, Error> {
+        Ok(call_data)
+    }
+}
+
+#[async_trait]
+impl<T, S> AsyncSigner<T> for S
+where
+    T: Config,
+    S: SignerT<T> + Send + Sync,
+{
+    fn account_id(&self) -> T::AccountId {
+        SignerT::account_id(self)
+    }
+
+    fn address(&self) -> T::Address {
+        SignerT::address(self)
+    }
+
+    async fn sign(&self, signer_payload: &[u8]) -> Result<T::Signature, Error> {
+        Ok(SignerT::sign(self, signer_payload))
+    }
+
+    fn wrap_call_data(&self, call_data: Vec<u8>, metadata: &Metadata) -> Result<Vec<u8>, Error> {
+        Ok(SignerT::wrap_call_data(self, call_data, metadata)?)
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::{mock::MockBackend, TransactionStatus};
+    use crate::dynamic::Value;
+    use crate::tx::Payload;
+    use crate::utils::{AccountId32, MultiAddress, MultiSignature};
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Encode;
+    use futures::channel::{mpsc, oneshot};
+    use futures::{SinkExt, StreamExt};
+    use std::sync::Arc;
+
+    type SignRequest = (Vec<u8>, oneshot::Sender<MultiSignature>);
+
+    /// A signer which sends each payload over a channel and waits for a signature to come
+    /// back, standing in for some remote signing service.
+    struct ChannelSigner {
+        account_id: AccountId32,
+        requests: mpsc::Sender<SignRequest>,
+    }
+
+    #[async_trait]
+    impl AsyncSigner<SubstrateConfig> for ChannelSigner {
+        fn account_id(&self) -> AccountId32 {
+            self.account_id.clone()
+        }
+
+        fn address(&self) -> MultiAddress<AccountId32, u32> {
+            self.account_id.clone().into()
+        }
+
+        async fn sign(&self, signer_payload: &[u8]) -> Result<MultiSignature, Error> {
+            let (tx, rx) = oneshot::channel();
+            self.requests
+                .clone()
+                .send((signer_payload.to_vec(), tx))
+                .await
+                .map_err(|e| Error::Other(format!("Cannot reach remote signer: {e}")))?;
+            rx.await
+                .map_err(|_| Error::Other("Remote signer refused to sign".into()))
+        }
+    }
+
+    /// An async signer which wraps each call in a `System::remark`, standing in for async
+    /// signers which wrap calls, for instance to dispatch them via a proxy.
+    struct RemarkingSigner(subxt_signer::sr25519::Keypair);
+
+    #[async_trait]
+    impl AsyncSigner<SubstrateConfig> for RemarkingSigner {
+        fn account_id(&self) -> AccountId32 {
+            self.0.public_key().to_account_id()
+        }
+
+        fn address(&self) -> MultiAddress<AccountId32, u32> {
+            self.0.public_key().to_address()
+        }
+
+        async fn sign(&self, signer_payload: &[u8]) -> Result<MultiSignature, Error> {
+            Ok(self.0.sign(signer_payload).into())
+        }
+
+        fn wrap_call_data(
+            &self,
+            call_data: Vec<u8>,
+            metadata: &Metadata,
+        ) -> Result<Vec<u8>, Error> {
+            let remark = crate::dynamic::tx("System", "remark", vec![Value::from_bytes(call_data)]);
+            Ok(remark.encode_call_data(metadata)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn wraps_partial_extrinsics_for_async_signers() {
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let signer = RemarkingSigner(subxt_signer::sr25519::dev::alice());
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let wrapped = crate::dynamic::tx(
+            "System",
+            "remark",
+            vec![Value::from_bytes(api.tx().call_data(&call).unwrap())],
+        );
+        let wrapped = api.tx().call_data(&wrapped).unwrap();
+
+        let partial = api
+            .tx()
+            .create_partial_signed_for(&call, &signer, Default::default())
+            .await
+            .unwrap();
+        let partial_offline = api
+            .tx()
+            .create_partial_signed_offline_for(&call, &signer, Default::default())
+            .unwrap();
+        assert_eq!(partial.call_data(), wrapped);
+        assert_eq!(partial_offline.call_data(), wrapped);
+
+        let signed = partial.sign_async(&signer).await.unwrap();
+        assert!(signed.encoded().ends_with(&wrapped));
+    }
+
+    #[tokio::test]
+    async fn signs_and_submits_via_async_signer() {
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            .transaction_statuses([TransactionStatus::Validated])
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+
+        // The "remote" end signs the first payload with Alice's key, and refuses the next.
+        let alice = subxt_signer::sr25519::dev::alice();
+        let (requests, mut incoming) = mpsc::channel::<SignRequest>(1);
+        let remote = tokio::spawn(async move {
+            let (payload, reply) = incoming.next().await.unwrap();
+            let signature = MultiSignature::from(alice.sign(&payload));
+            reply.send(signature.clone()).unwrap();
+
+            let (_payload, reply) = incoming.next().await.unwrap();
+            drop(reply);
+            signature
+        });
+        let signer = ChannelSigner {
+            account_id: subxt_signer::sr25519::dev::alice()
+                .public_key()
+                .to_account_id(),
+            requests,
+        };
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        api.tx()
+            .sign_and_submit_default_async(&call, &signer)
+            .await
+            .unwrap();
+        assert!(api
+            .tx()
+            .sign_and_submit_default_async(&call, &signer)
+            .await
+            .is_err());
+
+        // The signature from the remote end made it into the submitted extrinsic.
+        let signature = remote.await.unwrap().encode();
+        let submitted = backend.submitted_transactions();
+        assert_eq!(submitted.len(), 1);
+        assert!(submitted[0]
+            .windows(signature.len())
+            .any(|w| w == signature));
+
+        // Ordinary signers can be used as async signers too.
+        let partial = api
+            .tx()
+            .create_partial_signed_offline(&call, Default::default())
+            .unwrap();
+        let signed = partial
+            .sign_async(&subxt_signer::sr25519::dev::bob())
+            .await
+            .unwrap();
+        assert!(signed.encoded().ends_with(partial.call_data()));
+    }
+}
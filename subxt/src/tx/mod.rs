@@ -11,8 +11,15 @@
 
 use crate::macros::cfg_substrate_compat;
 
+mod async_signer;
+mod nonce_manager;
+mod tx_batch;
 mod tx_client;
+mod tx_dry_run;
+mod tx_fee;
+mod tx_multisig;
 mod tx_progress;
+mod tx_resubmit;
 
 // The PairSigner impl currently relies on Substrate bits and pieces, so make it an optional
 // feature if we want to avoid needing sp_core and sp_runtime.
@@ -20,10 +27,17 @@ cfg_substrate_compat! {
     pub use subxt_core::tx::signer::PairSigner;
 }
 
+pub use async_signer::AsyncSigner;
+pub use nonce_manager::NonceManager;
 pub use subxt_core::tx::payload::{dynamic, DefaultPayload, DynamicPayload, Payload};
-pub use subxt_core::tx::signer::{self, Signer};
+pub use subxt_core::tx::signer::{self, ProxySigner, Signer};
+pub use tx_batch::{BatchKind, TxBatchBuilder};
 pub use tx_client::{
     PartialExtrinsic, SubmittableExtrinsic, TransactionInvalid, TransactionUnknown, TxClient,
     ValidationResult,
 };
+pub use tx_dry_run::DryRunOutcome;
+pub use tx_fee::{DispatchClass, FeeDetails};
+pub use tx_multisig::{Multisig, PendingMultisig, Timepoint};
 pub use tx_progress::{TxInBlock, TxProgress, TxStatus};
+pub use tx_resubmit::{ResubmitEvent, ResubmitPolicy, ResubmitProgress};
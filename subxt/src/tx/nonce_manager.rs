@@ -0,0 +1,400 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Hand out account nonces locally so that many transactions can be submitted
+//! concurrently from the same account.
+
+use crate::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    client::OnlineClientT,
+    config::Config,
+    error::{Error, TransactionError},
+    tx::{TransactionInvalid, TxStatus, ValidationResult},
+    utils::PhantomDataSendSync,
+};
+use codec::Encode;
+use derive_where::derive_where;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// A [`NonceManager`] caches the next nonce to use for each account that it's asked about,
+/// and hands out increasing nonces from this cache. This avoids fetching a nonce from the node
+/// for every transaction, which would hand out the same nonce to transactions that are submitted
+/// concurrently from the same account.
+///
+/// If a transaction is rejected because its nonce is stale or too far in the future, or is dropped
+/// or deemed invalid after submission, then the cached nonce may no longer be accurate. Pass these
+/// results to [`NonceManager::observe_validation_result()`], [`NonceManager::observe_status()`]
+/// or [`NonceManager::observe_error()`] and the nonce for that account will be fetched again from
+/// the node. If a nonce is handed out but the transaction it was for is never submitted, hand it
+/// back via [`NonceManager::release()`] so that it's used again rather than leaving a gap.
+///
+/// Cloning a [`NonceManager`] is cheap, and clones share the same nonce cache.
+#[derive_where(Clone; C)]
+pub struct NonceManager<T: Config, C> {
+    client: C,
+    source: NonceSource<T>,
+    // Nonces are keyed by the SCALE encoded account ID.
+    nonces: Arc<Mutex<HashMap<Vec<u8>, AccountNonces>>>,
+    _marker: PhantomDataSendSync<T>,
+}
+
+/// The nonces that we'll hand out next for some account.
+struct AccountNonces {
+    /// Nonces below `next` which were handed out and then released unused.
+    released: BTreeSet<u64>,
+    /// The next nonce to hand out once there are no released nonces left.
+    next: u64,
+}
+
+impl AccountNonces {
+    fn new(next: u64) -> Self {
+        Self {
+            released: BTreeSet::new(),
+            next,
+        }
+    }
+
+    fn take(&mut self) -> u64 {
+        if let Some(nonce) = self.released.pop_first() {
+            return nonce;
+        }
+        let nonce = self.next;
+        self.next += 1;
+        nonce
+    }
+
+    fn release(&mut self, nonce: u64) {
+        if nonce >= self.next {
+            return;
+        }
+        self.released.insert(nonce);
+        // Released nonces just below `next` can simply be handed out from `next` again.
+        while self.next > 0 && self.released.remove(&(self.next - 1)) {
+            self.next -= 1;
+        }
+    }
+}
+
+/// Fetch the next nonce for some account from the node.
+type FetchNonceFut = Pin<Box<dyn Future<Output = Result<u64, Error>> + Send + 'static>>;
+
+/// Where to obtain account nonces from when we don't have one cached.
+#[derive_where(Clone)]
+enum NonceSource<T: Config> {
+    /// Call the `AccountNonceApi_account_nonce` runtime API at the latest finalized block.
+    RuntimeApi,
+    /// Call the `system_accountNextIndex` RPC method, which also takes into
+    /// account transactions in the node's transaction pool.
+    AccountNextIndex(Arc<dyn Fn(T::AccountId) -> FetchNonceFut + Send + Sync>),
+}
+
+impl<T, C> NonceManager<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    /// Create a new [`NonceManager`] which obtains nonces by calling the `AccountNonceApi`
+    /// runtime API at the latest finalized block. Nonces obtained this way do not take into
+    /// account transactions which are waiting in the transaction pool.
+    pub fn new(client: C) -> Self {
+        Self::with_source(client, NonceSource::RuntimeApi)
+    }
+
+    /// Create a new [`NonceManager`] which obtains nonces by calling the legacy
+    /// `system_accountNextIndex` RPC method via the given [`RpcClient`]. Unlike
+    /// [`NonceManager::new()`], this takes into account transactions which are
+    /// waiting in the node's transaction pool.
+    pub fn with_account_next_index(client: C, rpc_client: RpcClient) -> Self
+    where
+        T::AccountId: Serialize + Send + Sync,
+    {
+        let methods = LegacyRpcMethods::<T>::new(rpc_client);
+        let fetch = move |account_id: T::AccountId| -> FetchNonceFut {
+            let methods = methods.clone();
+            Box::pin(async move { methods.system_account_next_index(&account_id).await })
+        };
+        Self::with_source(client, NonceSource::AccountNextIndex(Arc::new(fetch)))
+    }
+
+    fn with_source(client: C, source: NonceSource<T>) -> Self {
+        Self {
+            client,
+            source,
+            nonces: Arc::new(Mutex::new(HashMap::new())),
+            _marker: PhantomDataSendSync::new(),
+        }
+    }
+
+    /// Return the next nonce to use for the given account. The first time an account
+    /// is seen, its nonce is fetched from the node. After this, each call hands out a
+    /// nonce one higher than the last, unless a lower nonce has been handed back via
+    /// [`NonceManager::release()`], in which case that's handed out first.
+    pub async fn next_nonce(&self, account_id: &T::AccountId) -> Result<u64, Error> {
+        let key = account_id.encode();
+        if let Some(nonce) = self.take_cached(&key) {
+            return Ok(nonce);
+        }
+
+        // Don't hold the lock while fetching. If several tasks race to fetch a nonce
+        // for the same account, the first to finish is kept and the others hand out
+        // nonces following on from it.
+        let fetched = self.fetch_nonce(account_id).await?;
+        let mut nonces = self.nonces.lock().expect("nonce cache lock poisoned");
+        let nonce = nonces
+            .entry(key)
+            .or_insert_with(|| AccountNonces::new(fetched))
+            .take();
+        Ok(nonce)
+    }
+
+    /// Hand back a nonce obtained from [`NonceManager::next_nonce()`] which won't be used, for
+    /// instance because the transaction it was for could not be created. The nonce will be handed
+    /// out again before any higher nonces, so that no gap is left which would leave later
+    /// transactions from the account stuck in the transaction pool.
+    pub fn release(&self, account_id: &T::AccountId, nonce: u64) {
+        if let Some(nonces) = self
+            .nonces
+            .lock()
+            .expect("nonce cache lock poisoned")
+            .get_mut(&account_id.encode())
+        {
+            nonces.release(nonce);
+        }
+    }
+
+    /// Fetch the nonce for the given account from the node again, replacing any cached value.
+    /// Returns the next nonce that will be handed out for the account.
+    pub async fn resync(&self, account_id: &T::AccountId) -> Result<u64, Error> {
+        let fetched = self.fetch_nonce(account_id).await?;
+        self.nonces
+            .lock()
+            .expect("nonce cache lock poisoned")
+            .insert(account_id.encode(), AccountNonces::new(fetched));
+        Ok(fetched)
+    }
+
+    /// Forget any cached nonce for the given account, so that it's fetched again the
+    /// next time that [`NonceManager::next_nonce()`] is called.
+    pub fn reset(&self, account_id: &T::AccountId) {
+        self.nonces
+            .lock()
+            .expect("nonce cache lock poisoned")
+            .remove(&account_id.encode());
+    }
+
+    /// Inspect the result of validating a transaction from the given account. If the transaction
+    /// was invalid because its nonce was [`TransactionInvalid::Stale`] or [`TransactionInvalid::Future`],
+    /// the nonce for the account is resynced. Returns `true` if a resync happened.
+    pub async fn observe_validation_result(
+        &self,
+        account_id: &T::AccountId,
+        result: &ValidationResult,
+    ) -> Result<bool, Error> {
+        let should_resync = matches!(
+            result,
+            ValidationResult::Invalid(TransactionInvalid::Stale | TransactionInvalid::Future)
+        );
+        self.resync_if(should_resync, account_id).await
+    }
+
+    /// Inspect a status obtained from the [`crate::tx::TxProgress`] of a transaction from the given
+    /// account. If the transaction was [`TxStatus::Dropped`] or [`TxStatus::Invalid`], the nonce for
+    /// the account is resynced. Returns `true` if a resync happened.
+    pub async fn observe_status<Client>(
+        &self,
+        account_id: &T::AccountId,
+        status: &TxStatus<T, Client>,
+    ) -> Result<bool, Error> {
+        let should_resync = matches!(status, TxStatus::Dropped { .. } | TxStatus::Invalid { .. });
+        self.resync_if(should_resync, account_id).await
+    }
+
+    /// Inspect an error returned while submitting or watching a transaction from the given account,
+    /// for instance from [`crate::tx::TxProgress::wait_for_finalized()`]. If the transaction was
+    /// dropped or deemed invalid, the nonce for the account is resynced. Returns `true` if a resync
+    /// happened.
+    pub async fn observe_error(
+        &self,
+        account_id: &T::AccountId,
+        error: &Error,
+    ) -> Result<bool, Error> {
+        let should_resync = matches!(
+            error,
+            Error::Transaction(TransactionError::Dropped(_) | TransactionError::Invalid(_))
+        );
+        self.resync_if(should_resync, account_id).await
+    }
+
+    async fn resync_if(
+        &self,
+        should_resync: bool,
+        account_id: &T::AccountId,
+    ) -> Result<bool, Error> {
+        if should_resync {
+            self.resync(account_id).await?;
+        }
+        Ok(should_resync)
+    }
+
+    fn take_cached(&self, key: &[u8]) -> Option<u64> {
+        let mut nonces = self.nonces.lock().expect("nonce cache lock poisoned");
+        Some(nonces.get_mut(key)?.take())
+    }
+
+    async fn fetch_nonce(&self, account_id: &T::AccountId) -> Result<u64, Error> {
+        match &self.source {
+            NonceSource::RuntimeApi => {
+                let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+                crate::blocks::get_account_nonce(&self.client, account_id, block_ref.hash()).await
+            }
+            NonceSource::AccountNextIndex(fetch) => fetch(account_id.clone()).await,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::dynamic::Value;
+    use crate::tx::ProxySigner;
+    use crate::{OnlineClient, SubstrateConfig};
+
+    async fn nonce_manager() -> NonceManager<SubstrateConfig, OnlineClient<SubstrateConfig>> {
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 5u32.encode())
+            .build();
+        let api = OnlineClient::from_backend(Arc::new(backend)).await.unwrap();
+        NonceManager::new(api)
+    }
+
+    #[tokio::test]
+    async fn hands_out_unique_nonces_concurrently() {
+        let nonce_manager = nonce_manager().await;
+        let alice = subxt_signer::sr25519::dev::alice()
+            .public_key()
+            .to_account_id();
+
+        let tasks = (0..50).map(|_| {
+            let (nonce_manager, alice) = (nonce_manager.clone(), alice.clone());
+            tokio::spawn(async move { nonce_manager.next_nonce(&alice).await.unwrap() })
+        });
+        let mut nonces: Vec<u64> = futures::future::try_join_all(tasks).await.unwrap();
+        nonces.sort();
+
+        assert_eq!(nonces, (5..55).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn resyncs_on_invalid_nonce() {
+        let nonce_manager = nonce_manager().await;
+        let alice = subxt_signer::sr25519::dev::alice()
+            .public_key()
+            .to_account_id();
+
+        for expected in 5..8 {
+            assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), expected);
+        }
+
+        let payment = ValidationResult::Invalid(TransactionInvalid::Payment);
+        assert!(!nonce_manager
+            .observe_validation_result(&alice, &payment)
+            .await
+            .unwrap());
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 8);
+
+        let stale = ValidationResult::Invalid(TransactionInvalid::Stale);
+        assert!(nonce_manager
+            .observe_validation_result(&alice, &stale)
+            .await
+            .unwrap());
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn reuses_released_nonces() {
+        let nonce_manager = nonce_manager().await;
+        let alice = subxt_signer::sr25519::dev::alice()
+            .public_key()
+            .to_account_id();
+
+        for expected in 5..9 {
+            assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), expected);
+        }
+
+        // Gaps are filled in before higher nonces are handed out..
+        nonce_manager.release(&alice, 6);
+        nonce_manager.release(&alice, 5);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 5);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 6);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 9);
+
+        // ..and releasing the most recent nonces hands them out again in order.
+        nonce_manager.release(&alice, 8);
+        nonce_manager.release(&alice, 9);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 8);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 9);
+        assert_eq!(nonce_manager.next_nonce(&alice).await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn explicit_nonce_does_not_use_managed_nonce() {
+        let nonce_manager = nonce_manager().await;
+        let api = nonce_manager.client.clone();
+        let alice = subxt_signer::sr25519::dev::alice();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let params = crate::config::DefaultExtrinsicParamsBuilder::new()
+            .nonce(100)
+            .build();
+        let signed = api
+            .tx()
+            .create_signed_with_nonce_manager(&call, &alice, params, &nonce_manager)
+            .await
+            .unwrap();
+        let extrinsics = subxt_core::blocks::decode_from::<SubstrateConfig>(
+            vec![signed.encoded().to_vec()],
+            api.metadata(),
+        )
+        .unwrap();
+        let nonce = extrinsics
+            .iter()
+            .next()
+            .unwrap()
+            .unwrap()
+            .signed_extensions()
+            .unwrap()
+            .nonce();
+        assert_eq!(nonce, Some(100));
+
+        // The manager hands out the nonce that it would have done without the extrinsic.
+        let alice_id = alice.public_key().to_account_id();
+        assert_eq!(nonce_manager.next_nonce(&alice_id).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn failing_to_create_extrinsic_does_not_use_nonce() {
+        let nonce_manager = nonce_manager().await;
+        let api = nonce_manager.client.clone();
+        let alice = subxt_signer::sr25519::dev::alice();
+        let bob = subxt_signer::sr25519::dev::bob();
+
+        // There is no `Proxy` pallet in this metadata, so the call can't be wrapped.
+        let signer = ProxySigner::new(alice.clone(), bob.public_key().to_account_id());
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let res = api
+            .tx()
+            .create_signed_with_nonce_manager(&call, &signer, Default::default(), &nonce_manager)
+            .await;
+        assert!(res.is_err());
+
+        let alice_id = alice.public_key().to_account_id();
+        assert_eq!(nonce_manager.next_nonce(&alice_id).await.unwrap(), 5);
+    }
+}
@@ -0,0 +1,534 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::BlockRef,
+    client::{OfflineClientT, OnlineClientT},
+    config::{Config, ExtrinsicParams, Header, RefineParams, RefineParamsData},
+    constants::DefaultAddress,
+    error::{BlockError, Error},
+    metadata::Metadata,
+    runtime_api::RuntimeApi,
+    tx::{dynamic, DynamicPayload, Payload, Signer as SignerT, SubmittableExtrinsic, TxClient},
+    utils::{PhantomDataSendSync, Weight},
+};
+use codec::Encode;
+use derive_where::derive_where;
+use futures::{StreamExt, TryStreamExt};
+use scale_decode::DecodeAsType;
+use scale_value::Value;
+use subxt_core::tx::payload::call_data_to_value;
+
+/// How many `TransactionPaymentApi_query_info` calls to have in flight at once
+/// when weighing the individual calls in a batch.
+const QUERY_INFO_CONCURRENCY: usize = 16;
+
+/// The `Utility` pallet call that batched calls are wrapped in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BatchKind {
+    /// `Utility::batch_all`; if any call fails, the whole batch is reverted.
+    #[default]
+    BatchAll,
+    /// `Utility::batch`; calls are dispatched until the first failure, and any
+    /// calls dispatched before it are not reverted.
+    Batch,
+    /// `Utility::force_batch`; every call is dispatched, regardless of whether
+    /// earlier calls failed.
+    ForceBatch,
+}
+
+impl BatchKind {
+    fn call_name(&self) -> &'static str {
+        match self {
+            BatchKind::BatchAll => "batch_all",
+            BatchKind::Batch => "batch",
+            BatchKind::ForceBatch => "force_batch",
+        }
+    }
+}
+
+/// A builder which packs many calls into as few `Utility` batch calls as possible, such
+/// that each resulting batch stays within the maximum weight allowed for an extrinsic.
+///
+/// Construct one of these via [`TxClient::batch()`].
+#[derive_where(Clone; C)]
+pub struct TxBatchBuilder<T: Config, C> {
+    client: C,
+    calls: Vec<Vec<u8>>,
+    kind: BatchKind,
+    max_weight: Option<Weight>,
+    _marker: PhantomDataSendSync<T>,
+}
+
+impl<T: Config, C: OfflineClientT<T>> TxBatchBuilder<T, C> {
+    pub(crate) fn new(client: C) -> Self {
+        Self {
+            client,
+            calls: Vec::new(),
+            kind: BatchKind::default(),
+            max_weight: None,
+            _marker: PhantomDataSendSync::new(),
+        }
+    }
+
+    /// Add a call to the batch. The call is validated against the current metadata
+    /// (if it's possible to do so) and encoded straight away.
+    pub fn add_call<Call: Payload>(mut self, call: &Call) -> Result<Self, Error> {
+        let metadata = self.client.metadata();
+        subxt_core::tx::validate(call, &metadata)?;
+        self.calls.push(call.encode_call_data(&metadata)?);
+        Ok(self)
+    }
+
+    /// Add each of the given calls to the batch. See [`TxBatchBuilder::add_call()`].
+    pub fn add_calls<Calls>(self, calls: Calls) -> Result<Self, Error>
+    where
+        Calls: IntoIterator,
+        Calls::Item: Payload,
+    {
+        calls
+            .into_iter()
+            .try_fold(self, |this, call| this.add_call(&call))
+    }
+
+    /// Which `Utility` call to wrap the calls in. Defaults to [`BatchKind::BatchAll`].
+    pub fn kind(mut self, kind: BatchKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The maximum weight that any single batch is allowed to have. If this isn't
+    /// provided, the normal class `max_extrinsic` weight from the `System::BlockWeights`
+    /// constant is used.
+    pub fn max_weight(mut self, max_weight: Weight) -> Self {
+        self.max_weight = Some(max_weight);
+        self
+    }
+
+    /// The number of calls that have been added so far.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Have any calls been added yet?
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Wrap the given encoded calls into a single `Utility` batch call.
+    fn batch_payload(
+        &self,
+        calls: &[Vec<u8>],
+        metadata: &Metadata,
+    ) -> Result<DynamicPayload, Error> {
+        let calls = calls
+            .iter()
+            .map(|call| call_data_to_value(call, metadata))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(dynamic(
+            "Utility",
+            self.kind.call_name(),
+            vec![Value::unnamed_composite(calls)],
+        ))
+    }
+
+    /// Look up the maximum weight that a normal extrinsic is permitted to have.
+    fn max_extrinsic_weight(&self) -> Result<Weight, Error> {
+        // We only decode the parts of `frame_system::limits::BlockWeights` that we care about.
+        #[derive(DecodeAsType)]
+        struct BlockWeights {
+            max_block: Weight,
+            per_class: PerDispatchClass,
+        }
+        #[derive(DecodeAsType)]
+        struct PerDispatchClass {
+            normal: WeightsPerClass,
+        }
+        #[derive(DecodeAsType)]
+        struct WeightsPerClass {
+            max_extrinsic: Option<Weight>,
+        }
+
+        let address = DefaultAddress::<BlockWeights>::new("System", "BlockWeights");
+        let block_weights = self.client.constants().at(&address)?;
+        Ok(block_weights
+            .per_class
+            .normal
+            .max_extrinsic
+            .unwrap_or(block_weights.max_block))
+    }
+}
+
+impl<T, C> TxBatchBuilder<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    /// Split the calls into `Utility` batch calls, each of which has a weight no greater
+    /// than the maximum allowed. Calls are kept in the order that they were added.
+    ///
+    /// A call which exceeds the maximum weight on its own is placed into a batch by itself.
+    pub async fn payloads(&self) -> Result<Vec<DynamicPayload>, Error> {
+        let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        self.payloads_at(block_ref).await
+    }
+
+    /// Split the calls into batches and sign each one, ready to be submitted. Each batch
+    /// is given the next account nonce in sequence, starting from the current nonce of the
+    /// signer, and so the resulting extrinsics should be submitted in order. Passes default
+    /// parameters to construct the "signed extra" and "additional" payloads needed by each
+    /// extrinsic.
+    pub async fn create_signed_default<Signer>(
+        &self,
+        signer: &Signer,
+    ) -> Result<Vec<SubmittableExtrinsic<T, C>>, Error>
+    where
+        Signer: SignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::Params: Default,
+    {
+        self.create_signed(signer, Default::default).await
+    }
+
+    /// Split the calls into batches and sign each one, ready to be submitted. Each batch
+    /// is given the next account nonce in sequence, starting from the current nonce of the
+    /// signer, and so the resulting extrinsics should be submitted in order.
+    ///
+    /// `params` is called once per batch to obtain the parameters for that extrinsic. The
+    /// account nonce and mortality are filled in for each batch if they are not provided.
+    pub async fn create_signed<Signer>(
+        &self,
+        signer: &Signer,
+        mut params: impl FnMut() -> <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<Vec<SubmittableExtrinsic<T, C>>, Error>
+    where
+        Signer: SignerT<T>,
+    {
+        let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        let block_header = self
+            .client
+            .backend()
+            .block_header(block_ref.hash())
+            .await?
+            .ok_or_else(|| Error::Block(BlockError::not_found(block_ref.hash())))?;
+        let account_nonce =
+            crate::blocks::get_account_nonce(&self.client, &signer.account_id(), block_ref.hash())
+                .await?;
+
+        let payloads = self.payloads_at(block_ref).await?;
+        let tx_client = TxClient::new(self.client.clone());
+
+        payloads
+            .iter()
+            .zip(account_nonce..)
+            .map(|(payload, nonce)| {
+                let mut params = params();
+                params.refine(&RefineParamsData::new(
+                    nonce,
+                    block_header.number().into(),
+                    block_header.hash(),
+                ));
+                tx_client.create_signed_offline(payload, signer, params)
+            })
+            .collect()
+    }
+
+    async fn payloads_at(
+        &self,
+        block_ref: BlockRef<T::Hash>,
+    ) -> Result<Vec<DynamicPayload>, Error> {
+        let metadata = self.client.metadata();
+        let max_weight = match self.max_weight {
+            Some(max_weight) => max_weight,
+            None => self.max_extrinsic_weight()?,
+        };
+        let runtime_api = self.client.runtime_api().at(block_ref);
+
+        // Weigh each call individually to get a first guess at how to split them up.
+        let weights: Vec<Weight> = futures::stream::iter(&self.calls)
+            .map(|call| {
+                let (runtime_api, metadata) = (&runtime_api, &metadata);
+                async move { query_weight(runtime_api, &RawCall(call), metadata).await }
+            })
+            .buffered(QUERY_INFO_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut payloads = Vec::new();
+        let mut start = 0;
+        while start < self.calls.len() {
+            // Greedily take as many calls as fit within the max weight.
+            let mut end = start + 1;
+            let mut total = weights[start];
+            while end < self.calls.len() {
+                let next_total = total.saturating_add(weights[end]);
+                if next_total.any_gt(max_weight) {
+                    break;
+                }
+                total = next_total;
+                end += 1;
+            }
+
+            // The batch call itself adds some overhead, so weigh the actual batch. If it's
+            // too heavy, use the overhead seen to estimate how many calls to drop from the
+            // end of it, and weigh the smaller batch that's left, until one fits.
+            let payload = loop {
+                let payload = self.batch_payload(&self.calls[start..end], &metadata)?;
+                if end - start == 1 {
+                    break payload;
+                }
+                let weight = query_weight(&runtime_api, &payload, &metadata).await?;
+                if weight.all_lte(max_weight) {
+                    break payload;
+                }
+
+                let overhead = weight.saturating_sub(total);
+                loop {
+                    end -= 1;
+                    total = total.saturating_sub(weights[end]);
+                    if end - start == 1 || total.saturating_add(overhead).all_lte(max_weight) {
+                        break;
+                    }
+                }
+            };
+
+            payloads.push(payload);
+            start = end;
+        }
+
+        Ok(payloads)
+    }
+}
+
+/// Ask the runtime for the weight of some call by handing it an unsigned extrinsic.
+pub(super) async fn query_weight<T, C>(
+    runtime_api: &RuntimeApi<T, C>,
+    call: &impl Payload,
+    metadata: &Metadata,
+) -> Result<Weight, Error>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    let tx = subxt_core::tx::create_unsigned::<T, _>(call, metadata)?;
+    let mut params = tx.encoded().to_vec();
+    (tx.encoded().len() as u32).encode_to(&mut params);
+
+    // destructuring RuntimeDispatchInfo, see type information <https://paritytech.github.io/substrate/master/pallet_transaction_payment_rpc_runtime_api/struct.RuntimeDispatchInfo.html>
+    // data layout: {weight: Weight, class: u8, partial_fee: u128}
+    let (weight, _, _) = runtime_api
+        .call_raw::<(Weight, u8, u128)>("TransactionPaymentApi_query_info", Some(&params))
+        .await?;
+    Ok(weight)
+}
+
+/// Some call data which has already been encoded.
+pub(super) struct RawCall<'a>(pub(super) &'a [u8]);
+
+impl Payload for RawCall<'_> {
+    fn encode_call_data_to(
+        &self,
+        _metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt_core::Error> {
+        out.extend_from_slice(self.0);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend, MockBackendBuilder};
+    use crate::config::substrate::H256;
+    use crate::{OnlineClient, SubstrateConfig};
+    use std::sync::Arc;
+
+    // Each remark has a different length so that each call encodes differently.
+    fn remark(n: usize) -> DynamicPayload {
+        dynamic("System", "remark", vec![Value::from_bytes(vec![0u8; n])])
+    }
+
+    fn info(ref_time: u64) -> Vec<u8> {
+        (Weight::from_parts(ref_time, 0), 0u8, 0u128).encode()
+    }
+
+    // The call parameters that `query_weight` hands to `TransactionPaymentApi_query_info`.
+    fn info_params(call: &impl Payload, metadata: &Metadata) -> Vec<u8> {
+        let tx = subxt_core::tx::create_unsigned::<SubstrateConfig, _>(call, metadata).unwrap();
+        let mut params = tx.encoded().to_vec();
+        (tx.encoded().len() as u32).encode_to(&mut params);
+        params
+    }
+
+    struct Fixture {
+        metadata: Metadata,
+        builder: MockBackendBuilder<SubstrateConfig>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            // The small metadata has no `Utility` pallet.
+            let metadata_bytes =
+                std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+            let metadata = codec::Decode::decode(&mut &*metadata_bytes).unwrap();
+            let builder = MockBackend::builder()
+                .metadata(metadata_bytes)
+                .block(test_header(0, H256::zero()), vec![]);
+            Self { metadata, builder }
+        }
+
+        fn weigh(mut self, call: &impl Payload, ref_time: u64) -> Self {
+            let params = info_params(call, &self.metadata);
+            self.builder = self.builder.runtime_api_call_with_params(
+                "TransactionPaymentApi_query_info",
+                params,
+                info(ref_time),
+            );
+            self
+        }
+
+        fn weigh_batch(self, calls: &[&DynamicPayload], ref_time: u64) -> Self {
+            let batch = batch_of(calls, &self.metadata);
+            self.weigh(&batch, ref_time)
+        }
+
+        async fn payloads(self, calls: &[&DynamicPayload], max_weight: u64) -> Vec<Vec<u8>> {
+            let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(self.builder.build()))
+                .await
+                .unwrap();
+            let payloads = api
+                .tx()
+                .batch()
+                .add_calls(calls.iter().copied().cloned())
+                .unwrap()
+                .max_weight(Weight::from_parts(max_weight, u64::MAX))
+                .payloads()
+                .await
+                .unwrap();
+            payloads
+                .iter()
+                .map(|p| p.encode_call_data(&self.metadata).unwrap())
+                .collect()
+        }
+    }
+
+    fn batch_of(calls: &[&DynamicPayload], metadata: &Metadata) -> DynamicPayload {
+        let calls: Vec<_> = calls
+            .iter()
+            .map(|call| call.encode_call_data(metadata).unwrap())
+            .collect();
+        let calls = calls
+            .iter()
+            .map(|call| call_data_to_value(call, metadata).unwrap())
+            .collect::<Vec<_>>();
+        dynamic(
+            "Utility",
+            "batch_all",
+            vec![Value::unnamed_composite(calls)],
+        )
+    }
+
+    fn encoded(calls: &[&DynamicPayload], metadata: &Metadata) -> Vec<u8> {
+        batch_of(calls, metadata)
+            .encode_call_data(metadata)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exact_fit_is_one_batch() {
+        let calls = [remark(1), remark(2), remark(3)];
+        let calls: Vec<_> = calls.iter().collect();
+        let fixture = Fixture::new()
+            .weigh(calls[0], 10)
+            .weigh(calls[1], 10)
+            .weigh(calls[2], 10)
+            .weigh_batch(&calls, 30);
+        let metadata = fixture.metadata.clone();
+
+        let payloads = fixture.payloads(&calls, 30).await;
+        assert_eq!(payloads, vec![encoded(&calls, &metadata)]);
+    }
+
+    #[tokio::test]
+    async fn oversize_call_is_batched_alone() {
+        let calls = [remark(1), remark(2), remark(3)];
+        let calls: Vec<_> = calls.iter().collect();
+        let fixture = Fixture::new()
+            .weigh(calls[0], 10)
+            .weigh(calls[1], 100)
+            .weigh(calls[2], 10);
+        let metadata = fixture.metadata.clone();
+
+        // No batch with more than one call is weighed; there would be no response.
+        let payloads = fixture.payloads(&calls, 30).await;
+        assert_eq!(
+            payloads,
+            vec![
+                encoded(&calls[0..1], &metadata),
+                encoded(&calls[1..2], &metadata),
+                encoded(&calls[2..3], &metadata),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_across_several_batches() {
+        let calls = [remark(1), remark(2), remark(3), remark(4), remark(5)];
+        let calls: Vec<_> = calls.iter().collect();
+
+        // Each batch adds an overhead of 5, so only two calls fit in each. Only the batches
+        // which are guessed at are given a weight; any others would fail to be weighed.
+        let fixture = Fixture::new()
+            .weigh(calls[0], 10)
+            .weigh(calls[1], 10)
+            .weigh(calls[2], 10)
+            .weigh(calls[3], 10)
+            .weigh(calls[4], 10)
+            .weigh_batch(&calls[0..3], 35)
+            .weigh_batch(&calls[0..2], 25)
+            .weigh_batch(&calls[2..5], 35)
+            .weigh_batch(&calls[2..4], 25);
+        let metadata = fixture.metadata.clone();
+
+        let payloads = fixture.payloads(&calls, 30).await;
+        assert_eq!(
+            payloads,
+            vec![
+                encoded(&calls[0..2], &metadata),
+                encoded(&calls[2..4], &metadata),
+                encoded(&calls[4..5], &metadata),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_several_calls_with_one_weighing() {
+        let calls = [remark(1), remark(2), remark(3), remark(4)];
+        let calls: Vec<_> = calls.iter().collect();
+
+        // The overhead of 25 means that only one call fits in each batch, which we work out
+        // from a single weighing of each greedily chosen batch.
+        let fixture = Fixture::new()
+            .weigh(calls[0], 10)
+            .weigh(calls[1], 10)
+            .weigh(calls[2], 10)
+            .weigh(calls[3], 10)
+            .weigh_batch(&calls[0..3], 55)
+            .weigh_batch(&calls[1..4], 55)
+            .weigh_batch(&calls[2..4], 45);
+        let metadata = fixture.metadata.clone();
+
+        let payloads = fixture.payloads(&calls, 30).await;
+        assert_eq!(
+            payloads,
+            vec![
+                encoded(&calls[0..1], &metadata),
+                encoded(&calls[1..2], &metadata),
+                encoded(&calls[2..3], &metadata),
+                encoded(&calls[3..4], &metadata),
+            ]
+        );
+    }
+}
@@ -7,7 +7,13 @@ use crate::{
     client::{OfflineClientT, OnlineClientT},
     config::{Config, ExtrinsicParams, Header, RefineParams, RefineParamsData},
     error::{BlockError, Error},
-    tx::{Payload, Signer as SignerT, TxProgress},
+    tx::{
+        tx_batch::RawCall,
+        tx_dry_run::{dry_run, DryRunOutcome},
+        tx_fee::{query_fee_details, query_total_fee, quote_fee_in_asset, FeeDetails},
+        AsyncSigner, Multisig, NonceManager, Payload, ResubmitPolicy, ResubmitProgress,
+        Signer as SignerT, TxBatchBuilder, TxProgress,
+    },
     utils::PhantomDataSendSync,
 };
 use codec::{Compact, Decode, Encode};
@@ -83,6 +89,29 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
             .map_err(Into::into)
     }
 
+    /// Create a partial extrinsic to be signed by the given signer, which is given the chance to
+    /// wrap the call data first (see [`AsyncSigner::wrap_call_data()`]). Partial extrinsics which
+    /// will be signed by a signer that wraps call data, such as a [`crate::tx::ProxySigner`], must
+    /// be created this way. Any [`SignerT`] is also an [`AsyncSigner`].
+    ///
+    /// Note: if not provided, the default account nonce will be set to 0 and the default mortality will be _immortal_.
+    /// This is because this method runs offline, and so is unable to fetch the data needed for more appropriate values.
+    pub fn create_partial_signed_offline_for<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<PartialExtrinsic<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        self.validate(call)?;
+
+        let call_data = self.signer_call_data(call, signer)?;
+        self.create_partial_signed_offline(&RawCall(&call_data), params)
+    }
+
     /// Creates a signed extrinsic without submitting it.
     ///
     /// Note: if not provided, the default account nonce will be set to 0 and the default mortality will be _immortal_.
@@ -104,6 +133,38 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
             })
             .map_err(Into::into)
     }
+
+    /// Encode the given call, handing the call data to the signer to wrap if it wishes to.
+    /// See [`AsyncSigner::wrap_call_data()`].
+    fn signer_call_data<Call, Signer>(&self, call: &Call, signer: &Signer) -> Result<Vec<u8>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        let metadata = self.client.metadata();
+        let call_data = call.encode_call_data(&metadata)?;
+        signer.wrap_call_data(call_data, &metadata)
+    }
+
+    /// Start building a batch of calls. These will be packed into as few `Utility` batch
+    /// calls as possible while respecting the maximum extrinsic weight. See [`TxBatchBuilder`].
+    pub fn batch(&self) -> TxBatchBuilder<T, C> {
+        TxBatchBuilder::new(self.client.clone())
+    }
+
+    /// Work with the `pallet_multisig` account made up of the given signatories and
+    /// threshold; derive its account ID, build the calls needed to approve operations
+    /// from it and look up operations pending approval. See [`Multisig`].
+    ///
+    /// This returns an error if the threshold is less than 2 or greater than the number
+    /// of distinct signatories.
+    pub fn multisig(
+        &self,
+        signatories: impl IntoIterator<Item = T::AccountId>,
+        threshold: u16,
+    ) -> Result<Multisig<T, C>, Error> {
+        Multisig::new(self.client.clone(), signatories, threshold)
+    }
 }
 
 impl<T, C> TxClient<T, C>
@@ -157,12 +218,36 @@ where
         self.create_partial_signed_offline(call, params)
     }
 
+    /// Creates a partial signed extrinsic to be signed by the given signer, without submitting it.
+    /// The signer is given the chance to wrap the call data first (see [`AsyncSigner::wrap_call_data()`]),
+    /// and so partial extrinsics which will be signed by a signer that wraps call data, such as a
+    /// [`crate::tx::ProxySigner`], must be created this way. Any [`SignerT`] is also an [`AsyncSigner`].
+    pub async fn create_partial_signed_for<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<PartialExtrinsic<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        // Validate this call against the current node metadata if the call comes
+        // with a hash allowing us to do so.
+        self.validate(call)?;
+
+        // The signer may wrap the call data, for instance to proxy it.
+        let call_data = self.signer_call_data(call, signer)?;
+        self.create_partial_signed(&RawCall(&call_data), &signer.account_id(), params)
+            .await
+    }
+
     /// Creates a signed extrinsic, without submitting it.
     pub async fn create_signed<Call, Signer>(
         &self,
         call: &Call,
         signer: &Signer,
-        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+        mut params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
     ) -> Result<SubmittableExtrinsic<T, C>, Error>
     where
         Call: Payload,
@@ -172,14 +257,79 @@ where
         // with a hash allowing us to do so.
         self.validate(call)?;
 
-        // 2. Gather the "additional" and "extra" params along with the encoded call data,
-        //    ready to be signed.
-        let partial_signed = self
-            .create_partial_signed(call, &signer.account_id(), params)
+        // 2. Refine the params by adding account nonce and latest block information.
+        self.refine_params(&signer.account_id(), &mut params)
             .await?;
 
-        // 3. Sign and construct an extrinsic from these details.
-        Ok(partial_signed.sign(signer))
+        // 3. Gather the "additional" and "extra" params along with the encoded call data
+        //    (which the signer may wrap, for instance to proxy it), then sign and construct
+        //    an extrinsic from these details.
+        self.create_signed_offline(call, signer, params)
+    }
+
+    /// Creates a signed extrinsic, without submitting it, using the next nonce handed out for
+    /// the signer by the given [`NonceManager`] rather than fetching it from the node. This allows
+    /// many extrinsics from the same account to be created and submitted concurrently.
+    ///
+    /// If `params` already provides an account nonce, it is used and no nonce is taken from the
+    /// [`NonceManager`]. Otherwise, the nonce is only taken once everything else needed to
+    /// create the extrinsic is in hand, and is handed back to the [`NonceManager`] if the
+    /// extrinsic cannot be created.
+    pub async fn create_signed_with_nonce_manager<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        mut params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+        nonce_manager: &NonceManager<T, C>,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: SignerT<T>,
+    {
+        self.validate(call)?;
+
+        let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        let block_header = self
+            .client
+            .backend()
+            .block_header(block_ref.hash())
+            .await?
+            .ok_or_else(|| Error::Block(BlockError::not_found(block_ref.hash())))?;
+
+        // Only take a nonce from the manager if the params don't already provide one.
+        let account_id = signer.account_id();
+        let (account_nonce, from_manager) = match params.account_nonce() {
+            Some(nonce) => (nonce, false),
+            None => (nonce_manager.next_nonce(&account_id).await?, true),
+        };
+        params.refine(&RefineParamsData::new(
+            account_nonce,
+            block_header.number().into(),
+            block_header.hash(),
+        ));
+
+        let signed = self.create_signed_offline(call, signer, params);
+        if signed.is_err() && from_manager {
+            nonce_manager.release(&account_id, account_nonce);
+        }
+        signed
+    }
+
+    /// Creates a signed extrinsic, without submitting it, using an [`AsyncSigner`] to obtain
+    /// the signature.
+    pub async fn create_signed_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        let partial_signed = self.create_partial_signed_for(call, signer, params).await?;
+
+        partial_signed.sign_async(signer).await
     }
 
     /// Creates and signs an extrinsic and submits it to the chain. Passes default parameters
@@ -221,6 +371,33 @@ where
             .await
     }
 
+    /// Creates and signs an extrinsic and submits it to the chain, re-creating, re-signing and
+    /// resubmitting it according to the given [`ResubmitPolicy`] if it's dropped, deemed invalid,
+    /// or not finalized in time.
+    ///
+    /// `params` is called before each attempt with the attempt number (starting from 0), and
+    /// returns the parameters to use for that attempt. A fresh account nonce and mortality are
+    /// obtained for each attempt unless these parameters provide them, and a tip can be increased
+    /// for later attempts to improve the chances of inclusion.
+    ///
+    /// Returns a [`ResubmitProgress`], which can be used to track each attempt.
+    pub async fn sign_and_submit_then_watch_with_resubmission<Call, Signer, Params>(
+        &self,
+        call: Call,
+        signer: Signer,
+        params: Params,
+        policy: ResubmitPolicy,
+    ) -> Result<ResubmitProgress<T, C>, Error>
+    where
+        Call: Payload + Send + Sync + 'static,
+        Signer: SignerT<T> + Send + Sync + 'static,
+        Params: FnMut(u32) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::Params + Send + 'static,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::Params: Send,
+        T::AccountId: Send + Sync,
+    {
+        ResubmitProgress::new(self.client.clone(), call, signer, params, policy).await
+    }
+
     /// Creates and signs an extrinsic and submits to the chain for block inclusion. Passes
     /// default parameters to construct the "signed extra" and "additional" payloads needed
     /// by the extrinsic.
@@ -267,6 +444,87 @@ where
             .submit()
             .await
     }
+
+    /// Creates and signs an extrinsic using an [`AsyncSigner`] and submits it to the chain.
+    /// Passes default parameters to construct the "signed extra" and "additional" payloads
+    /// needed by the extrinsic.
+    ///
+    /// Returns a [`TxProgress`], which can be used to track the status of the transaction
+    /// and obtain details about it, once it has made it into a block.
+    pub async fn sign_and_submit_then_watch_default_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::Params: Default,
+    {
+        self.sign_and_submit_then_watch_async(call, signer, Default::default())
+            .await
+    }
+
+    /// Creates and signs an extrinsic using an [`AsyncSigner`] and submits it to the chain.
+    ///
+    /// Returns a [`TxProgress`], which can be used to track the status of the transaction
+    /// and obtain details about it, once it has made it into a block.
+    pub async fn sign_and_submit_then_watch_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<TxProgress<T, C>, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        self.create_signed_async(call, signer, params)
+            .await?
+            .submit_and_watch()
+            .await
+    }
+
+    /// Creates and signs an extrinsic using an [`AsyncSigner`] and submits to the chain for
+    /// block inclusion. Passes default parameters to construct the "signed extra" and
+    /// "additional" payloads needed by the extrinsic.
+    ///
+    /// Returns `Ok` with the extrinsic hash if it is valid extrinsic. See
+    /// [`TxClient::sign_and_submit()`] for more.
+    pub async fn sign_and_submit_default_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::Params: Default,
+    {
+        self.sign_and_submit_async(call, signer, Default::default())
+            .await
+    }
+
+    /// Creates and signs an extrinsic using an [`AsyncSigner`] and submits to the chain for
+    /// block inclusion.
+    ///
+    /// Returns `Ok` with the extrinsic hash if it is valid extrinsic. See
+    /// [`TxClient::sign_and_submit()`] for more.
+    pub async fn sign_and_submit_async<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        params: <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+    ) -> Result<T::Hash, Error>
+    where
+        Call: Payload,
+        Signer: AsyncSigner<T>,
+    {
+        self.create_signed_async(call, signer, params)
+            .await?
+            .submit()
+            .await
+    }
 }
 
 /// This payload contains the information needed to produce an extrinsic.
@@ -295,6 +553,11 @@ where
     /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit.
     /// The provided `signer` is responsible for providing the "from" address for the transaction,
     /// as well as providing a signature to attach to it.
+    ///
+    /// The call data is not handed to the signer to wrap here, so if the signer wraps call data
+    /// (for instance a [`crate::tx::ProxySigner`]), this [`PartialExtrinsic`] should have been
+    /// created via [`TxClient::create_partial_signed_for()`] or
+    /// [`TxClient::create_partial_signed_offline_for()`].
     pub fn sign<Signer>(&self, signer: &Signer) -> SubmittableExtrinsic<T, C>
     where
         Signer: SignerT<T>,
@@ -305,6 +568,25 @@ where
         }
     }
 
+    /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit,
+    /// using an [`AsyncSigner`] to provide the "from" address and the signature. This fails
+    /// if the signer fails to provide a signature.
+    ///
+    /// The call data is not handed to the signer to wrap here, so if the signer wraps call data,
+    /// this [`PartialExtrinsic`] should have been created via
+    /// [`TxClient::create_partial_signed_for()`] or
+    /// [`TxClient::create_partial_signed_offline_for()`].
+    pub async fn sign_async<Signer>(
+        &self,
+        signer: &Signer,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Signer: AsyncSigner<T>,
+    {
+        let signature = signer.sign(&self.signer_payload()).await?;
+        Ok(self.sign_with_address_and_signature(&signer.address(), &signature))
+    }
+
     /// Convert this [`PartialExtrinsic`] into a [`SubmittableExtrinsic`], ready to submit.
     /// An address, and something representing a signature that can be SCALE encoded, are both
     /// needed in order to construct it. If you have a `Signer` to hand, you can use
@@ -464,6 +746,22 @@ where
         ValidationResult::try_from_bytes(res)
     }
 
+    /// Dry run the extrinsic against the state at the given block, without submitting it. This
+    /// uses the `DryRunApi` runtime API if the runtime exposes it, which also hands back the events
+    /// that the extrinsic would emit. Otherwise, it falls back to the `system_dryRun` RPC method,
+    /// which is only available via the legacy backend and does not report any events.
+    ///
+    /// Returns `Ok` with a [`DryRunOutcome`] if the dry run was performed, regardless of whether the
+    /// extrinsic itself would have succeeded.
+    pub async fn dry_run_at(
+        &self,
+        at: impl Into<BlockRef<T::Hash>>,
+    ) -> Result<DryRunOutcome<T>, Error> {
+        let block_hash = at.into().hash();
+        let metadata = self.client.metadata();
+        dry_run(self.client.backend(), self.encoded(), block_hash, &metadata).await
+    }
+
     /// This returns an estimate for what the extrinsic is expected to cost to execute, less any tips.
     /// The actual amount paid can vary from block to block based on node traffic and other factors.
     pub async fn partial_fee_estimate(&self) -> Result<u128, Error> {
@@ -484,6 +782,47 @@ where
             .await?;
         Ok(partial_fee)
     }
+
+    /// This returns a breakdown of the fees that the extrinsic is expected to pay, along with its
+    /// weight and dispatch class. Like [`SubmittableExtrinsic::partial_fee_estimate()`], the actual
+    /// amount paid can vary from block to block.
+    pub async fn fee_details(&self) -> Result<FeeDetails, Error> {
+        let latest_block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        query_fee_details(
+            self.client.backend(),
+            self.encoded(),
+            latest_block_ref.hash(),
+        )
+        .await
+    }
+
+    /// This returns an estimate for how much of the given asset would be needed to pay the fees
+    /// for this extrinsic on chains which use `ChargeAssetTxPayment` with asset conversion (for
+    /// instance Asset Hub). The native fee, including any tip, is quoted via the `AssetConversionApi`
+    /// as the amount of `asset_id` needed to obtain it, where `native_asset_id` identifies the
+    /// native token in the same form.
+    ///
+    /// Returns `Ok(None)` if there is no pool to convert the asset via.
+    pub async fn asset_fee_estimate(
+        &self,
+        asset_id: T::AssetId,
+        native_asset_id: T::AssetId,
+    ) -> Result<Option<u128>, Error> {
+        let latest_block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        let block_hash = latest_block_ref.hash();
+        let backend = self.client.backend();
+
+        let native_fee = query_total_fee(backend, self.encoded(), block_hash).await?;
+        quote_fee_in_asset(
+            backend,
+            &self.client.metadata(),
+            &asset_id,
+            &native_asset_id,
+            native_fee,
+            block_hash,
+        )
+        .await
+    }
 }
 
 impl ValidationResult {
@@ -0,0 +1,338 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::{
+        legacy::rpc_methods::{DryRunResult, DryRunResultBytes},
+        Backend,
+    },
+    error::{DispatchError, Error},
+    events::{Events, Phase},
+    metadata::Metadata,
+    Config,
+};
+use codec::{Compact, Encode};
+use derive_where::derive_where;
+use scale_encode::EncodeAsType;
+use scale_value::{At, Value, ValueDef};
+
+/// The runtime API and method that we prefer to use to dry run extrinsics, since unlike
+/// `system_dryRun` it works with any backend and hands back the events emitted.
+const DRY_RUN_API: &str = "DryRunApi";
+const DRY_RUN_METHOD: &str = "dry_run_extrinsic";
+
+/// The outcome of dry running an extrinsic via
+/// [`super::SubmittableExtrinsic::dry_run_at()`].
+#[derive_where(Debug)]
+pub struct DryRunOutcome<T: Config> {
+    /// Whether the extrinsic would have been included and dispatched successfully.
+    pub result: DryRunResult,
+    /// The events that the extrinsic would have emitted. This is only available when the
+    /// runtime exposes the `DryRunApi`, and the extrinsic was valid. Each event is given the
+    /// phase [`Phase::ApplyExtrinsic`] with an extrinsic index of 0.
+    pub events: Option<Events<T>>,
+}
+
+/// Dry run the given extrinsic bytes at the given block, using the `DryRunApi` runtime API if
+/// the runtime exposes it, and falling back to [`Backend::dry_run()`] if not.
+pub(crate) async fn dry_run<T: Config>(
+    backend: &dyn Backend<T>,
+    extrinsic: &[u8],
+    at: T::Hash,
+    metadata: &Metadata,
+) -> Result<DryRunOutcome<T>, Error> {
+    let dry_run_method = metadata
+        .runtime_api_trait_by_name(DRY_RUN_API)
+        .and_then(|api| api.method_by_name(DRY_RUN_METHOD))
+        .filter(|method| method.inputs().len() == 1);
+
+    let Some(method) = dry_run_method else {
+        let bytes = backend.dry_run(extrinsic, at).await?;
+        let result = DryRunResultBytes(bytes).into_dry_run_result(metadata)?;
+        return Ok(DryRunOutcome {
+            result,
+            events: None,
+        });
+    };
+
+    let bytes = backend
+        .call(
+            &format!("{DRY_RUN_API}_{DRY_RUN_METHOD}"),
+            Some(extrinsic),
+            at,
+        )
+        .await?;
+    decode_dry_run_effects(&bytes, method.output_ty(), metadata)
+}
+
+/// Decode the `Result<ExtrinsicDryRunEffects, Error>` handed back from `DryRunApi_dry_run_extrinsic`.
+fn decode_dry_run_effects<T: Config>(
+    bytes: &[u8],
+    output_ty: u32,
+    metadata: &Metadata,
+) -> Result<DryRunOutcome<T>, Error> {
+    let output = scale_value::scale::decode_as_type(&mut &*bytes, output_ty, metadata.types())?;
+    let unexpected = || Error::Other("Unexpected shape of DryRunApi output".into());
+
+    let ValueDef::Variant(outcome) = &output.value else {
+        return Err(unexpected());
+    };
+    let effects = outcome.values.values().next().ok_or_else(unexpected)?;
+    if outcome.name != "Ok" {
+        // The runtime rejects extrinsics which fail validation with an `InvalidExtrinsic` error.
+        return match &effects.value {
+            ValueDef::Variant(err) if err.name == "InvalidExtrinsic" => Ok(DryRunOutcome {
+                result: DryRunResult::TransactionValidityError,
+                events: None,
+            }),
+            _ => Err(Error::Other(format!(
+                "The DryRunApi was unable to dry run the extrinsic: {effects}"
+            ))),
+        };
+    }
+
+    let execution_result = effects.at("execution_result").ok_or_else(unexpected)?;
+    let ValueDef::Variant(execution_result) = &execution_result.value else {
+        return Err(unexpected());
+    };
+    let result = if execution_result.name == "Ok" {
+        DryRunResult::Success
+    } else {
+        let error = execution_result
+            .values
+            .values()
+            .next()
+            .and_then(|err| err.at("error"))
+            .ok_or_else(unexpected)?;
+        let error_bytes = encode_value(error, metadata)?;
+        DryRunResult::DispatchError(DispatchError::decode_from(error_bytes, metadata.clone())?)
+    };
+
+    // Rebuild the events in the same shape as they are stored in `System.Events`, so that
+    // they can be decoded and inspected like any others.
+    let ValueDef::Composite(emitted_events) =
+        &effects.at("emitted_events").ok_or_else(unexpected)?.value
+    else {
+        return Err(unexpected());
+    };
+    let mut event_bytes = Compact(emitted_events.len() as u32).encode();
+    for event in emitted_events.values() {
+        Phase::ApplyExtrinsic(0).encode_to(&mut event_bytes);
+        event_bytes.extend(encode_value(event, metadata)?);
+        Vec::<T::Hash>::new().encode_to(&mut event_bytes);
+    }
+
+    Ok(DryRunOutcome {
+        result,
+        events: Some(Events::decode_from(event_bytes, metadata.clone())),
+    })
+}
+
+/// Encode a decoded value back to bytes using the type that it was decoded from.
+fn encode_value(value: &Value<u32>, metadata: &Metadata) -> Result<Vec<u8>, Error> {
+    Ok(value.encode_as_type(value.context, metadata.types())?)
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use crate::backend::legacy::rpc_methods::DryRunResult;
+    use crate::backend::mock::{test_header, MockBackend, MockBackendBuilder};
+    use crate::config::substrate::H256;
+    use crate::dynamic::Value;
+    use crate::error::DispatchError;
+    use crate::events::Phase;
+    use crate::{config::Header, Metadata, OnlineClient, SubstrateConfig};
+    use codec::{Compact, Decode, Encode};
+    use frame_metadata::{v15, RuntimeMetadata, RuntimeMetadataPrefixed};
+    use scale_info::{form::PortableForm, PortableType};
+    use std::sync::Arc;
+
+    async fn dry_run_with(
+        backend: MockBackendBuilder<SubstrateConfig>,
+    ) -> super::DryRunOutcome<SubstrateConfig> {
+        let genesis_hash = test_header(0, H256::zero()).hash();
+        let backend = backend.block(test_header(0, H256::zero()), vec![]).build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let params = crate::config::DefaultExtrinsicParamsBuilder::new()
+            .nonce(0)
+            .build();
+        api.tx()
+            .create_signed_offline(&call, &signer, params)
+            .unwrap()
+            .dry_run_at(genesis_hash)
+            .await
+            .unwrap()
+    }
+
+    async fn dry_run_with_response(response: Vec<u8>) -> super::DryRunOutcome<SubstrateConfig> {
+        // This metadata has no `DryRunApi`, so we fall back to `system_dryRun`.
+        let metadata = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata)
+            .runtime_api_call("system_dryRun", response);
+        dry_run_with(backend).await
+    }
+
+    /// The small metadata with a `DryRunApi` added, in roughly the shape that Polkadot has it.
+    fn metadata_with_dry_run_api() -> Vec<u8> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let mut prefixed = RuntimeMetadataPrefixed::decode(&mut &*bytes).unwrap();
+        let RuntimeMetadata::V15(metadata) = &mut prefixed.1 else {
+            panic!("expected V15 metadata");
+        };
+
+        let types = &mut metadata.types.types;
+        let dispatch_error = types
+            .iter()
+            .find(|ty| ty.ty.path.segments == ["sp_runtime", "DispatchError"])
+            .unwrap()
+            .id;
+        let event = metadata.outer_enums.event_enum_ty.id;
+
+        let mut add_type = |type_def: scale_info::TypeDef<PortableForm>| {
+            let id = types.len() as u32;
+            let ty = scale_info::Type {
+                path: scale_info::Path { segments: vec![] },
+                type_params: vec![],
+                type_def,
+                docs: vec![],
+            };
+            types.push(PortableType { id, ty });
+            id
+        };
+        let field = |name: Option<&str>, ty: u32| scale_info::Field::<PortableForm> {
+            name: name.map(Into::into),
+            ty: ty.into(),
+            type_name: None,
+            docs: vec![],
+        };
+        let variant = |name: &str, index: u8, fields| scale_info::Variant::<PortableForm> {
+            name: name.into(),
+            fields,
+            index,
+            docs: vec![],
+        };
+        let composite =
+            |fields| scale_info::TypeDef::Composite(scale_info::TypeDefComposite { fields });
+        let variants =
+            |variants| scale_info::TypeDef::Variant(scale_info::TypeDefVariant { variants });
+
+        let unit = add_type(scale_info::TypeDef::Tuple(scale_info::TypeDefTuple {
+            fields: vec![],
+        }));
+        let error_with_info = add_type(composite(vec![
+            field(Some("post_info"), unit),
+            field(Some("error"), dispatch_error),
+        ]));
+        let execution_result = add_type(variants(vec![
+            variant("Ok", 0, vec![field(None, unit)]),
+            variant("Err", 1, vec![field(None, error_with_info)]),
+        ]));
+        let events = add_type(scale_info::TypeDef::Sequence(scale_info::TypeDefSequence {
+            type_param: event.into(),
+        }));
+        let effects = add_type(composite(vec![
+            field(Some("execution_result"), execution_result),
+            field(Some("emitted_events"), events),
+        ]));
+        let api_error = add_type(variants(vec![
+            variant("Unimplemented", 0, vec![]),
+            variant("InvalidExtrinsic", 1, vec![]),
+        ]));
+        let output = add_type(variants(vec![
+            variant("Ok", 0, vec![field(None, effects)]),
+            variant("Err", 1, vec![field(None, api_error)]),
+        ]));
+
+        metadata.apis.push(v15::RuntimeApiMetadata {
+            name: "DryRunApi".into(),
+            methods: vec![v15::RuntimeApiMethodMetadata {
+                name: "dry_run_extrinsic".into(),
+                inputs: vec![v15::RuntimeApiMethodParamMetadata {
+                    name: "extrinsic".into(),
+                    ty: unit.into(),
+                }],
+                output: output.into(),
+                docs: vec![],
+            }],
+            docs: vec![],
+        });
+        prefixed.encode()
+    }
+
+    async fn dry_run_with_dry_run_api(response: Vec<u8>) -> super::DryRunOutcome<SubstrateConfig> {
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata_with_dry_run_api())
+            .runtime_api_call("DryRunApi_dry_run_extrinsic", response);
+        dry_run_with(backend).await
+    }
+
+    #[tokio::test]
+    async fn dry_runs_via_system_dry_run() {
+        let outcome = dry_run_with_response(vec![0, 0]).await;
+        assert_eq!(outcome.result, DryRunResult::Success);
+        assert!(outcome.events.is_none());
+
+        let outcome = dry_run_with_response(vec![1, 0, 1]).await;
+        assert_eq!(outcome.result, DryRunResult::TransactionValidityError);
+
+        // Ok(Err(DispatchError::BadOrigin)):
+        let outcome = dry_run_with_response(vec![0, 1, 2]).await;
+        assert!(matches!(
+            outcome.result,
+            DryRunResult::DispatchError(DispatchError::BadOrigin)
+        ));
+    }
+
+    #[tokio::test]
+    async fn dry_runs_via_dry_run_api() {
+        let metadata = Metadata::decode(&mut &*metadata_with_dry_run_api()).unwrap();
+        let system = metadata.pallet_by_name("System").unwrap();
+        let remarked = system
+            .event_variants()
+            .unwrap()
+            .iter()
+            .find(|v| v.name == "Remarked")
+            .unwrap();
+        let mut event = vec![system.event_index(), remarked.index];
+        event.extend([1u8; 32]);
+        event.extend([2u8; 32]);
+
+        // Ok(effects) where the extrinsic failed with BadOrigin but still emitted an event:
+        let mut response = vec![0, 1, 2];
+        Compact(1u32).encode_to(&mut response);
+        response.extend(event);
+
+        let outcome = dry_run_with_dry_run_api(response).await;
+        assert!(matches!(
+            outcome.result,
+            DryRunResult::DispatchError(DispatchError::BadOrigin)
+        ));
+        let events: Vec<_> = outcome
+            .events
+            .expect("DryRunApi hands back events")
+            .iter()
+            .map(|ev| ev.unwrap())
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].pallet_name(), "System");
+        assert_eq!(events[0].variant_name(), "Remarked");
+        assert_eq!(events[0].phase(), Phase::ApplyExtrinsic(0));
+
+        // Ok(effects) where the extrinsic succeeded without emitting anything:
+        let outcome = dry_run_with_dry_run_api(vec![0, 0, 0]).await;
+        assert_eq!(outcome.result, DryRunResult::Success);
+        assert_eq!(outcome.events.unwrap().len(), 0);
+
+        // Err(InvalidExtrinsic):
+        let outcome = dry_run_with_dry_run_api(vec![1, 1]).await;
+        assert_eq!(outcome.result, DryRunResult::TransactionValidityError);
+        assert!(outcome.events.is_none());
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::{Backend, BackendExt},
+    error::Error,
+    metadata::Metadata,
+    utils::Weight,
+    Config,
+};
+use codec::{Decode, Encode};
+use scale_encode::EncodeAsType;
+
+/// A breakdown of the fees that an extrinsic is expected to pay, obtained via
+/// [`super::SubmittableExtrinsic::fee_details()`]. Amounts are in the native token.
+///
+/// Extrinsics which don't pay an inclusion fee (for instance unsigned extrinsics) have a
+/// base, length and adjusted weight fee of 0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FeeDetails {
+    /// The minimum fee that any extrinsic must pay.
+    pub base_fee: u128,
+    /// The fee paid for the length of the extrinsic in bytes.
+    pub len_fee: u128,
+    /// The fee paid for the weight of the extrinsic, adjusted according to how
+    /// congested the chain is.
+    pub adjusted_weight_fee: u128,
+    /// The tip given by the extrinsic.
+    pub tip: u128,
+    /// The dispatch class of the extrinsic.
+    pub class: DispatchClass,
+    /// The weight of the extrinsic.
+    pub weight: Weight,
+}
+
+impl FeeDetails {
+    /// The fee paid for the extrinsic to be included in a block; everything except the tip.
+    pub fn inclusion_fee(&self) -> u128 {
+        self.base_fee
+            .saturating_add(self.len_fee)
+            .saturating_add(self.adjusted_weight_fee)
+    }
+
+    /// The total amount expected to be paid, including the tip.
+    pub fn total_fee(&self) -> u128 {
+        self.inclusion_fee().saturating_add(self.tip)
+    }
+}
+
+/// The class of some dispatchable, which determines the limits and fees that apply to it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Decode, Encode)]
+pub enum DispatchClass {
+    /// A normal dispatch.
+    Normal,
+    /// An operational dispatch.
+    Operational,
+    /// A mandatory dispatch, which is always included regardless of its weight.
+    Mandatory,
+}
+
+/// Query the fee details for the given extrinsic bytes at the given block.
+pub(crate) async fn query_fee_details<T: Config>(
+    backend: &dyn Backend<T>,
+    extrinsic: &[u8],
+    at: T::Hash,
+) -> Result<FeeDetails, Error> {
+    let params = call_params(extrinsic);
+    let ((base_fee, len_fee, adjusted_weight_fee), tip) = query_fees(backend, &params, at).await?;
+
+    // destructuring RuntimeDispatchInfo, see type information <https://paritytech.github.io/polkadot-sdk/master/pallet_transaction_payment_rpc_runtime_api/struct.RuntimeDispatchInfo.html>
+    // data layout: {weight: Weight, class: DispatchClass, partial_fee: u128}
+    let (weight, class, _) = backend
+        .call_decoding::<(Weight, DispatchClass, u128)>(
+            "TransactionPaymentApi_query_info",
+            Some(&params),
+            at,
+        )
+        .await?;
+
+    Ok(FeeDetails {
+        base_fee,
+        len_fee,
+        adjusted_weight_fee,
+        tip,
+        class,
+        weight,
+    })
+}
+
+/// Query the total fee, including any tip, that the given extrinsic bytes are expected to pay at
+/// the given block. Unlike [`query_fee_details()`], this doesn't need the weight and class of the
+/// extrinsic, and so only makes one runtime API call.
+pub(crate) async fn query_total_fee<T: Config>(
+    backend: &dyn Backend<T>,
+    extrinsic: &[u8],
+    at: T::Hash,
+) -> Result<u128, Error> {
+    let ((base_fee, len_fee, adjusted_weight_fee), tip) =
+        query_fees(backend, &call_params(extrinsic), at).await?;
+    Ok(base_fee
+        .saturating_add(len_fee)
+        .saturating_add(adjusted_weight_fee)
+        .saturating_add(tip))
+}
+
+// The `TransactionPaymentApi` calls take the extrinsic followed by its length.
+fn call_params(extrinsic: &[u8]) -> Vec<u8> {
+    let mut params = extrinsic.to_vec();
+    (extrinsic.len() as u32).encode_to(&mut params);
+    params
+}
+
+// Hands back the base, length and adjusted weight fees (all 0 if there is no inclusion fee)
+// alongside the tip.
+async fn query_fees<T: Config>(
+    backend: &dyn Backend<T>,
+    params: &[u8],
+    at: T::Hash,
+) -> Result<((u128, u128, u128), u128), Error> {
+    // destructuring FeeDetails, see type information <https://paritytech.github.io/polkadot-sdk/master/pallet_transaction_payment/struct.FeeDetails.html>
+    // data layout: {inclusion_fee: Option<{base_fee: u128, len_fee: u128, adjusted_weight_fee: u128}>, tip: u128}
+    let (inclusion_fee, tip) = backend
+        .call_decoding::<(Option<(u128, u128, u128)>, u128)>(
+            "TransactionPaymentApi_query_fee_details",
+            Some(params),
+            at,
+        )
+        .await?;
+    Ok((inclusion_fee.unwrap_or_default(), tip))
+}
+
+/// Ask the `AssetConversionApi` how much of the given asset would need to be swapped to obtain
+/// the given amount of the native asset. Returns `None` if there is no pool to swap via.
+pub(crate) async fn quote_fee_in_asset<T: Config>(
+    backend: &dyn Backend<T>,
+    metadata: &Metadata,
+    asset_id: &T::AssetId,
+    native_asset_id: &T::AssetId,
+    native_fee: u128,
+    at: T::Hash,
+) -> Result<Option<u128>, Error> {
+    let method = metadata
+        .runtime_api_trait_by_name("AssetConversionApi")
+        .and_then(|api| api.method_by_name("quote_price_tokens_for_exact_tokens"))
+        .ok_or_else(|| {
+            Error::Other("The AssetConversionApi is not available on this chain".into())
+        })?;
+    let input_tys: Vec<u32> = method.inputs().map(|input| input.ty).collect();
+    let [asset_ty, native_ty, amount_ty, include_fee_ty] = input_tys[..] else {
+        return Err(Error::Other(
+            "Unexpected arguments to AssetConversionApi_quote_price_tokens_for_exact_tokens".into(),
+        ));
+    };
+
+    // We want the amount of `asset_id` needed to obtain exactly `native_fee` of the native asset,
+    // accounting for the swap fee.
+    let types = metadata.types();
+    let mut params = Vec::new();
+    asset_id.encode_as_type_to(asset_ty, types, &mut params)?;
+    native_asset_id.encode_as_type_to(native_ty, types, &mut params)?;
+    native_fee.encode_as_type_to(amount_ty, types, &mut params)?;
+    true.encode_as_type_to(include_fee_ty, types, &mut params)?;
+
+    backend
+        .call_decoding::<Option<u128>>(
+            "AssetConversionApi_quote_price_tokens_for_exact_tokens",
+            Some(&params),
+            at,
+        )
+        .await
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::H256;
+    use crate::dynamic::Value;
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Compact;
+    use frame_metadata::{v15, RuntimeMetadata, RuntimeMetadataPrefixed};
+    use scale_info::{TypeDef, TypeDefPrimitive};
+    use std::sync::Arc;
+
+    /// The small metadata with an `AssetConversionApi` added, whose assets are `u32`s.
+    fn metadata_with_asset_conversion_api() -> Vec<u8> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let mut prefixed = RuntimeMetadataPrefixed::decode(&mut &*bytes).unwrap();
+        let RuntimeMetadata::V15(metadata) = &mut prefixed.1 else {
+            panic!("expected V15 metadata");
+        };
+
+        let primitive = |primitive: TypeDefPrimitive| {
+            metadata
+                .types
+                .types
+                .iter()
+                .find(|ty| ty.ty.type_def == TypeDef::Primitive(primitive.clone()))
+                .unwrap()
+                .id
+                .into()
+        };
+        let param = |name: &str, ty| v15::RuntimeApiMethodParamMetadata {
+            name: name.into(),
+            ty,
+        };
+        let method = v15::RuntimeApiMethodMetadata {
+            name: "quote_price_tokens_for_exact_tokens".into(),
+            inputs: vec![
+                param("asset1", primitive(TypeDefPrimitive::U32)),
+                param("asset2", primitive(TypeDefPrimitive::U32)),
+                param("amount", primitive(TypeDefPrimitive::U128)),
+                param("include_fee", primitive(TypeDefPrimitive::Bool)),
+            ],
+            // The output is decoded without consulting the metadata.
+            output: primitive(TypeDefPrimitive::U128),
+            docs: vec![],
+        };
+
+        metadata.apis.push(v15::RuntimeApiMetadata {
+            name: "AssetConversionApi".into(),
+            methods: vec![method],
+            docs: vec![],
+        });
+        prefixed.encode()
+    }
+
+    #[tokio::test]
+    async fn quotes_total_fee_in_asset() {
+        // An inclusion fee of 123 and a tip of 5:
+        let fee_details = (Some((100u128, 20u128, 3u128)), 5u128).encode();
+        let quote_params = (7u32, 0u32, 128u128, true).encode();
+
+        // No response is given for `TransactionPaymentApi_query_info`, since it isn't needed.
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata_with_asset_conversion_api())
+            .block(test_header(0, H256::zero()), vec![])
+            .runtime_api_call("TransactionPaymentApi_query_fee_details", fee_details)
+            .runtime_api_call_with_params(
+                "AssetConversionApi_quote_price_tokens_for_exact_tokens",
+                quote_params,
+                Some(1234u128).encode(),
+            )
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let params = crate::config::DefaultExtrinsicParamsBuilder::new()
+            .nonce(0)
+            .build();
+        let tx = api
+            .tx()
+            .create_signed_offline(&call, &signer, params)
+            .unwrap();
+
+        assert_eq!(tx.asset_fee_estimate(7, 0).await.unwrap(), Some(1234));
+        // Any other quote isn't known about by the mock backend, and so fails:
+        assert!(tx.asset_fee_estimate(8, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn decodes_fee_details() {
+        let fee_details = (Some((100u128, 20u128, 3u128)), 5u128).encode();
+        let info = (Compact(1000u64), Compact(64u64), 1u8, 123u128).encode();
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("TransactionPaymentApi_query_fee_details", fee_details)
+            .runtime_api_call("TransactionPaymentApi_query_info", info)
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let params = crate::config::DefaultExtrinsicParamsBuilder::new()
+            .nonce(0)
+            .build();
+        let details = api
+            .tx()
+            .create_signed_offline(&call, &signer, params)
+            .unwrap()
+            .fee_details()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            details,
+            FeeDetails {
+                base_fee: 100,
+                len_fee: 20,
+                adjusted_weight_fee: 3,
+                tip: 5,
+                class: DispatchClass::Operational,
+                weight: Weight::from_parts(1000, 64),
+            }
+        );
+        assert_eq!(details.inclusion_fee(), 123);
+        assert_eq!(details.total_fee(), 128);
+    }
+}
@@ -0,0 +1,465 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    backend::BlockRef,
+    client::{OfflineClientT, OnlineClientT},
+    config::Config,
+    error::Error,
+    metadata::Metadata,
+    storage::StaticStorageKey,
+    tx::{dynamic, DynamicPayload, Payload},
+    utils::Weight,
+};
+use codec::{Decode, Encode};
+use derive_where::derive_where;
+use futures::TryStreamExt;
+use scale_decode::DecodeAsType;
+use scale_value::{Composite, Value};
+use subxt_core::tx::payload::{call_argument_to_value, call_data_to_value};
+
+/// The prefix that `pallet_multisig` hashes alongside the signatories and threshold in
+/// order to derive the multisig account ID.
+const MULTISIG_ACCOUNT_PREFIX: &[u8; 16] = b"modlpy/utilisuba";
+
+/// The block number and extrinsic index at which a multisig operation was first approved.
+/// Every subsequent approval of the operation must provide this.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, DecodeAsType)]
+pub struct Timepoint {
+    /// The block number that the first approval was included in.
+    pub height: u32,
+    /// The index of the first approval's extrinsic in that block.
+    pub index: u32,
+}
+
+/// An entry in `Multisig::Multisigs` storage, describing a multisig operation which is still
+/// awaiting enough approvals to be executed.
+#[derive(Clone, Debug, PartialEq, Eq, DecodeAsType)]
+pub struct PendingMultisig<AccountId> {
+    /// When the operation was first approved.
+    pub when: Timepoint,
+    /// The amount held in reserve by the depositor until the operation is executed or cancelled.
+    pub deposit: u128,
+    /// The account which placed the deposit; this is the first approver.
+    pub depositor: AccountId,
+    /// The signatories which have approved the operation so far.
+    pub approvals: Vec<AccountId>,
+}
+
+/// Helpers to derive a `pallet_multisig` account and to build the `as_multi` and
+/// `approve_as_multi` calls needed to coordinate operations from it.
+///
+/// Construct one of these via [`super::TxClient::multisig()`].
+#[derive_where(Clone; C)]
+pub struct Multisig<T: Config, C> {
+    client: C,
+    // Sorted by their encoded bytes, which is the order that `pallet_multisig` expects.
+    signatories: Vec<T::AccountId>,
+    threshold: u16,
+}
+
+impl<T: Config, C: OfflineClientT<T>> Multisig<T, C> {
+    pub(crate) fn new(
+        client: C,
+        signatories: impl IntoIterator<Item = T::AccountId>,
+        threshold: u16,
+    ) -> Result<Self, Error> {
+        let mut signatories: Vec<_> = signatories
+            .into_iter()
+            .map(|account| (account.encode(), account))
+            .collect();
+        signatories.sort_by(|(a, _), (b, _)| a.cmp(b));
+        signatories.dedup_by(|(a, _), (b, _)| a == b);
+
+        // `pallet_multisig` needs at least two approvals (a threshold of one is handled by a
+        // separate call), and can't get more approvals than there are signatories.
+        if !(2..=signatories.len()).contains(&threshold.into()) {
+            return Err(Error::Other(format!(
+                "A multisig threshold of {threshold} is not between 2 and the number of signatories ({})",
+                signatories.len()
+            )));
+        }
+
+        Ok(Self {
+            client,
+            signatories: signatories
+                .into_iter()
+                .map(|(_, account)| account)
+                .collect(),
+            threshold,
+        })
+    }
+
+    /// The signatories of this multisig, sorted into the order that `pallet_multisig` expects.
+    pub fn signatories(&self) -> &[T::AccountId] {
+        &self.signatories
+    }
+
+    /// The number of signatories which must approve an operation before it's executed.
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    /// The account ID of this multisig, derived in the same way as `pallet_multisig`.
+    pub fn account_id(&self) -> Result<T::AccountId, Error>
+    where
+        T::AccountId: Decode,
+    {
+        let entropy = (MULTISIG_ACCOUNT_PREFIX, &self.signatories, self.threshold)
+            .using_encoded(sp_crypto_hashing::blake2_256);
+        Ok(T::AccountId::decode(&mut TrailingZeroInput(&entropy))?)
+    }
+
+    /// The hash of the given call, used to identify it in `approve_as_multi` calls and in
+    /// `Multisig::Multisigs` storage.
+    pub fn call_hash<Call: Payload>(&self, call: &Call) -> Result<[u8; 32], Error> {
+        let call_data = call.encode_call_data(&self.client.metadata())?;
+        Ok(sp_crypto_hashing::blake2_256(&call_data))
+    }
+
+    /// Build a `Multisig::as_multi` call for the given signatory to approve the given call.
+    /// Once the threshold is reached, the call is dispatched from the multisig account.
+    ///
+    /// `timepoint` must be `None` for the first approval, and the [`Timepoint`] of the first
+    /// approval thereafter. `max_weight` must be at least the weight of the call in order for
+    /// it to be dispatched.
+    pub fn as_multi_with<Call: Payload>(
+        &self,
+        signatory: &T::AccountId,
+        call: &Call,
+        timepoint: Option<Timepoint>,
+        max_weight: Weight,
+    ) -> Result<DynamicPayload, Error> {
+        let metadata = self.client.metadata();
+        let call_data = call.encode_call_data(&metadata)?;
+        let call = call_data_to_value(&call_data, &metadata)?;
+
+        self.multisig_payload(
+            "as_multi",
+            signatory,
+            timepoint,
+            ("call", call),
+            max_weight,
+            &metadata,
+        )
+    }
+
+    /// Build a `Multisig::approve_as_multi` call for the given signatory to approve the call
+    /// with the given hash, without providing the call itself. The final approval must use
+    /// `as_multi` instead so that the call can be dispatched.
+    ///
+    /// `timepoint` must be `None` for the first approval, and the [`Timepoint`] of the first
+    /// approval thereafter.
+    pub fn approve_as_multi_with(
+        &self,
+        signatory: &T::AccountId,
+        call_hash: [u8; 32],
+        timepoint: Option<Timepoint>,
+        max_weight: Weight,
+    ) -> Result<DynamicPayload, Error> {
+        let metadata = self.client.metadata();
+        self.multisig_payload(
+            "approve_as_multi",
+            signatory,
+            timepoint,
+            ("call_hash", Value::from_bytes(call_hash)),
+            max_weight,
+            &metadata,
+        )
+    }
+
+    /// Both multisig calls take the same arguments, except for the call (or call hash) itself.
+    fn multisig_payload(
+        &self,
+        call_name: &str,
+        signatory: &T::AccountId,
+        timepoint: Option<Timepoint>,
+        call: (&str, Value<()>),
+        max_weight: Weight,
+        metadata: &Metadata,
+    ) -> Result<DynamicPayload, Error> {
+        let signatory = signatory.encode();
+        let other_signatories: Vec<&T::AccountId> = self
+            .signatories
+            .iter()
+            .filter(|account| account.encode() != signatory)
+            .collect();
+        if other_signatories.len() == self.signatories.len() {
+            return Err(Error::Other(
+                "The signatory given is not one of the multisig signatories".into(),
+            ));
+        }
+
+        // Account IDs are chain specific, so we need the type that the call expects for them.
+        let other_signatories = call_argument_to_value(
+            "Multisig",
+            call_name,
+            "other_signatories",
+            &other_signatories,
+            metadata,
+        )?;
+
+        let timepoint = match timepoint {
+            Some(timepoint) => Value::unnamed_variant(
+                "Some",
+                [Value::named_composite([
+                    ("height", Value::u128(timepoint.height.into())),
+                    ("index", Value::u128(timepoint.index.into())),
+                ])],
+            ),
+            None => Value::unnamed_variant("None", []),
+        };
+        let max_weight = Value::named_composite([
+            ("ref_time", Value::u128(max_weight.ref_time.into())),
+            ("proof_size", Value::u128(max_weight.proof_size.into())),
+        ]);
+
+        Ok(dynamic(
+            "Multisig",
+            call_name.to_owned(),
+            Composite::named([
+                ("threshold", Value::u128(self.threshold.into())),
+                ("other_signatories", other_signatories),
+                ("maybe_timepoint", timepoint),
+                call,
+                ("max_weight", max_weight),
+            ]),
+        ))
+    }
+}
+
+impl<T, C> Multisig<T, C>
+where
+    T: Config,
+    T::AccountId: Decode + DecodeAsType,
+    C: OnlineClientT<T>,
+{
+    /// Fetch the pending operation for the call with the given hash from the latest
+    /// finalized block, if there is one.
+    pub async fn pending(
+        &self,
+        call_hash: [u8; 32],
+    ) -> Result<Option<PendingMultisig<T::AccountId>>, Error> {
+        let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        self.pending_at(call_hash, block_ref).await
+    }
+
+    /// Fetch every pending operation for this multisig account from the latest finalized
+    /// block, along with the hash of the call that each one will dispatch.
+    pub async fn pending_calls(
+        &self,
+    ) -> Result<Vec<([u8; 32], PendingMultisig<T::AccountId>)>, Error> {
+        let address = crate::storage::dynamic(
+            "Multisig",
+            "Multisigs",
+            StaticStorageKey::new(&self.account_id()?),
+        );
+        let storage = self.client.storage().at_latest().await?;
+
+        storage
+            .iter(address)
+            .await?
+            .map_err(Error::from)
+            .and_then(|kv| async move {
+                // The call hash is appended in full to the end of the storage key.
+                let call_hash = kv
+                    .key_bytes
+                    .get(kv.key_bytes.len().saturating_sub(32)..)
+                    .and_then(|hash| hash.try_into().ok())
+                    .ok_or_else(|| Error::Other("Unexpected Multisig::Multisigs key".into()))?;
+                Ok((call_hash, kv.value.as_type()?))
+            })
+            .try_collect()
+            .await
+    }
+
+    /// Build a `Multisig::as_multi` call for the given signatory to approve the given call,
+    /// as in [`Multisig::as_multi_with()`]. The [`Timepoint`] is looked up from the pending
+    /// operation if there is one, and `max_weight` is set to the weight of the call as
+    /// reported by the runtime.
+    pub async fn as_multi<Call: Payload>(
+        &self,
+        signatory: &T::AccountId,
+        call: &Call,
+    ) -> Result<DynamicPayload, Error> {
+        let block_ref = self.client.backend().latest_finalized_block_ref().await?;
+        let metadata = self.client.metadata();
+        let call_hash = self.call_hash(call)?;
+
+        let pending = self.pending_at(call_hash, block_ref.clone()).await?;
+        let runtime_api = self.client.runtime_api().at(block_ref);
+        let max_weight = super::tx_batch::query_weight(&runtime_api, call, &metadata).await?;
+
+        self.as_multi_with(signatory, call, pending.map(|p| p.when), max_weight)
+    }
+
+    /// Build a `Multisig::approve_as_multi` call for the given signatory to approve the call
+    /// with the given hash, as in [`Multisig::approve_as_multi_with()`]. The [`Timepoint`] is
+    /// looked up from the pending operation if there is one. Since the call is never dispatched
+    /// by `approve_as_multi`, `max_weight` is set to zero.
+    pub async fn approve_as_multi(
+        &self,
+        signatory: &T::AccountId,
+        call_hash: [u8; 32],
+    ) -> Result<DynamicPayload, Error> {
+        let pending = self.pending(call_hash).await?;
+        self.approve_as_multi_with(signatory, call_hash, pending.map(|p| p.when), Weight::ZERO)
+    }
+
+    async fn pending_at(
+        &self,
+        call_hash: [u8; 32],
+        block_ref: BlockRef<T::Hash>,
+    ) -> Result<Option<PendingMultisig<T::AccountId>>, Error> {
+        let address = crate::storage::dynamic(
+            "Multisig",
+            "Multisigs",
+            (
+                StaticStorageKey::new(&self.account_id()?),
+                StaticStorageKey::new(&call_hash),
+            ),
+        );
+        let pending = self.client.storage().at(block_ref).fetch(&address).await?;
+        Ok(pending.map(|pending| pending.as_type()).transpose()?)
+    }
+}
+
+/// Input that reads the bytes given, followed by an infinite number of zeroes. This allows
+/// account IDs of any length to be decoded from a hash, as `pallet_multisig` does.
+struct TrailingZeroInput<'a>(&'a [u8]);
+
+impl codec::Input for TrailingZeroInput<'_> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, codec::Error> {
+        Ok(None)
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), codec::Error> {
+        let len = into.len().min(self.0.len());
+        into[..len].copy_from_slice(&self.0[..len]);
+        into[len..].fill(0);
+        self.0 = &self.0[len..];
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::mock::{test_header, MockBackend};
+    use crate::config::substrate::H256;
+    use crate::config::Header;
+    use crate::utils::AccountId32;
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Compact;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn builds_approvals_for_pending_multisig() {
+        let genesis_hash = test_header(0, H256::zero()).hash();
+
+        let alice = subxt_signer::sr25519::dev::alice()
+            .public_key()
+            .to_account_id();
+        let bob = subxt_signer::sr25519::dev::bob()
+            .public_key()
+            .to_account_id();
+        let charlie = subxt_signer::sr25519::dev::charlie()
+            .public_key()
+            .to_account_id();
+        let mut signatories = vec![alice.clone(), bob.clone(), charlie.clone()];
+        signatories.sort();
+
+        let multisig_account = AccountId32(sp_crypto_hashing::blake2_256(
+            &(b"modlpy/utilisuba", &signatories, 2u16).encode(),
+        ));
+        let call_hash = [7u8; 32];
+
+        // Alice has already approved the call at block 5, extrinsic 1.
+        let key = [
+            &sp_crypto_hashing::twox_128(b"Multisig")[..],
+            &sp_crypto_hashing::twox_128(b"Multisigs"),
+            &sp_crypto_hashing::twox_64(&multisig_account.0),
+            &multisig_account.0,
+            &sp_crypto_hashing::blake2_128(&call_hash),
+            &call_hash,
+        ]
+        .concat();
+        let value = ((5u32, 1u32), 100u128, &alice, vec![&alice]).encode();
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .storage_entry(genesis_hash, key, value)
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        // The signatories are sorted and deduplicated, and the account ID derived from them.
+        let multisig = api
+            .tx()
+            .multisig(
+                [charlie.clone(), alice.clone(), bob.clone(), alice.clone()],
+                2,
+            )
+            .unwrap();
+        assert_eq!(multisig.signatories(), &signatories[..]);
+        assert_eq!(multisig.account_id().unwrap(), multisig_account);
+
+        let expected = PendingMultisig {
+            when: Timepoint {
+                height: 5,
+                index: 1,
+            },
+            deposit: 100,
+            depositor: alice.clone(),
+            approvals: vec![alice.clone()],
+        };
+        assert_eq!(
+            multisig.pending(call_hash).await.unwrap(),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            multisig.pending_calls().await.unwrap(),
+            vec![(call_hash, expected)]
+        );
+        assert_eq!(multisig.pending([0u8; 32]).await.unwrap(), None);
+
+        // Bob's approval refers back to the timepoint of Alice's approval.
+        let payload = multisig.approve_as_multi(&bob, call_hash).await.unwrap();
+        let metadata = api.metadata();
+        let pallet = metadata.pallet_by_name("Multisig").unwrap();
+        let call_index = pallet
+            .call_variant_by_name("approve_as_multi")
+            .unwrap()
+            .index;
+        let other_signatories: Vec<_> = signatories.iter().filter(|a| **a != bob).collect();
+        let expected_call_data = (
+            pallet.index(),
+            call_index,
+            2u16,
+            other_signatories,
+            Some((5u32, 1u32)),
+            call_hash,
+            (Compact(0u64), Compact(0u64)),
+        )
+            .encode();
+        assert_eq!(
+            payload.encode_call_data(&metadata).unwrap(),
+            expected_call_data
+        );
+
+        // Only signatories can approve.
+        let dave = subxt_signer::sr25519::dev::dave()
+            .public_key()
+            .to_account_id();
+        assert!(multisig.approve_as_multi(&dave, call_hash).await.is_err());
+
+        // Thresholds must be between 2 and the number of (distinct) signatories.
+        for threshold in [0, 1, 4] {
+            let signatories = [alice.clone(), bob.clone(), charlie.clone(), alice.clone()];
+            assert!(api.tx().multisig(signatories, threshold).is_err());
+        }
+        assert!(api.tx().multisig(signatories.clone(), 3).is_ok());
+    }
+}
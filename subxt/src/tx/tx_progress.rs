@@ -259,19 +259,45 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
     /// You can use [`TxInBlock::fetch_events`] instead if you'd like to
     /// work with multiple "error" events.
     ///
+    /// **Note:** If the transaction is a `Proxy::proxy` call (see [`crate::tx::ProxySigner`]),
+    /// the transaction succeeds even if the proxied call fails, and so we also return an
+    /// error if a `ProxyExecuted` event reports that the proxied call failed.
+    ///
     /// **Note:** This has to download block details from the node and decode events
     /// from them.
     pub async fn wait_for_success(&self) -> Result<crate::blocks::ExtrinsicEvents<T>, Error> {
-        let events = self.fetch_events().await?;
+        let (events, extrinsic_bytes) = self.fetch_events_and_extrinsic().await?;
+        let metadata = self.client.metadata();
+
+        let is_proxy_call = {
+            let extrinsics =
+                subxt_core::blocks::decode_from::<T>(vec![extrinsic_bytes], metadata.clone())
+                    .map_err(|e| Error::Block(e.into()))?;
+            match extrinsics.iter().next() {
+                Some(ext) => {
+                    let ext = ext?;
+                    ext.pallet_name()? == "Proxy" && ext.variant_name()? == "proxy"
+                }
+                None => false,
+            }
+        };
 
         // Try to find any errors; return the first one we encounter.
         for ev in events.iter() {
             let ev = ev?;
             if ev.pallet_name() == "System" && ev.variant_name() == "ExtrinsicFailed" {
                 let dispatch_error =
-                    DispatchError::decode_from(ev.field_bytes(), self.client.metadata())?;
+                    DispatchError::decode_from(ev.field_bytes(), metadata.clone())?;
                 return Err(dispatch_error.into());
             }
+            if is_proxy_call && ev.pallet_name() == "Proxy" && ev.variant_name() == "ProxyExecuted"
+            {
+                // The only field is a `DispatchResult`, which is an error if the first byte is 1.
+                if let [1, error_bytes @ ..] = ev.field_bytes() {
+                    let dispatch_error = DispatchError::decode_from(error_bytes, metadata.clone())?;
+                    return Err(dispatch_error.into());
+                }
+            }
         }
 
         Ok(events)
@@ -284,7 +310,16 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
     /// **Note:** This has to download block details from the node and decode events
     /// from them.
     pub async fn fetch_events(&self) -> Result<crate::blocks::ExtrinsicEvents<T>, Error> {
-        let block_body = self
+        let (events, _) = self.fetch_events_and_extrinsic().await?;
+        Ok(events)
+    }
+
+    /// Fetch the events associated with this transaction, along with the bytes of the
+    /// extrinsic itself.
+    async fn fetch_events_and_extrinsic(
+        &self,
+    ) -> Result<(crate::blocks::ExtrinsicEvents<T>, Vec<u8>), Error> {
+        let mut block_body = self
             .client
             .backend()
             .block_body(self.block_ref.hash())
@@ -309,11 +344,9 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
             .at(self.block_ref.clone())
             .await?;
 
-        Ok(crate::blocks::ExtrinsicEvents::new(
-            self.ext_hash,
-            extrinsic_idx as u32,
-            events,
-        ))
+        let events =
+            crate::blocks::ExtrinsicEvents::new(self.ext_hash, extrinsic_idx as u32, events);
+        Ok((events, block_body.swap_remove(extrinsic_idx)))
     }
 }
 
@@ -405,6 +438,114 @@ mod test {
         ));
     }
 
+    #[cfg(feature = "mock-backend")]
+    #[tokio::test]
+    async fn proxies_calls_and_decodes_proxy_executed() {
+        use crate::backend::mock::{test_header, MockBackend};
+        use crate::backend::BlockRef;
+        use crate::config::substrate::H256;
+        use crate::config::Header;
+        use crate::dynamic::Value;
+        use crate::error::DispatchError;
+        use crate::events::Phase;
+        use crate::tx::{ProxySigner, TxInBlock};
+        use crate::{Metadata, OnlineClient};
+        use codec::{Decode, Encode};
+        use std::sync::Arc;
+
+        // The small metadata has no `Proxy` pallet.
+        let metadata_bytes = std::fs::read("../artifacts/polkadot_metadata_full.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*metadata_bytes).unwrap();
+        let block0 = test_header(0, H256::zero());
+        let block1 = test_header(1, block0.hash());
+
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata_bytes.clone())
+            .block(block0.clone(), vec![])
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        // Alice signs on behalf of Bob, so the call is wrapped in `Proxy::proxy`.
+        let bob = subxt_signer::sr25519::dev::bob()
+            .public_key()
+            .to_account_id();
+        let signer = ProxySigner::new(subxt_signer::sr25519::dev::alice(), bob.clone())
+            .force_proxy_type("Any");
+        let remark = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signed = api
+            .tx()
+            .create_signed(&remark, &signer, Default::default())
+            .await
+            .unwrap();
+        // Partial extrinsics built for the signer are wrapped in the same way.
+        let partial = api
+            .tx()
+            .create_partial_signed_for(&remark, &signer, Default::default())
+            .await
+            .unwrap();
+        let partial_offline = api
+            .tx()
+            .create_partial_signed_offline_for(&remark, &signer, Default::default())
+            .unwrap();
+
+        let proxy = metadata.pallet_by_name("Proxy").unwrap();
+        let mut proxy_call = vec![
+            proxy.index(),
+            proxy.call_variant_by_name("proxy").unwrap().index,
+        ];
+        // MultiAddress::Id(bob), Some(ProxyType::Any), then the remark call.
+        proxy_call.push(0);
+        bob.encode_to(&mut proxy_call);
+        proxy_call.extend([1, 0]);
+        proxy_call.extend(api.tx().call_data(&remark).unwrap());
+        assert!(signed
+            .encoded()
+            .windows(proxy_call.len())
+            .any(|w| w == proxy_call));
+        assert_eq!(partial.call_data(), proxy_call);
+        assert_eq!(partial_offline.call_data(), proxy_call);
+        assert!(partial.sign(&signer).encoded().ends_with(&proxy_call));
+
+        // The extrinsic succeeds, but the proxied call fails with `BadOrigin`.
+        let mut events = vec![1u8 << 2];
+        Phase::ApplyExtrinsic(0).encode_to(&mut events);
+        let proxy_event_idx = proxy
+            .event_variants()
+            .unwrap()
+            .iter()
+            .find(|v| v.name == "ProxyExecuted")
+            .unwrap()
+            .index;
+        events.extend([proxy.index(), proxy_event_idx, 1, 2]);
+        Vec::<H256>::new().encode_to(&mut events);
+
+        let events_key = [
+            sp_crypto_hashing::twox_128(b"System"),
+            sp_crypto_hashing::twox_128(b"Events"),
+        ]
+        .concat();
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .metadata(metadata_bytes)
+            .block(block0, vec![])
+            .block(block1.clone(), vec![signed.encoded().to_vec()])
+            .storage_entry(block1.hash(), events_key, events)
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend))
+            .await
+            .unwrap();
+
+        let tx_in_block = TxInBlock::new(BlockRef::from_hash(block1.hash()), signed.hash(), api);
+        assert!(tx_in_block.fetch_events().await.is_ok());
+        // The extrinsic is a proxy call, so the failure of the proxied call is noticed.
+        assert!(matches!(
+            tx_in_block.wait_for_success().await,
+            Err(Error::Runtime(DispatchError::BadOrigin))
+        ));
+    }
+
     fn mock_tx_progress(statuses: Vec<MockSubstrateTxStatus>) -> MockTxProgress {
         let sub = create_substrate_tx_status_subscription(statuses);
         TxProgress::new(sub, MockClient, Default::default())
@@ -0,0 +1,528 @@
+// Copyright 2019-2024 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Sign and submit a transaction, automatically re-signing and resubmitting it if
+//! it's dropped, deemed invalid, or not finalized quickly enough.
+
+use crate::{
+    backend::{BlockRef, StreamOf, StreamOfResults},
+    client::OnlineClientT,
+    config::{Config, ExtrinsicParams, Header},
+    error::{Error, RpcError, TransactionError},
+    tx::{Payload, Signer as SignerT, TxClient, TxInBlock, TxProgress, TxStatus},
+};
+use derive_where::derive_where;
+use futures::{future::Either, Stream, StreamExt};
+use std::task::Poll;
+
+/// Configure when a transaction should be resubmitted, and when to give up on it.
+/// Use with [`crate::tx::TxClient::sign_and_submit_then_watch_with_resubmission()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResubmitPolicy {
+    max_attempts: u32,
+    resubmit_after_blocks: Option<u64>,
+    give_up_after_blocks: Option<u64>,
+}
+
+impl Default for ResubmitPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            resubmit_after_blocks: None,
+            give_up_after_blocks: None,
+        }
+    }
+}
+
+impl ResubmitPolicy {
+    /// Create a new [`ResubmitPolicy`]. By default, a transaction is submitted at most
+    /// 3 times, and is only resubmitted if it's dropped or deemed invalid.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of times that the transaction will be submitted, including
+    /// the first submission.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Re-sign and resubmit the transaction if it has not been finalized within this many
+    /// finalized blocks of being submitted. Set this to the mortality period of the transaction
+    /// in order to resubmit it once its mortal era expires.
+    pub fn resubmit_after_blocks(mut self, blocks: u64) -> Self {
+        self.resubmit_after_blocks = Some(blocks);
+        self
+    }
+
+    /// Give up on the transaction if it has not been finalized within this many finalized
+    /// blocks of first being submitted, regardless of how many attempts have been made.
+    pub fn give_up_after_blocks(mut self, blocks: u64) -> Self {
+        self.give_up_after_blocks = Some(blocks);
+        self
+    }
+
+    fn needs_finalized_blocks(&self) -> bool {
+        self.resubmit_after_blocks.is_some() || self.give_up_after_blocks.is_some()
+    }
+}
+
+/// Events emitted by [`ResubmitProgress`] as a transaction is submitted and resubmitted.
+#[derive_where(Debug; C)]
+pub enum ResubmitEvent<T: Config, C> {
+    /// The transaction was signed and submitted.
+    Submitted {
+        /// Which attempt this is, starting from 1.
+        attempt: u32,
+        /// The hash of the extrinsic that was submitted.
+        ext_hash: T::Hash,
+    },
+    /// The status of the currently submitted transaction changed. If the status is
+    /// [`TxStatus::Dropped`], [`TxStatus::Invalid`] or [`TxStatus::Error`], then the
+    /// transaction will be resubmitted, if possible.
+    Status {
+        /// The attempt that this status relates to.
+        attempt: u32,
+        /// The new status.
+        status: TxStatus<T, C>,
+    },
+    /// The currently submitted transaction was not finalized within the number of blocks
+    /// given by [`ResubmitPolicy::resubmit_after_blocks()`], and so will be resubmitted,
+    /// if possible.
+    Stalled {
+        /// The attempt that stalled.
+        attempt: u32,
+    },
+    /// The status updates for the currently submitted transaction ended before it reached
+    /// a final status, and so it will be resubmitted, if possible.
+    SubscriptionEnded {
+        /// The attempt whose status updates ended.
+        attempt: u32,
+    },
+}
+
+/// This struct represents the progress of a transaction which may be resubmitted a number of
+/// times, according to some [`ResubmitPolicy`]. It's a stream of [`ResubmitEvent`]s, which ends
+/// once the transaction is finalized, or with an error if we give up on it.
+pub struct ResubmitProgress<T: Config, C> {
+    inner: StreamOf<Result<ResubmitEvent<T, C>, Error>>,
+}
+
+impl<T: Config, C> std::fmt::Debug for ResubmitProgress<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResubmitProgress")
+            .field("inner", &"<stream>")
+            .finish()
+    }
+}
+
+impl<T: Config, C> Unpin for ResubmitProgress<T, C> {}
+
+impl<T: Config, C> Stream for ResubmitProgress<T, C> {
+    type Item = Result<ResubmitEvent<T, C>, Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<T, C> ResubmitProgress<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    /// Return the next event when it's emitted. This just delegates to the
+    /// [`futures::Stream`] implementation for [`ResubmitProgress`], but allows you to
+    /// avoid importing that trait if you don't otherwise need it.
+    pub async fn next(&mut self) -> Option<Result<ResubmitEvent<T, C>, Error>> {
+        StreamExt::next(self).await
+    }
+
+    /// Wait for the transaction to be finalized, resubmitting it as needed, and return a
+    /// [`TxInBlock`] instance when it is, or an error if we gave up on it.
+    ///
+    /// **Note:** consumes `self`. If you'd like to observe each attempt as it happens,
+    /// use [`ResubmitProgress::next()`] instead.
+    pub async fn wait_for_finalized(mut self) -> Result<TxInBlock<T, C>, Error> {
+        while let Some(ev) = self.next().await {
+            if let ResubmitEvent::Status {
+                status: TxStatus::InFinalizedBlock(s),
+                ..
+            } = ev?
+            {
+                return Ok(s);
+            }
+        }
+        Err(RpcError::SubscriptionDropped.into())
+    }
+
+    /// Wait for the transaction to be finalized, resubmitting it as needed, and for the
+    /// transaction events to indicate that the transaction was successful.
+    ///
+    /// **Note:** consumes `self`. If you'd like to observe each attempt as it happens,
+    /// use [`ResubmitProgress::next()`] instead.
+    pub async fn wait_for_finalized_success(
+        self,
+    ) -> Result<crate::blocks::ExtrinsicEvents<T>, Error> {
+        let evs = self.wait_for_finalized().await?.wait_for_success().await?;
+        Ok(evs)
+    }
+}
+
+impl<T, C> ResubmitProgress<T, C>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+{
+    pub(crate) async fn new<Call, Signer, Params>(
+        client: C,
+        call: Call,
+        signer: Signer,
+        params: Params,
+        policy: ResubmitPolicy,
+    ) -> Result<Self, Error>
+    where
+        Call: Payload + Send + Sync + 'static,
+        Signer: SignerT<T> + Send + Sync + 'static,
+        Params: FnMut(u32) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::Params + Send + 'static,
+        <T::ExtrinsicParams as ExtrinsicParams<T>>::Params: Send,
+        T::AccountId: Send + Sync,
+    {
+        let finalized_blocks = if policy.needs_finalized_blocks() {
+            Some(client.backend().stream_finalized_block_headers().await?)
+        } else {
+            None
+        };
+
+        let state = ResubmitState {
+            tx_client: TxClient::new(client),
+            call,
+            signer,
+            params,
+            policy,
+            attempt: 0,
+            progress: None,
+            finalized_blocks,
+            first_block: None,
+            attempt_block: None,
+            done: false,
+        };
+
+        let inner = futures::stream::unfold(state, |state| state.step());
+        Ok(ResubmitProgress {
+            inner: StreamOf::new(Box::pin(inner)),
+        })
+    }
+}
+
+type FinalizedBlocks<T> = StreamOfResults<(<T as Config>::Header, BlockRef<<T as Config>::Hash>)>;
+
+struct ResubmitState<T: Config, C, Call, Signer, Params> {
+    tx_client: TxClient<T, C>,
+    call: Call,
+    signer: Signer,
+    params: Params,
+    policy: ResubmitPolicy,
+    // How many attempts have been made so far.
+    attempt: u32,
+    // The progress of the current attempt, if there is one in flight.
+    progress: Option<TxProgress<T, C>>,
+    finalized_blocks: Option<FinalizedBlocks<T>>,
+    // The first finalized block number seen since the first/current attempt.
+    first_block: Option<u64>,
+    attempt_block: Option<u64>,
+    done: bool,
+}
+
+// Whichever of the transaction progress or the finalized block stream yields first.
+enum Next<A, B> {
+    Status(Option<A>),
+    Block(Option<B>),
+}
+
+impl<T, C, Call, Signer, Params> ResubmitState<T, C, Call, Signer, Params>
+where
+    T: Config,
+    C: OnlineClientT<T>,
+    Call: Payload,
+    Signer: SignerT<T>,
+    Params: FnMut(u32) -> <T::ExtrinsicParams as ExtrinsicParams<T>>::Params,
+{
+    async fn step(mut self) -> Option<(Result<ResubmitEvent<T, C>, Error>, Self)> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let Some(progress) = &mut self.progress else {
+                // Nothing in flight, so (re)submit the transaction if we're allowed to.
+                if self.attempt >= self.policy.max_attempts {
+                    let err = TransactionError::MaxAttemptsReached(self.attempt);
+                    return Some(self.finish(Err(err.into())));
+                }
+
+                let params = (self.params)(self.attempt);
+                // Only borrow the fields we need, so that the future remains `Send`.
+                let submission = self
+                    .tx_client
+                    .create_signed(&self.call, &self.signer, params);
+                let progress = match async { submission.await?.submit_and_watch().await }.await {
+                    Ok(progress) => progress,
+                    Err(e) => return Some(self.finish(Err(e))),
+                };
+
+                self.attempt += 1;
+                self.attempt_block = None;
+                let ev = ResubmitEvent::Submitted {
+                    attempt: self.attempt,
+                    ext_hash: progress.extrinsic_hash(),
+                };
+                self.progress = Some(progress);
+                return Some((Ok(ev), self));
+            };
+
+            let next = match &mut self.finalized_blocks {
+                Some(blocks) => {
+                    match futures::future::select(
+                        StreamExt::next(progress),
+                        StreamExt::next(blocks),
+                    )
+                    .await
+                    {
+                        Either::Left((status, _)) => Next::Status(status),
+                        Either::Right((block, _)) => Next::Block(block),
+                    }
+                }
+                None => Next::Status(StreamExt::next(progress).await),
+            };
+
+            match next {
+                Next::Status(Some(Ok(status))) => {
+                    match &status {
+                        TxStatus::InFinalizedBlock(_) => self.done = true,
+                        TxStatus::Dropped { .. }
+                        | TxStatus::Invalid { .. }
+                        | TxStatus::Error { .. } => self.progress = None,
+                        _ => {}
+                    }
+                    let ev = ResubmitEvent::Status {
+                        attempt: self.attempt,
+                        status,
+                    };
+                    return Some((Ok(ev), self));
+                }
+                Next::Status(Some(Err(e))) => return Some(self.finish(Err(e))),
+                // The subscription ended without a final status; try again.
+                Next::Status(None) => {
+                    self.progress = None;
+                    let ev = ResubmitEvent::SubscriptionEnded {
+                        attempt: self.attempt,
+                    };
+                    return Some((Ok(ev), self));
+                }
+                Next::Block(Some(Ok((header, _)))) => {
+                    let number: u64 = header.number().into();
+                    let first_block = *self.first_block.get_or_insert(number);
+                    let attempt_block = *self.attempt_block.get_or_insert(number);
+
+                    if let Some(limit) = self.policy.give_up_after_blocks {
+                        if number.saturating_sub(first_block) >= limit {
+                            let err = TransactionError::BlockLimitReached(limit);
+                            return Some(self.finish(Err(err.into())));
+                        }
+                    }
+                    if let Some(limit) = self.policy.resubmit_after_blocks {
+                        if number.saturating_sub(attempt_block) >= limit {
+                            self.progress = None;
+                            let ev = ResubmitEvent::Stalled {
+                                attempt: self.attempt,
+                            };
+                            return Some((Ok(ev), self));
+                        }
+                    }
+                }
+                Next::Block(Some(Err(e))) => return Some(self.finish(Err(e))),
+                // No more blocks, so we can no longer apply any block limits.
+                Next::Block(None) => self.finalized_blocks = None,
+            }
+        }
+    }
+
+    fn finish(
+        mut self,
+        res: Result<ResubmitEvent<T, C>, Error>,
+    ) -> (Result<ResubmitEvent<T, C>, Error>, Self) {
+        self.done = true;
+        (res, self)
+    }
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod test {
+    use super::*;
+    use crate::backend::{mock::MockBackend, TransactionStatus};
+    use crate::config::substrate::H256;
+    use crate::dynamic::Value;
+    use crate::{OnlineClient, SubstrateConfig};
+    use codec::Encode;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn resubmits_dropped_transactions() {
+        let dropped = || TransactionStatus::Dropped {
+            message: "dropped".into(),
+        };
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            .transaction_statuses([TransactionStatus::Validated, dropped()])
+            .transaction_statuses([
+                TransactionStatus::Validated,
+                TransactionStatus::InFinalizedBlock {
+                    hash: BlockRef::from_hash(H256::zero()),
+                },
+            ])
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let mut progress = api
+            .tx()
+            .sign_and_submit_then_watch_with_resubmission(
+                call,
+                signer,
+                |_| Default::default(),
+                ResubmitPolicy::new(),
+            )
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(ev) = progress.next().await {
+            events.push(ev.unwrap());
+        }
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                ResubmitEvent::Submitted { attempt: 1, .. },
+                ResubmitEvent::Status {
+                    status: TxStatus::Validated,
+                    ..
+                },
+                ResubmitEvent::Status {
+                    status: TxStatus::Dropped { .. },
+                    ..
+                },
+                ResubmitEvent::Submitted { attempt: 2, .. },
+                ResubmitEvent::Status {
+                    status: TxStatus::Validated,
+                    ..
+                },
+                ResubmitEvent::Status {
+                    attempt: 2,
+                    status: TxStatus::InFinalizedBlock(_),
+                },
+            ]
+        ));
+        assert_eq!(backend.submitted_transactions().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let dropped = || TransactionStatus::Dropped {
+            message: "dropped".into(),
+        };
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            .transaction_statuses([dropped()])
+            .transaction_statuses([dropped()])
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let err = api
+            .tx()
+            .sign_and_submit_then_watch_with_resubmission(
+                call,
+                signer,
+                |_| Default::default(),
+                ResubmitPolicy::new().max_attempts(2),
+            )
+            .await
+            .unwrap()
+            .wait_for_finalized()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Transaction(TransactionError::MaxAttemptsReached(2))
+        ));
+        assert_eq!(backend.submitted_transactions().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reports_status_updates_ending_early() {
+        let backend = MockBackend::<SubstrateConfig>::builder()
+            .with_genesis_and_small_metadata()
+            .runtime_api_call("AccountNonceApi_account_nonce", 0u32.encode())
+            // The first submission's status updates end without a final status.
+            .transaction_statuses([TransactionStatus::Validated])
+            .transaction_statuses([TransactionStatus::InFinalizedBlock {
+                hash: BlockRef::from_hash(H256::zero()),
+            }])
+            .build();
+        let api = OnlineClient::<SubstrateConfig>::from_backend(Arc::new(backend.clone()))
+            .await
+            .unwrap();
+
+        let call = crate::dynamic::tx("System", "remark", vec![Value::from_bytes("hi")]);
+        let signer = subxt_signer::sr25519::dev::alice();
+        let mut progress = api
+            .tx()
+            .sign_and_submit_then_watch_with_resubmission(
+                call,
+                signer,
+                |_| Default::default(),
+                ResubmitPolicy::new(),
+            )
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Some(ev) = progress.next().await {
+            events.push(ev.unwrap());
+        }
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                ResubmitEvent::Submitted { attempt: 1, .. },
+                ResubmitEvent::Status {
+                    status: TxStatus::Validated,
+                    ..
+                },
+                ResubmitEvent::SubscriptionEnded { attempt: 1 },
+                ResubmitEvent::Submitted { attempt: 2, .. },
+                ResubmitEvent::Status {
+                    attempt: 2,
+                    status: TxStatus::InFinalizedBlock(_),
+                },
+            ]
+        ));
+        assert_eq!(backend.submitted_transactions().len(), 2);
+    }
+}